@@ -1,7 +1,6 @@
-use anyhow::{anyhow, Result};
 use proc_macro::TokenStream;
 use quote::quote;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use syn::parse_macro_input;
 
 mod template;
@@ -9,90 +8,282 @@ mod tools;
 mod utils;
 
 use template::{
-    generate_current_version_struct, generate_impl_froms, generate_old_version_structs,
+    build_enum_schemas, build_struct_schemas, describe_fields, describe_variants,
+    generate_context_visitors, generate_current_version_struct, generate_impl_downgrades,
+    generate_impl_enum_downgrades, generate_impl_enum_froms, generate_impl_froms,
+    generate_impl_traits, generate_json_schema, generate_old_enum_versions,
+    generate_old_version_attrs, generate_old_version_container_attrs, generate_old_version_docs,
+    generate_old_version_enums, generate_old_version_structs, generate_old_versions,
+    generate_specta, generate_specta_derives, generate_xml_schema, StructVisibility,
 };
-use tools::{DeriveVersion, Migration};
+use tools::{
+    DeriveVersion, FieldKey, Migration, MigrationData, MigrationGraph, VariantData, VersionRepr,
+};
+use utils::{canonicalize_versions, check_version_idents};
 
 #[proc_macro_attribute]
 pub fn version(attr: TokenStream, input: TokenStream) -> TokenStream {
     let attr: DeriveVersion = parse_macro_input!(attr);
     let input: Migration = parse_macro_input!(input);
 
-    let version_string = attr.get_version();
+    expand(attr, input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let final_struct_fields = input
-        .struct_data
-        .fields
-        .iter()
-        .map(|field| match field {
-            syn::Field {
-                ident: Some(ident),
-                ty,
-                ..
-            } => Ok((ident.clone(), ty.clone())),
-            _ => Err(anyhow!("Failed to get field ident")),
-        })
-        .collect::<Vec<Result<_>>>()
-        .into_iter()
-        .collect::<Result<Vec<_>>>()
-        .expect("Failed to get field ident")
-        .into_iter()
-        .collect::<BTreeMap<_, _>>();
-    let versions = {
-        let mut temp_version = version_string.clone();
-        let mut ret = vec![];
-
-        while let Some(item) = input
+fn expand(attr: DeriveVersion, mut input: Migration) -> syn::Result<proc_macro2::TokenStream> {
+    let version_string = attr.get_version();
+    canonicalize_versions(&version_string, &mut input.versions);
+    if let Some(option) = &attr.preserve_unknown {
+        input.data.add_unknown_fields(option)?;
+    }
+    if attr.context.is_none() {
+        let named = input
             .versions
             .iter()
-            .find(|item| item.to.value() == temp_version)
-        {
-            ret.push((item.from.value(), item.changes.clone(), item.to.value()));
-            temp_version = item.from.value();
+            .flat_map(|item| item.converters())
+            .find_map(|converter| converter.context.as_ref());
+        if let Some((name, _)) = named {
+            return Err(syn::Error::new_spanned(
+                name,
+                "A converter can only name the migration context of a type declared with `context(...)`",
+            ));
         }
-        ret
-    };
+    }
 
-    let old_version_structs = generate_old_version_structs(
-        input.struct_data.ident.clone(),
-        version_string.clone(),
-        final_struct_fields.clone(),
-        input
-            .extra_macros
-            .iter()
+    let ident = input.data.ident().clone();
+    let generics = input.data.generics().clone();
+    let vis = input.data.vis().clone();
+    // Serde container attributes may differ between versions, the others are copied
+    // onto every historical version as they are
+    let (container_attrs, extra_macros): (Vec<_>, Vec<_>) = input
+        .extra_macros
+        .iter()
+        .partition(|(key, _)| key == "serde");
+    let [container_attrs, mut extra_macros] = [container_attrs, extra_macros].map(|attrs| {
+        attrs
+            .into_iter()
             .map(|(key, value)| {
                 quote! {
                     #[#key #value]
                 }
             })
-            .collect::<Vec<_>>(),
-        versions.clone(),
-    )
-    .expect("Failed to generate old version structs");
-
-    // Confirm the order of the versions
-    let impl_versions = generate_impl_froms(
-        input.struct_data.ident.clone(),
-        version_string.clone(),
-        final_struct_fields.clone(),
-        input.versions.clone(),
-    )
-    .expect("Failed to generate impl froms");
+            .collect::<Vec<_>>()
+    });
+
+    // Every template below follows this graph, so it is checked once up front
+    let graph = MigrationGraph::resolve(&version_string, &input.versions)?;
+    check_version_idents(&ident, &graph)?;
+    let mut container_attrs = generate_old_version_container_attrs(container_attrs, &graph)
+        .map_err(|error| syn::Error::new(ident.span(), error))?;
+    if attr.specta {
+        extra_macros.extend(generate_specta_derives(&input.extra_macros)?);
+    }
+    let span = ident.span();
+    // Errors raised at a specific literal keep their span
+    let error = |error: anyhow::Error| {
+        error
+            .downcast::<syn::Error>()
+            .unwrap_or_else(|error| syn::Error::new(span, error))
+    };
+
+    let context = attr.context.as_ref();
+
+    // The shape of every version as it is read, for the schema generators, and the
+    // versions read by a visitor that hands on the migration context
+    let (old_version_items, impl_versions, schemas, visited) = match &input.data {
+        MigrationData::Struct(struct_data) => {
+            let final_struct_fields = struct_data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| match &field.ident {
+                    Some(ident) => (FieldKey::Named(ident.clone()), field.ty.clone()),
+                    None => (FieldKey::Index(index as u32), field.ty.clone()),
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            let final_field_attrs = struct_data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| match &field.ident {
+                    Some(ident) => (FieldKey::Named(ident.clone()), field.attrs.clone()),
+                    None => (FieldKey::Index(index as u32), field.attrs.clone()),
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            let visibility = StructVisibility {
+                vis: &vis,
+                fields: struct_data
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field)| match &field.ident {
+                        Some(ident) => (FieldKey::Named(ident.clone()), field.vis.clone()),
+                        None => (FieldKey::Index(index as u32), field.vis.clone()),
+                    })
+                    .collect(),
+            };
+
+            let old_versions = generate_old_versions(final_struct_fields, &graph).map_err(error)?;
+            generate_old_version_docs(
+                &ident,
+                &graph,
+                &describe_fields(&old_versions),
+                &mut container_attrs,
+            )
+            .map_err(error)?;
+            let old_version_attrs =
+                generate_old_version_attrs(final_field_attrs, &graph).map_err(error)?;
+            let schemas = build_struct_schemas(&old_versions, &old_version_attrs, &container_attrs)
+                .map_err(error)?;
+            let (context_visitors, visited) = generate_context_visitors(
+                &ident,
+                &generics,
+                &old_versions,
+                &old_version_attrs,
+                &container_attrs,
+                context,
+            )
+            .map_err(error)?;
+
+            let old_version_structs = generate_old_version_structs(
+                ident.clone(),
+                generics.clone(),
+                &visibility,
+                &old_versions,
+                &old_version_attrs,
+                extra_macros,
+                &container_attrs,
+            )
+            .map_err(error)?;
+
+            let old_versions = old_versions.into_iter().collect::<BTreeMap<_, _>>();
+            let impl_versions = generate_impl_froms(
+                ident.clone(),
+                generics.clone(),
+                version_string.clone(),
+                &old_versions,
+                &graph,
+                context,
+            )
+            .map_err(error)?;
+
+            let impl_downgrades =
+                generate_impl_downgrades(ident.clone(), generics.clone(), &old_versions, &graph)
+                    .map_err(error)?;
+
+            (
+                quote! {
+                    #old_version_structs
+                    #context_visitors
+                },
+                quote! {
+                    #impl_versions
+                    #impl_downgrades
+                },
+                schemas,
+                visited,
+            )
+        }
+        MigrationData::Enum(enum_data) => {
+            let final_enum_variants = enum_data
+                .variants
+                .iter()
+                .map(|variant| (variant.ident.clone(), VariantData::from_variant(variant)))
+                .collect::<BTreeMap<_, _>>();
+
+            let old_versions =
+                generate_old_enum_versions(final_enum_variants, &graph).map_err(error)?;
+            generate_old_version_docs(
+                &ident,
+                &graph,
+                &describe_variants(&old_versions),
+                &mut container_attrs,
+            )
+            .map_err(error)?;
+            let schemas = build_enum_schemas(&old_versions, &container_attrs).map_err(error)?;
+
+            let old_version_enums = generate_old_version_enums(
+                ident.clone(),
+                generics.clone(),
+                &vis,
+                &old_versions,
+                extra_macros,
+                &container_attrs,
+            )
+            .map_err(error)?;
+
+            let old_versions = old_versions.into_iter().collect::<BTreeMap<_, _>>();
+            let impl_versions = generate_impl_enum_froms(
+                ident.clone(),
+                generics.clone(),
+                version_string.clone(),
+                &old_versions,
+                &graph,
+                context,
+            )
+            .map_err(error)?;
+
+            let impl_downgrades = generate_impl_enum_downgrades(
+                ident.clone(),
+                generics.clone(),
+                &old_versions,
+                &graph,
+            )
+            .map_err(error)?;
+
+            (
+                old_version_enums,
+                quote! {
+                    #impl_versions
+                    #impl_downgrades
+                },
+                schemas,
+                BTreeSet::new(),
+            )
+        }
+    };
+
+    let json_schema = attr
+        .json_schema
+        .then(|| generate_json_schema(&attr, &ident, &generics, &schemas))
+        .transpose()
+        .map_err(error)?;
+    // The version is the namespace of the root element unless `repr = "xml"` puts it
+    // in another attribute
+    let version_attribute =
+        (matches!(attr.repr, VersionRepr::Xml) && attr.tag != "xmlns").then_some(attr.tag.as_str());
+    let xml_schema = attr
+        .xml_schema
+        .then(|| generate_xml_schema(&ident, &generics, &schemas, version_attribute))
+        .transpose()
+        .map_err(error)?;
+    let specta = attr
+        .specta
+        .then(|| generate_specta(&attr, &ident, &generics, &schemas))
+        .transpose()
+        .map_err(error)?;
+    let impl_traits = generate_impl_traits(ident.clone(), generics, &graph).map_err(error)?;
 
     let current_version_struct = generate_current_version_struct(
         attr.clone(),
         input.clone(),
-        input.struct_data.ident.clone(),
-        version_string.clone(),
-        final_struct_fields,
-        versions,
+        ident,
+        &graph,
+        &container_attrs[&graph.current],
+        &visited,
     )
-    .expect("Failed to generate current version struct");
+    .map_err(error)?;
 
-    quote! {
+    Ok(quote! {
         #current_version_struct
-        #old_version_structs
+        #old_version_items
         #impl_versions
-    }
-    .into()
+        #impl_traits
+        #json_schema
+        #xml_schema
+        #specta
+    })
 }
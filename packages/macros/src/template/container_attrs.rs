@@ -0,0 +1,251 @@
+use anyhow::{anyhow, Result};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use std::collections::BTreeMap;
+use syn::{Ident, Type};
+
+use crate::tools::{
+    FieldKey, MigrationField, MigrationGraph, MigrationVariant, VariantData, VariantShape,
+};
+
+/// Infers the `#[serde(...)]` container attributes of every version along its route.
+///
+/// A version keeps the attributes of the next version of its route, unless the
+/// migration block leading there declares its own with inner attributes such as
+/// `#![serde(rename_all = "snake_case")]`.
+pub(crate) fn generate_old_version_container_attrs(
+    final_container_attrs: Vec<TokenStream>,
+    graph: &MigrationGraph,
+) -> Result<BTreeMap<String, Vec<TokenStream>>> {
+    let mut old_version_attrs = BTreeMap::from([(graph.current.clone(), final_container_attrs)]);
+
+    for version in graph.versions.iter().skip(1) {
+        let item = graph
+            .route(version)
+            .ok_or_else(|| anyhow!("Cannot find the route of version {}", version))?;
+
+        let container_attrs = if item.attrs.is_empty() {
+            old_version_attrs[&item.to.value()].clone()
+        } else {
+            item.attrs
+                .iter()
+                .map(|attr| {
+                    let meta = &attr.meta;
+                    quote! { #[#meta] }
+                })
+                .collect()
+        };
+        old_version_attrs.insert(version.clone(), container_attrs);
+    }
+
+    Ok(old_version_attrs)
+}
+
+/// The changes a migration makes to the fields, one line each, with the fields of
+/// a migrated variant named with `prefix` in front.
+fn describe_changes(changes: &[MigrationField], prefix: &str) -> Vec<String> {
+    let name = |key: &FieldKey| format!("`{}{}`", prefix, key);
+    let names = |source: &[(FieldKey, Type)]| {
+        source
+            .iter()
+            .map(|(key, _)| name(key))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    changes
+        .iter()
+        .map(|rule| match rule {
+            MigrationField::Add {
+                value,
+                converter: None,
+            } => format!("{} is added with its default value", name(&value.0)),
+            MigrationField::Add { value, .. } => {
+                format!("{} is added by a converter", name(&value.0))
+            }
+            MigrationField::Remove { value, .. } => format!("{} is removed", name(&value.0)),
+            MigrationField::Rename {
+                source,
+                target,
+                converter: None,
+                ..
+            } if source.len() == 1 => {
+                format!("{} is renamed to {}", names(source), name(&target.0))
+            }
+            MigrationField::Copy {
+                source,
+                target,
+                converter: None,
+                ..
+            } if source.len() == 1 => {
+                format!("{} is copied into {}", names(source), name(&target.0))
+            }
+            MigrationField::Rename { source, target, .. }
+            | MigrationField::Copy { source, target, .. } => {
+                format!("{} is converted from {}", name(&target.0), names(source))
+            }
+        })
+        .collect()
+}
+
+/// A type as it is written, without the spaces `to_string` puts between tokens.
+fn type_name(ty: &Type) -> String {
+    ty.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" ,", ",")
+        .replace(" ;", ";")
+        .replace("& ", "&")
+}
+
+/// The fields of every version of a struct, as a list in its documentation.
+pub(crate) fn describe_fields(
+    versions: &[(String, BTreeMap<FieldKey, Type>)],
+) -> BTreeMap<String, String> {
+    versions
+        .iter()
+        .map(|(version, fields)| {
+            let doc = if fields.is_empty() {
+                "It has no fields.".to_string()
+            } else {
+                let fields = fields
+                    .iter()
+                    .map(|(key, ty)| format!("- `{}`: `{}`", key, type_name(ty)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("Fields:\n\n{}", fields)
+            };
+            (version.clone(), doc)
+        })
+        .collect()
+}
+
+/// The variants of every version of an enum, as a list in its documentation.
+pub(crate) fn describe_variants(
+    versions: &[(String, BTreeMap<Ident, VariantData>)],
+) -> BTreeMap<String, String> {
+    versions
+        .iter()
+        .map(|(version, variants)| {
+            let variants = variants
+                .iter()
+                .map(|(variant, data)| match &data.shape {
+                    VariantShape::Unit => format!("- `{}`", variant),
+                    VariantShape::Tuple(types) => {
+                        let types = types.iter().map(type_name).collect::<Vec<_>>();
+                        format!("- `{}({})`", variant, types.join(", "))
+                    }
+                    VariantShape::Named(fields) => {
+                        let fields = fields
+                            .iter()
+                            .map(|(key, ty)| format!("{}: {}", key, type_name(ty)))
+                            .collect::<Vec<_>>();
+                        format!("- `{} {{ {} }}`", variant, fields.join(", "))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            (version.clone(), format!("Variants:\n\n{}", variants))
+        })
+        .collect()
+}
+
+/// Documents every version with its fields or variants, given by `members`, and the
+/// changes made by the migration of its route.
+pub(crate) fn generate_old_version_docs(
+    ident: &Ident,
+    graph: &MigrationGraph,
+    members: &BTreeMap<String, String>,
+    container_attrs: &mut BTreeMap<String, Vec<TokenStream>>,
+) -> Result<()> {
+    for version in graph.versions.iter() {
+        let doc = match graph.route(version) {
+            Some(item) => {
+                let mut changes = describe_changes(&item.changes, "");
+                for rule in item.variants.iter() {
+                    match rule {
+                        MigrationVariant::Add { variant } => {
+                            changes.push(format!("variant `{}` is added", variant));
+                        }
+                        MigrationVariant::Remove {
+                            variant,
+                            converter: None,
+                            ..
+                        } => {
+                            changes.push(format!(
+                                "variant `{}` is removed in favor of the default",
+                                variant
+                            ));
+                        }
+                        MigrationVariant::Remove { variant, .. } => {
+                            changes
+                                .push(format!("variant `{}` is removed by a converter", variant));
+                        }
+                        MigrationVariant::Rename { source, target, .. } => {
+                            let source = source
+                                .iter()
+                                .map(|variant| format!("`{}`", variant))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            changes
+                                .push(format!("variant `{}` is renamed from {}", target, source));
+                        }
+                        MigrationVariant::Fields {
+                            variant,
+                            changes: fields,
+                        } => {
+                            changes.extend(describe_changes(fields, &format!("{}.", variant)));
+                        }
+                    }
+                }
+
+                let summary = format!(
+                    "Version `{}` of [`{}`], which migrates into version `{}`.\n\n{}",
+                    version,
+                    ident,
+                    item.to.value(),
+                    members[version]
+                );
+                if changes.is_empty() {
+                    summary
+                } else {
+                    let changes = changes
+                        .iter()
+                        .map(|change| format!("- {}", change))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    format!(
+                        "{}\n\nMigrating into version `{}`:\n\n{}",
+                        summary,
+                        item.to.value(),
+                        changes
+                    )
+                }
+            }
+            None => format!(
+                "Version `{}` of [`{}`], as it is serialized.\n\n{}",
+                version, ident, members[version]
+            ),
+        };
+
+        // Written as doc comments, since `specta::Type` declares a `doc` helper
+        // attribute that makes every `#[doc]` attribute on the item ambiguous
+        let doc = doc
+            .lines()
+            .map(|line| format!("/// {}\n", line))
+            .collect::<String>()
+            .parse::<TokenStream>()
+            .map_err(|error| anyhow!("Cannot document version {}: {}", version, error))?;
+        container_attrs
+            .entry(version.clone())
+            .or_default()
+            .push(doc);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,405 @@
+use anyhow::{anyhow, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{parse_quote, Attribute, GenericParam, Generics, Ident, LitStr, Type};
+
+use crate::{
+    tools::FieldKey,
+    utils::{
+        deserialize_generics, generate_ident, is_context_field, phantom_type, with_bound,
+        SerdeAttrs, SerdeDefault,
+    },
+};
+
+use super::schema_builder::{field_name, parse_container_attrs};
+
+/// A field of a version, as the generated visitor reads it.
+struct VisitedField<'a> {
+    key: &'a FieldKey,
+    ty: &'a Type,
+    name: String,
+    aliases: Vec<String>,
+    default: Option<SerdeDefault>,
+    skip: bool,
+    context: bool,
+}
+
+/// Implements `DeserializeInContext` for every historical version with a field
+/// marked `#[version(context)]`, through a visitor that hands the context on to
+/// those fields. Returns the versions it covers, which the current type reads
+/// through it instead of through their derived `Deserialize`.
+///
+/// The visitor follows the serde attributes that decide which key a field is read
+/// from and what a missing one is filled with, and the `unknown_fields` of
+/// `preserve_unknown`. Options that read a value through code of their own, like
+/// `with`, are rejected on these versions.
+pub(crate) fn generate_context_visitors(
+    ident: &Ident,
+    generics: &Generics,
+    old_versions: &[(String, BTreeMap<FieldKey, Type>)],
+    old_version_attrs: &BTreeMap<String, BTreeMap<FieldKey, Vec<Attribute>>>,
+    container_attrs: &BTreeMap<String, Vec<TokenStream>>,
+    context: Option<&Type>,
+) -> Result<(TokenStream, BTreeSet<String>)> {
+    let mut visitors = vec![];
+    let mut visited = BTreeSet::new();
+
+    for (version, fields) in old_versions {
+        let no_attrs = vec![];
+        let attrs = |key: &FieldKey| old_version_attrs[version].get(key).unwrap_or(&no_attrs);
+
+        let mut marked = None;
+        for key in fields.keys() {
+            if is_context_field(attrs(key))? {
+                marked = Some(key);
+            }
+        }
+        let Some(marked) = marked else {
+            continue;
+        };
+        let Some(context_ty) = context else {
+            let attr = attrs(marked)
+                .iter()
+                .find(|attr| attr.path().is_ident("version"))
+                .expect("the field is marked");
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`#[version(context)]` needs the migration context of a type declared with `context(...)`",
+            )
+            .into());
+        };
+
+        let container = parse_container_attrs(&container_attrs[version])?;
+        if let Some(custom) = &container.custom {
+            return Err(syn::Error::new_spanned(
+                custom,
+                format!(
+                    "Version {} of {} has fields read with the migration context, which cannot be combined with `{}`",
+                    version,
+                    ident,
+                    quote! { #custom }
+                ),
+            )
+            .into());
+        }
+
+        let mut visited_fields = vec![];
+        let mut unknown_fields = None;
+        for (key, ty) in fields {
+            let serde = SerdeAttrs::parse(attrs(key))?;
+            if let Some(custom) = &serde.custom {
+                return Err(syn::Error::new_spanned(
+                    custom,
+                    format!(
+                        "Field `{}` of version {} of {} cannot use `{}`, since the version has fields read with the migration context",
+                        key,
+                        version,
+                        ident,
+                        quote! { #custom }
+                    ),
+                )
+                .into());
+            }
+            // Only the keys kept by `preserve_unknown` can be flattened
+            if serde.flatten {
+                if !matches!(key, FieldKey::Named(name) if name == "unknown_fields") {
+                    return Err(anyhow!(
+                        "Field `{}` of version {} of {} cannot be flattened, since the version has fields read with the migration context",
+                        key,
+                        version,
+                        ident
+                    ));
+                }
+                unknown_fields = Some(key);
+                continue;
+            }
+
+            visited_fields.push(VisitedField {
+                key,
+                ty,
+                name: field_name(key, &serde, container.rename_all),
+                aliases: serde.aliases,
+                default: serde.default,
+                skip: serde.skip,
+                context: is_context_field(attrs(key))?,
+            });
+        }
+
+        let struct_name = generate_ident(ident, version)?;
+        let visitor_name = format_ident!("__{}Visitor", struct_name);
+        let serialized_name = container
+            .rename
+            .clone()
+            .unwrap_or_else(|| struct_name.to_string());
+        let (_, ty_generics, _) = generics.split_for_impl();
+
+        // The visitor borrows the context for `'__c`
+        let lifetime: GenericParam = parse_quote! { '__c };
+        let mut visitor_generics = generics.clone();
+        visitor_generics.params.insert(0, lifetime.clone());
+        let (_, visitor_ty_generics, visitor_where_clause) = visitor_generics.split_for_impl();
+
+        let impl_generics = visited_fields.iter().filter(|field| !field.skip).fold(
+            deserialize_generics(generics),
+            |generics, field| {
+                let ty = field.ty;
+                let bound = if field.context {
+                    quote! { ::hifumi::__private::DeserializeInContext<'de, #context_ty> }
+                } else {
+                    quote! { ::serde::Deserialize<'de> }
+                };
+                with_bound(&generics, quote! { #ty }, bound)
+            },
+        );
+        let mut impl_visitor_generics = impl_generics.clone();
+        impl_visitor_generics.params.insert(1, lifetime);
+        let (impl_impl_generics, _, impl_where_clause) = impl_generics.split_for_impl();
+        let (impl_visitor_impl_generics, _, _) = impl_visitor_generics.split_for_impl();
+
+        let bindings = (0..visited_fields.len())
+            .map(|index| format_ident!("__field{}", index))
+            .collect::<Vec<_>>();
+        let read = visited_fields
+            .iter()
+            .zip(bindings.iter())
+            .filter(|(field, _)| !field.skip)
+            .collect::<Vec<_>>();
+        let field_names = read
+            .iter()
+            .map(|(field, _)| LitStr::new(&field.name, Span::call_site()))
+            .collect::<Vec<_>>();
+        let accepted_names = read.iter().map(|(field, _)| {
+            let names = std::iter::once(&field.name)
+                .chain(field.aliases.iter())
+                .map(|name| LitStr::new(name, Span::call_site()));
+            quote! { &[#(#names),*] }
+        });
+
+        // A field falls back on its own default first, then on the container's
+        let container_default = container.default.as_ref().map(|default| match default {
+            SerdeDefault::Trait => quote! {
+                let __default: Self::Value = ::core::default::Default::default();
+            },
+            SerdeDefault::Path(path) => quote! {
+                let __default: Self::Value = #path();
+            },
+        });
+        let default_of = |field: &VisitedField| {
+            let key = field.key;
+            match (&field.default, &container.default) {
+                (Some(SerdeDefault::Trait), _) => {
+                    Some(quote! { ::core::default::Default::default() })
+                }
+                (Some(SerdeDefault::Path(path)), _) => Some(quote! { #path() }),
+                (None, Some(_)) => Some(quote! { __default.#key }),
+                (None, None) => None,
+            }
+        };
+        let seed = |field: &VisitedField| {
+            let ty = field.ty;
+            quote! { ::hifumi::WithContext::<#ty, #context_ty>::forward(self.context) }
+        };
+        let skipped = visited_fields
+            .iter()
+            .zip(bindings.iter())
+            .filter(|(field, _)| field.skip)
+            .map(|(field, binding)| {
+                let value = default_of(field)
+                    .unwrap_or_else(|| quote! { ::core::default::Default::default() });
+                quote! { let #binding = #value; }
+            })
+            .collect::<Vec<_>>();
+
+        let keys = visited_fields.iter().map(|field| field.key);
+        let unknown_value = unknown_fields.map(|key| {
+            quote! { #key: ::hifumi::__private::unknown_fields(__unknown, self.human_readable), }
+        });
+        let phantom = phantom_type(generics).map(|_| {
+            quote! { __phantom: ::core::marker::PhantomData, }
+        });
+        let construct = quote! {
+            Ok(#struct_name {
+                #(#keys: #bindings,)*
+                #unknown_value
+                #phantom
+            })
+        };
+
+        let expecting = format!("struct {}", serialized_name);
+
+        // A flattened field makes serde read the struct as a map, so does the visitor
+        let visit_seq = unknown_fields.is_none().then(|| {
+            let expecting_len =
+                format!("struct {} with {} elements", serialized_name, read.len());
+            let elements = read.iter().enumerate().map(|(index, (field, binding))| {
+                let ty = field.ty;
+                let element = if field.context {
+                    let seed = seed(field);
+                    quote! { ::serde::de::SeqAccess::next_element_seed(&mut __seq, #seed)? }
+                } else {
+                    quote! { ::serde::de::SeqAccess::next_element::<#ty>(&mut __seq)? }
+                };
+                let missing = default_of(field).unwrap_or_else(|| {
+                    quote! {
+                        return Err(::serde::de::Error::invalid_length(#index, &#expecting_len))
+                    }
+                });
+                quote! {
+                    let #binding = match #element {
+                        Some(value) => value,
+                        None => #missing,
+                    };
+                }
+            });
+
+            quote! {
+                fn visit_seq<__A>(self, mut __seq: __A) -> ::core::result::Result<Self::Value, __A::Error>
+                where
+                    __A: ::serde::de::SeqAccess<'de>,
+                {
+                    #container_default
+                    #(#elements)*
+                    #(#skipped)*
+                    #construct
+                }
+            }
+        });
+
+        let slots = read.iter().map(|(field, binding)| {
+            let ty = field.ty;
+            quote! { let mut #binding: Option<#ty> = None; }
+        });
+        let arms = read.iter().enumerate().map(|(index, (field, binding))| {
+            let name = &field.name;
+            let ty = field.ty;
+            let value = if field.context {
+                let seed = seed(field);
+                quote! { ::serde::de::MapAccess::next_value_seed(&mut __map, #seed)? }
+            } else {
+                quote! { ::serde::de::MapAccess::next_value::<#ty>(&mut __map)? }
+            };
+            quote! {
+                ::hifumi::__private::FieldName::Known(#index) => {
+                    if #binding.is_some() {
+                        return Err(::serde::de::Error::duplicate_field(#name));
+                    }
+                    #binding = Some(#value);
+                }
+            }
+        });
+        let unknown_arm = if container.deny_unknown_fields {
+            quote! {
+                return Err(::serde::de::Error::unknown_field(&__key, &[#(#field_names),*]));
+            }
+        } else if unknown_fields.is_some() {
+            quote! {
+                let value = ::serde::de::MapAccess::next_value::<::hifumi::__private::Content<'de>>(&mut __map)?;
+                __unknown.insert(__key, value.into_owned());
+            }
+        } else {
+            quote! {
+                ::serde::de::MapAccess::next_value::<::serde::de::IgnoredAny>(&mut __map)?;
+            }
+        };
+        let unknown_slot = unknown_fields.map(|_| {
+            quote! { let mut __unknown = ::std::collections::BTreeMap::new(); }
+        });
+        let keep_unknown = container.deny_unknown_fields || unknown_fields.is_some();
+        let missing = read.iter().map(|(field, binding)| {
+            let ty = field.ty;
+            let name = &field.name;
+            let missing = default_of(field).unwrap_or_else(|| {
+                let missing = quote! { ::hifumi::__private::MissingField::<__A::Error>::new(#name) };
+                if field.context {
+                    quote! {
+                        <#ty as ::hifumi::__private::DeserializeInContext<'de, #context_ty>>::deserialize_in_context(#missing, self.context)?
+                    }
+                } else {
+                    quote! { <#ty as ::serde::Deserialize<'de>>::deserialize(#missing)? }
+                }
+            });
+            quote! {
+                let #binding = match #binding {
+                    Some(value) => value,
+                    None => #missing,
+                };
+            }
+        });
+
+        let human_readable = unknown_fields.map(|_| {
+            quote! { human_readable: bool, }
+        });
+        let deserialize = if unknown_fields.is_some() {
+            quote! {
+                let human_readable = ::serde::Deserializer::is_human_readable(&deserializer);
+                ::serde::Deserializer::deserialize_map(deserializer, #visitor_name { human_readable, context, marker: ::core::marker::PhantomData })
+            }
+        } else {
+            quote! {
+                const FIELDS: &[&str] = &[#(#field_names),*];
+                ::serde::Deserializer::deserialize_struct(deserializer, #serialized_name, FIELDS, #visitor_name { context, marker: ::core::marker::PhantomData })
+            }
+        };
+
+        visitors.push(quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #visitor_name #visitor_generics #visitor_where_clause {
+                context: Option<&'__c #context_ty>,
+                #human_readable
+                marker: ::core::marker::PhantomData<fn() -> #struct_name #ty_generics>,
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            impl #impl_visitor_impl_generics ::serde::de::Visitor<'de> for #visitor_name #visitor_ty_generics #impl_where_clause {
+                type Value = #struct_name #ty_generics;
+
+                fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                    formatter.write_str(#expecting)
+                }
+
+                #visit_seq
+
+                fn visit_map<__A>(self, mut __map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+                where
+                    __A: ::serde::de::MapAccess<'de>,
+                {
+                    #container_default
+                    #(#slots)*
+                    #unknown_slot
+                    while let Some(__key) = ::serde::de::MapAccess::next_key_seed(
+                        &mut __map,
+                        ::hifumi::__private::FieldIdentifier::new(&[#(#accepted_names),*], #keep_unknown),
+                    )? {
+                        match __key {
+                            #(#arms)*
+                            ::hifumi::__private::FieldName::Known(_) => unreachable!(),
+                            ::hifumi::__private::FieldName::Unknown(__key) => {
+                                #unknown_arm
+                            }
+                        }
+                    }
+                    #(#missing)*
+                    #(#skipped)*
+                    #construct
+                }
+            }
+
+            impl #impl_impl_generics ::hifumi::__private::DeserializeInContext<'de, #context_ty> for #struct_name #ty_generics #impl_where_clause {
+                fn deserialize_in_context<__D>(
+                    deserializer: __D,
+                    context: Option<&#context_ty>,
+                ) -> ::core::result::Result<Self, __D::Error>
+                where
+                    __D: ::serde::Deserializer<'de>,
+                {
+                    #deserialize
+                }
+            }
+        });
+        visited.insert(version.clone());
+    }
+
+    Ok((quote! { #(#visitors)* }, visited))
+}
@@ -1,39 +1,179 @@
-use std::collections::BTreeMap;
-
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use syn::{Ident, LitStr, Type};
+use quote::{format_ident, quote};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{parse_quote, Fields, GenericParam, Ident, LitStr};
 
 use crate::{
-    tools::{DeriveVersion, Migration, MigrationField},
-    utils::generate_ident,
+    tools::{
+        DeriveVersion, FieldKey, Migration, MigrationData, MigrationGraph, Untagged, VersionRepr,
+    },
+    utils::{
+        deserialize_generics, generate_ident, newest_first, normalize_version, strip_version_attrs,
+        version_id, with_bound,
+    },
+};
+
+use super::{
+    migration_report::generate_migration_report,
+    serialize_proxy::{generate_serialize_proxy, proxy_generics},
 };
 
-use self::old_version_structs::generate_old_versions;
+/// The `(id, version)` pairs of `repr = "envelope_id"`, which have to be distinct.
+fn envelope_ids(ident: &Ident, versions: &[String]) -> Result<Vec<TokenStream>> {
+    let mut ids = BTreeMap::new();
+    for version in versions {
+        if let Some(other) = ids.insert(version_id(version), version) {
+            return Err(anyhow!(
+                "Versions \"{}\" and \"{}\" of {} share the same id, use `repr = \"envelope\"` instead",
+                other,
+                version,
+                ident
+            ));
+        }
+    }
+
+    Ok(ids
+        .into_iter()
+        .map(|(id, version)| quote! { (#id, #version) })
+        .collect())
+}
+
+/// Migrates `value`, read as `version`, into the current type, then runs the `post`
+/// blocks of the hops on its route and the `validate` hook of the type, and records
+/// how it was migrated into the `__report` slot, if the seed gave one.
+fn generate_migrate_and_validate(
+    attr: &DeriveVersion,
+    ident: &Ident,
+    data: &MigrationData,
+    graph: &MigrationGraph,
+    version: &str,
+) -> Result<TokenStream> {
+    let (_, ty_generics, _) = data.generics().split_for_impl();
+    let ty = ident.to_string();
+
+    let mut checks = vec![];
+    for item in graph.path(version, &graph.current).unwrap_or_default() {
+        let Some(post) = &item.post else {
+            continue;
+        };
+        let post = &post.stmts;
+        // The blocks only exist on structs, and see their fields by reference
+        let MigrationData::Struct(data) = data else {
+            unreachable!("`post` blocks are rejected on enums");
+        };
+        let bindings = data.fields.iter().enumerate().map(|(index, field)| {
+            let key = match &field.ident {
+                Some(ident) => FieldKey::Named(ident.clone()),
+                None => FieldKey::Index(index as u32),
+            };
+            quote! { #key }
+        });
+        let pattern = match &data.fields {
+            Fields::Named(_) => quote! { #ident { #(#bindings),* } },
+            Fields::Unnamed(_) => quote! { #ident(#(#bindings),*) },
+            Fields::Unit => quote! { _ },
+        };
+
+        checks.push(quote! {
+            ::hifumi::__private::validate(#ty, #version, {
+                #[allow(unused_variables)]
+                let #pattern = &mut value;
+                #(#post)*
+            })
+            .map_err(D::Error::custom)?;
+        });
+    }
+    if let Some(validate) = &attr.validate {
+        checks.push(quote! {
+            ::hifumi::__private::validate(#ty, #version, #validate(&value))
+                .map_err(D::Error::custom)?;
+        });
+    }
+    // Only `post` blocks change the value
+    let mutability = graph
+        .path(version, &graph.current)
+        .unwrap_or_default()
+        .iter()
+        .any(|item| item.post.is_some())
+        .then(|| quote! { mut });
+    let report = generate_migration_report(graph, version);
 
-use super::old_version_structs;
+    // Types with a migration context get it from the `__context` in scope
+    let migrate = match &attr.context {
+        Some(context_ty) => quote! {
+            <#ident #ty_generics as ::hifumi::__private::MigrateWith<_, #context_ty>>::migrate_with(value, __context)
+        },
+        None => quote! {
+            <#ident #ty_generics as ::core::convert::TryFrom<_>>::try_from(value)
+        },
+    };
+
+    Ok(quote! {
+        {
+            let #mutability value = #migrate.map_err(D::Error::custom)?;
+            #(#checks)*
+            if let Some(__report) = __report {
+                *__report = Some(#report);
+            }
+            Ok(value)
+        }
+    })
+}
 
 pub(crate) fn generate_current_version_struct(
-    _attr: DeriveVersion,
+    attr: DeriveVersion,
     input: Migration,
     ident: Ident,
-    final_version: String,
-    final_struct_fields: BTreeMap<Ident, Type>,
-    versions: Vec<(String, Vec<MigrationField>, String)>,
+    graph: &MigrationGraph,
+    container_attrs: &[TokenStream],
+    visited: &BTreeSet<String>,
 ) -> Result<TokenStream> {
+    let final_version = &graph.current;
+    let old_versions = &graph.versions;
     let Migration {
         extra_macros,
-        struct_data,
+        mut data,
         ..
     } = input;
 
-    let old_version_structs =
-        generate_old_versions(final_version.clone(), final_struct_fields, versions)?;
-    let old_version_structs_enum = old_version_structs
+    if let (VersionRepr::Xml, MigrationData::Struct(item)) = (&attr.repr, &data) {
+        if let Fields::Unnamed(_) = item.fields {
+            return Err(anyhow!(
+                "`repr = \"xml\"` cannot be used on tuple structs, whose fields are not XML names"
+            ));
+        }
+    }
+
+    // The proxy keeps the serde attributes of the fields, so it is generated first
+    let serialize_proxy = generate_serialize_proxy(&ident, &data, container_attrs)?;
+
+    // Serde attributes of the fields and variants only take effect on the generated
+    // historical versions, since the user's own type does not derive serde itself
+    match &mut data {
+        MigrationData::Struct(item) => {
+            for field in item.fields.iter_mut() {
+                field.attrs.retain(|attr| !attr.path().is_ident("serde"));
+                strip_version_attrs(&mut field.attrs);
+            }
+        }
+        MigrationData::Enum(item) => {
+            for variant in item.variants.iter_mut() {
+                variant.attrs.retain(|attr| !attr.path().is_ident("serde"));
+                for field in variant.fields.iter_mut() {
+                    field.attrs.retain(|attr| !attr.path().is_ident("serde"));
+                }
+            }
+        }
+    }
+
+    let generics = data.generics();
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+
+    let old_version_structs_enum = old_versions
         .clone()
         .iter()
-        .map(|(version, _)| {
+        .map(|version| {
             Ok((
                 generate_ident(&ident, version)?,
                 LitStr::new(version, Span::call_site()),
@@ -43,69 +183,453 @@ pub(crate) fn generate_current_version_struct(
         .collect::<Vec<Result<_>>>()
         .into_iter()
         .collect::<Result<Vec<_>>>()?;
+    // The current version is serialized through a proxy that borrows the value,
+    // the others are built by downgrading it from a reference
+    let proxy_ident = format_ident!("__{}Ref", ident);
+    let proxy_generics = proxy_generics(generics);
+    let (_, proxy_ty_generics, _) = proxy_generics.split_for_impl();
     let old_version_structs_enum = old_version_structs_enum
         .iter()
-        .map(|(enum_name, enum_rename_litstr, enum_ty)| {
-            quote! {
-                #[serde(rename = #enum_rename_litstr)]
-                #enum_name(#enum_ty),
+        .zip(old_versions.iter())
+        .map(|((enum_name, enum_rename_litstr, enum_ty), version)| {
+            if version == final_version {
+                quote! {
+                    #[serde(rename = #enum_rename_litstr)]
+                    #enum_name(#proxy_ident #proxy_ty_generics),
+                }
+            } else {
+                quote! {
+                    #[serde(rename = #enum_rename_litstr)]
+                    #enum_name(#enum_ty #ty_generics),
+                }
             }
         })
         .collect::<Vec<TokenStream>>();
-    let old_version_structs_enum_name = generate_ident(&ident, "#outer")?;
-    let old_version_structs_enum = quote! {
-        #[doc(hidden)]
-        #[allow(non_camel_case_types, unused_variables, dead_code)]
-        #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
-        #[serde(tag = "$version")]
-        enum #old_version_structs_enum_name {
-            #(#old_version_structs_enum)*
-        }
-    };
-
-    let extra_macros = extra_macros.iter().map(|(key, value)| {
+    let old_version_structs_enum_name = format_ident!("__{}Versions", ident);
+    let serde_tag = attr.serde_tag();
+    let old_version_structs_enum = (!attr.reads_version_first()).then(|| {
         quote! {
-            #[#key #value]
+            #[doc(hidden)]
+            #[allow(non_camel_case_types, unused_variables, dead_code)]
+            #[derive(::serde::Serialize)]
+            #serde_tag
+            enum #old_version_structs_enum_name #proxy_generics #where_clause {
+                #(#old_version_structs_enum)*
+            }
         }
     });
 
+    let extra_macros = extra_macros
+        .iter()
+        .filter(|(key, _)| key != "serde")
+        .map(|(key, value)| {
+            quote! {
+                #[#key #value]
+            }
+        });
+
     // Generate serialize implementation
 
-    let impl_serialize_final_version_ident = generate_ident(&ident, &final_version)?;
+    let impl_serialize_final_version_ident = generate_ident(&ident, final_version)?;
+    let serialize_final_version = attr.serialize_version(
+        &data,
+        quote! { #old_version_structs_enum_name::#impl_serialize_final_version_ident },
+        final_version,
+        quote! { #proxy_ident::new(self) },
+    );
+    let serialize_generics = generics
+        .type_params()
+        .fold(generics.clone(), |generics, param| {
+            let param = &param.ident;
+            with_bound(&generics, quote! { #param }, quote! { ::serde::Serialize })
+        });
+    let (serialize_impl_generics, _, serialize_where_clause) = serialize_generics.split_for_impl();
     let impl_serialize = quote! {
-        impl ::serde::Serialize for #ident {
+        impl #serialize_impl_generics ::serde::Serialize for #ident #ty_generics #serialize_where_clause {
             fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
             where
                 S: ::serde::Serializer,
             {
-                #old_version_structs_enum_name::#impl_serialize_final_version_ident(
-                    #impl_serialize_final_version_ident::from(self.to_owned())
-                ).serialize(serializer)
+                #serialize_final_version
+            }
+        }
+    };
+
+    let supported_versions = old_versions
+        .iter()
+        .map(|version| LitStr::new(version, Span::call_site()))
+        .collect::<Vec<_>>();
+    let unknown_version = format!(
+        "No version of {} is compatible with {{}}, expected one of: {}",
+        ident,
+        old_versions.join(", ")
+    );
+
+    // Walk the route of the requested version backwards, one `TryFrom` hop at a time.
+    // The first hop borrows the value, and only exists when the fields it carries
+    // over can be cloned, which the method asks for with the lifetime of `self`
+    let mut borrowed_hops = BTreeSet::new();
+    let serialize_as_version_steps = old_versions
+        .iter()
+        .enumerate()
+        .map(|(index, version)| {
+            let struct_name = generate_ident(&ident, version)?;
+            let path = graph.path(version, final_version).unwrap_or_default();
+            let hops = path
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(step, item)| {
+                    let from_ident = generate_ident(&ident, item.from.value())?;
+                    if step == 0 {
+                        borrowed_hops.insert(from_ident.to_string());
+                        return Ok(quote! {
+                            let __value: #from_ident #ty_generics =
+                                ::core::convert::TryFrom::try_from(self).map_err(S::Error::custom)?;
+                        });
+                    }
+                    Ok(quote! {
+                        let __value: #from_ident #ty_generics =
+                            ::core::convert::TryFrom::try_from(__value).map_err(S::Error::custom)?;
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let variant = quote! { #old_version_structs_enum_name::#struct_name };
+            if version == final_version {
+                let serialize = attr.serialize_version(
+                    &data,
+                    variant,
+                    version,
+                    quote! { #proxy_ident::new(self) },
+                );
+                return Ok(quote! {
+                    #index => #serialize,
+                });
+            }
+
+            let serialize = attr.serialize_version(&data, variant, version, quote! { __value });
+            Ok(quote! {
+                #index => {
+                    #(#hops)*
+                    #serialize
+                }
+            })
+        })
+        .collect::<Result<Vec<TokenStream>>>()?;
+    let borrowed_hops = borrowed_hops.iter().map(|from_ident| {
+        let from_ident = Ident::new(from_ident, Span::call_site());
+        quote! {
+            #from_ident #ty_generics: ::core::convert::TryFrom<&'__a #ident #ty_generics, Error = ::hifumi::__private::Error>,
+        }
+    });
+    let impl_serialize_as_version = quote! {
+        impl #serialize_impl_generics #ident #ty_generics #serialize_where_clause {
+            /// Serializes the value as the given older version of the type, walking
+            /// its migration route backwards with the reverse rules.
+            pub fn serialize_as_version<'__a, S>(&'__a self, version: &str, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+                #(#borrowed_hops)*
+            {
+                use ::serde::ser::Error as _;
+
+                let index = ::hifumi::resolve_version(version, &[#(#supported_versions),*])
+                    .ok_or_else(|| S::Error::custom(format!(#unknown_version, version)))?;
+                match index {
+                    #(#serialize_as_version_steps)*
+                    _ => unreachable!(),
+                }
             }
         }
     };
 
     // Generate deserialize implementation
-    let impl_deserialize_match_list = old_version_structs
+    let migrate =
+        |version: &String| generate_migrate_and_validate(&attr, &ident, &data, graph, version);
+    // Versions with fields marked `#[version(context)]` are read with the context
+    let read_version = |version: &String, struct_name: &Ident, content: TokenStream| match (
+        &attr.context,
+        visited.contains(version),
+    ) {
+        (Some(context_ty), true) => quote! {
+            <#struct_name #ty_generics as ::hifumi::__private::DeserializeInContext<'de, #context_ty>>::deserialize_in_context(#content, __context)
+        },
+        _ => quote! {
+            <#struct_name #ty_generics as ::serde::Deserialize>::deserialize(#content)
+        },
+    };
+    // The version tag is read first, then the rest of the payload is deserialized
+    // into the closest compatible version
+    let impl_deserialize_match_list = old_versions
         .iter()
-        .map(|(version, _)| {
+        .enumerate()
+        .map(|(index, version)| {
             let struct_name = generate_ident(&ident, version)?;
+            let migrate = migrate(version)?;
+            let read = read_version(version, &struct_name, quote! { content });
             Ok(quote! {
-                #old_version_structs_enum_name::#struct_name(val) => Ok(val.into())
+                Some(#index) => {
+                    let value = #read?;
+                    #migrate
+                }
             })
         })
         .collect::<Vec<Result<TokenStream>>>()
         .into_iter()
         .collect::<Result<Vec<TokenStream>>>()?;
-    let impl_deserialize = quote! {
-        impl<'de> ::serde::Deserialize<'de> for #ident {
-            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
-            where
-                D: ::serde::Deserializer<'de>,
-            {
-                let value = #old_version_structs_enum_name::deserialize(deserializer)?;
-                match value {
+    let deserialize_generics = old_versions.iter().try_fold(
+        deserialize_generics(generics),
+        |generics, version| -> Result<_> {
+            let struct_name = generate_ident(&ident, version)?;
+            let bound = match (&attr.context, visited.contains(version)) {
+                (Some(context_ty), true) => {
+                    quote! { ::hifumi::__private::DeserializeInContext<'de, #context_ty> }
+                }
+                _ => quote! { ::serde::Deserialize<'de> },
+            };
+            Ok(with_bound(
+                &generics,
+                quote! { #struct_name #ty_generics },
+                bound,
+            ))
+        },
+    )?;
+    let (deserialize_impl_generics, _, deserialize_where_clause) =
+        deserialize_generics.split_for_impl();
+    let untagged_version = match &attr.untagged {
+        None => None,
+        Some(Untagged::Default(default)) => {
+            let version = old_versions
+                .iter()
+                .find(|version| normalize_version(version) == normalize_version(&default.value()))
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        default,
+                        format!(
+                            "Version \"{}\" is not one of the versions of {}",
+                            default.value(),
+                            ident
+                        ),
+                    )
+                })?;
+
+            Some(quote! {
+                let version = version.unwrap_or_else(|| #version.to_string());
+            })
+        }
+        Some(Untagged::Structural) => {
+            // Newer versions are tried first, so that an older version does not
+            // silently drop the fields it does not know yet
+            let attempts = newest_first(old_versions)
+                .into_iter()
+                .map(|version| {
+                    let struct_name = generate_ident(&ident, version)?;
+                    let migrate = migrate(version)?;
+                    let read = read_version(version, &struct_name, quote! { content.clone() });
+                    Ok(quote! {
+                        if let Ok(value) = #read {
+                            return #migrate;
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let no_match = format!(
+                "The untagged data matches no version of {}, expected one of: {}",
+                ident,
+                old_versions.join(", ")
+            );
+
+            Some(quote! {
+                let version = match version {
+                    Some(version) => version,
+                    None => {
+                        #(#attempts)*
+                        return Err(D::Error::custom(#no_match));
+                    }
+                };
+            })
+        }
+    };
+    // `body` reads the value from `deserializer`, with the migration context given
+    // as `__context` and the slot of the report as `__report`. Plain `Deserialize`
+    // leaves both out
+    let context_ty = attr
+        .context
+        .as_ref()
+        .map_or_else(|| quote! { () }, |context_ty| quote! { #context_ty });
+    let impl_deserialize_entry = |body: TokenStream| {
+        let impl_in_context = attr.context.as_ref().map(|_| {
+            quote! {
+                impl #deserialize_impl_generics ::hifumi::__private::DeserializeInContext<'de, #context_ty> for #ident #ty_generics #deserialize_where_clause {
+                    fn deserialize_in_context<D>(
+                        deserializer: D,
+                        context: Option<&#context_ty>,
+                    ) -> std::result::Result<Self, D::Error>
+                    where
+                        D: ::serde::Deserializer<'de>,
+                    {
+                        <Self as ::hifumi::__private::DeserializeVersioned<'de, #context_ty>>::deserialize_versioned(deserializer, context, None)
+                    }
+                }
+            }
+        });
+
+        quote! {
+            impl #deserialize_impl_generics ::hifumi::__private::DeserializeVersioned<'de, #context_ty> for #ident #ty_generics #deserialize_where_clause {
+                #[allow(unused_variables)]
+                fn deserialize_versioned<D>(
+                    deserializer: D,
+                    __context: Option<&#context_ty>,
+                    __report: Option<&mut Option<::hifumi::MigrationReport>>,
+                ) -> std::result::Result<Self, D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                {
+                    #body
+                }
+            }
+
+            #impl_in_context
+
+            impl #deserialize_impl_generics ::serde::Deserialize<'de> for #ident #ty_generics #deserialize_where_clause {
+                fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                {
+                    <Self as ::hifumi::__private::DeserializeVersioned<'de, #context_ty>>::deserialize_versioned(deserializer, None, None)
+                }
+            }
+        }
+    };
+    let impl_deserialize = match attr.repr {
+        // The data is read straight from the sequence or the root element once its
+        // version is known, so it is never buffered through `deserialize_any`
+        VersionRepr::Envelope { .. } | VersionRepr::Xml => {
+            let envelope_ident = format_ident!("__{}Envelope", ident);
+            let turbofish = ty_generics.as_turbofish();
+            // The seed carries the migration context and the slot of the report into
+            // the payload
+            let lifetime: GenericParam = parse_quote! { '__c };
+            let [mut envelope_generics, mut envelope_deserialize_generics] =
+                [generics.clone(), deserialize_generics.clone()];
+            envelope_generics.params.insert(0, lifetime.clone());
+            envelope_deserialize_generics.params.insert(1, lifetime);
+            let (envelope_deserialize_impl_generics, _, _) =
+                envelope_deserialize_generics.split_for_impl();
+            let (_, envelope_ty_generics, _) = envelope_generics.split_for_impl();
+            let seed = quote! {
+                |version| #envelope_ident #turbofish {
+                    version,
+                    context: __context,
+                    report: __report,
+                    marker: ::core::marker::PhantomData,
+                }
+            };
+            let read_version = match attr.repr {
+                VersionRepr::Envelope { id: true } => {
+                    let ids = envelope_ids(&ident, old_versions)?;
+                    quote! {
+                        ::hifumi::__private::deserialize_envelope(deserializer, Some(&[#(#ids),*]), #seed)
+                    }
+                }
+                VersionRepr::Envelope { id: false } => quote! {
+                    ::hifumi::__private::deserialize_envelope(deserializer, None, #seed)
+                },
+                _ => {
+                    let key = format!("@{}", attr.tag);
+                    quote! {
+                        ::hifumi::__private::deserialize_xml(deserializer, #key, #seed)
+                    }
+                }
+            };
+
+            let impl_entry = impl_deserialize_entry(read_version);
+
+            quote! {
+                #[doc(hidden)]
+                #[allow(non_camel_case_types)]
+                struct #envelope_ident #envelope_generics #where_clause {
+                    version: String,
+                    context: Option<&'__c #context_ty>,
+                    report: Option<&'__c mut Option<::hifumi::MigrationReport>>,
+                    marker: ::core::marker::PhantomData<fn() -> #ident #ty_generics>,
+                }
+
+                impl #envelope_deserialize_impl_generics ::serde::de::DeserializeSeed<'de> for #envelope_ident #envelope_ty_generics #deserialize_where_clause {
+                    type Value = #ident #ty_generics;
+
+                    fn deserialize<D>(self, content: D) -> std::result::Result<Self::Value, D::Error>
+                    where
+                        D: ::serde::Deserializer<'de>,
+                    {
+                        use ::serde::de::Error as _;
+
+                        let version = self.version;
+                        let __context = self.context;
+                        let __report = self.report;
+                        match ::hifumi::resolve_version(&version, &[#(#supported_versions),*]) {
+                            #( #impl_deserialize_match_list, )*
+                            _ => Err(D::Error::custom(format!(#unknown_version, version))),
+                        }
+                    }
+                }
+
+                #impl_entry
+            }
+        }
+        _ => {
+            let deserialize_version = attr.deserialize_version();
+
+            impl_deserialize_entry(quote! {
+                use ::serde::de::Error as _;
+
+                let (version, content) = #deserialize_version?;
+                #untagged_version
+                match ::hifumi::resolve_version(&version, &[#(#supported_versions),*]) {
                     #( #impl_deserialize_match_list, )*
+                    _ => Err(D::Error::custom(format!(#unknown_version, version))),
+                }
+            })
+        }
+    };
+    let impl_deserialize_with_context = attr.context.as_ref().map(|context_ty| {
+        let (impl_generics, _, _) = generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// Deserializes the value with `context` available to the converters
+                /// of its migrations.
+                pub fn deserialize_with_context<'de, D>(deserializer: D, context: &#context_ty) -> std::result::Result<Self, D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                    Self: ::hifumi::__private::DeserializeInContext<'de, #context_ty>,
+                {
+                    <Self as ::hifumi::__private::DeserializeInContext<'de, #context_ty>>::deserialize_in_context(
+                        deserializer,
+                        Some(context),
+                    )
+                }
+            }
+        }
+    });
+
+    let impl_deserialize_with_report = {
+        let (impl_generics, _, _) = generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// Deserializes the value along with a report of the version it was
+                /// read as and the migrations it went through.
+                pub fn deserialize_with_report<'de, D>(deserializer: D) -> std::result::Result<(Self, ::hifumi::MigrationReport), D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                    Self: ::hifumi::__private::DeserializeVersioned<'de, #context_ty>,
+                {
+                    ::serde::de::DeserializeSeed::deserialize(
+                        ::hifumi::WithReport::<Self, #context_ty>::new(),
+                        deserializer,
+                    )
                 }
             }
         }
@@ -113,10 +637,14 @@ pub(crate) fn generate_current_version_struct(
 
     Ok(quote! {
         #(#extra_macros)*
-        #struct_data
+        #data
 
+        #serialize_proxy
         #impl_serialize
+        #impl_serialize_as_version
         #impl_deserialize
+        #impl_deserialize_with_context
+        #impl_deserialize_with_report
         #old_version_structs_enum
     })
 }
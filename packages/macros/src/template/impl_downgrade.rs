@@ -0,0 +1,500 @@
+use anyhow::{anyhow, Result};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{parse_quote, GenericParam, Generics, Ident, Type};
+
+use crate::{
+    tools::{
+        FieldKey, MigrationField, MigrationGraph, MigrationVariant, VariantData, VariantShape,
+    },
+    utils::{generate_ident, phantom_type},
+};
+
+/// The statements and field values that rebuild an older version from a newer one,
+/// along with the fields of the newer version that are carried over as they are, or
+/// the reason why a rule of the migration cannot be walked backwards.
+type ReverseImpl = std::result::Result<
+    (
+        Vec<TokenStream>,
+        BTreeMap<FieldKey, TokenStream>,
+        BTreeSet<FieldKey>,
+    ),
+    String,
+>;
+
+/// Builds the value of every field of the older version.
+///
+/// `access` gives a field of the newer version as a reference and as the value that
+/// is carried over. Added and copied fields are simply dropped and pure renames are
+/// undone, while removed or converted fields need an explicit `<= { ... }` rule.
+/// Those rules see every field of the newer version by reference.
+fn generate_newer_version_impl(
+    access: impl Fn(&FieldKey) -> (TokenStream, TokenStream),
+    new_struct_fields: BTreeMap<FieldKey, Type>,
+    convert_rules: Vec<MigrationField>,
+) -> ReverseImpl {
+    let mut statements = vec![];
+    let mut carried = new_struct_fields.keys().cloned().collect::<BTreeSet<_>>();
+
+    let borrows = new_struct_fields
+        .keys()
+        .map(|key| {
+            let (borrow, _) = access(key);
+            quote! { let #key = #borrow; }
+        })
+        .collect::<Vec<_>>();
+    let mut reverse = |keys: Vec<&(FieldKey, syn::Type)>, converter: &TokenStream| {
+        let idents = keys
+            .iter()
+            .map(|(key, _)| format_ident!("__reverse{}", key.ident()))
+            .collect::<Vec<_>>();
+        let types = keys.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+        // A rule rebuilding several fields yields them as a tuple
+        let (binding, ty) = if keys.len() == 1 {
+            (quote! { #(#idents)* }, quote! { #(#types)* })
+        } else {
+            (quote! { (#(#idents),*) }, quote! { (#(#types),*) })
+        };
+
+        statements.push(quote! {
+            let #binding: #ty = {
+                #(#borrows)*
+                #converter
+            };
+        });
+        keys.into_iter()
+            .zip(idents)
+            .map(|((key, _), ident)| (key.clone(), quote! { #ident }))
+            .collect::<Vec<_>>()
+    };
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Add { value, .. } => {
+                let (key, _) = value;
+                carried.remove(key);
+            }
+            MigrationField::Copy { target, .. } | MigrationField::Rename { target, .. } => {
+                let (key, _) = target;
+                carried.remove(key);
+            }
+            MigrationField::Remove { .. } => {}
+        }
+    }
+    let mut struct_fields = carried
+        .iter()
+        .map(|key| (key.clone(), access(key).1))
+        .collect::<BTreeMap<_, _>>();
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Add { .. } | MigrationField::Copy { .. } => {}
+            MigrationField::Remove {
+                value,
+                reverse: rule,
+                ..
+            } => match rule {
+                Some(converter) => {
+                    struct_fields.extend(reverse(vec![value], converter));
+                }
+                None => {
+                    return Err(format!(
+                        "the removed field `{}` has no reverse rule",
+                        value.0
+                    ));
+                }
+            },
+            MigrationField::Rename {
+                source,
+                target,
+                converter,
+                reverse: rule,
+                ..
+            } => match rule {
+                Some(converter) => {
+                    struct_fields.extend(reverse(source.iter().collect(), converter));
+                }
+                None => {
+                    let (target_key, target_ty) = target;
+
+                    match source.as_slice() {
+                        [(source_key, source_ty)]
+                            if converter.is_none() && source_ty == target_ty =>
+                        {
+                            struct_fields.insert(source_key.clone(), access(target_key).1);
+                            carried.insert(target_key.clone());
+                        }
+                        _ => {
+                            return Err(format!(
+                                "the converted field `{}` has no reverse rule",
+                                target_key
+                            ));
+                        }
+                    }
+                }
+            },
+        }
+    }
+
+    Ok((statements, struct_fields, carried))
+}
+
+/// The generics of a downgrade from the current type by reference, which borrows it
+/// for `'__a` and clones the fields it carries over. The lifetime of the bounds keeps
+/// them from being trivial, so that the impl only goes missing when a field cannot
+/// be cloned.
+fn borrowed_generics<'a>(
+    generics: &Generics,
+    cloned: impl IntoIterator<Item = &'a Type>,
+) -> Generics {
+    let mut generics = generics.clone();
+    let lifetime: GenericParam = parse_quote! { '__a };
+    generics.params.insert(0, lifetime);
+    for ty in cloned {
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote! { for<'__c> #ty: ::core::clone::Clone });
+    }
+    generics
+}
+
+pub(crate) fn generate_impl_downgrades(
+    ident: Ident,
+    generics: Generics,
+    old_struct_fields: &BTreeMap<String, BTreeMap<FieldKey, Type>>,
+    graph: &MigrationGraph,
+) -> Result<TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let phantom = phantom_type(&generics).map(|_| {
+        quote! {
+            __phantom: ::core::marker::PhantomData,
+        }
+    });
+
+    let mut impl_downgrades = vec![];
+
+    for item in graph.edges.iter() {
+        let new_struct_fields = old_struct_fields[&item.to.value()].clone();
+        let from_ident = generate_ident(&ident, item.from.value())?;
+        let to_ident = generate_ident(&ident, item.to.value())?;
+        let body = |reverse: ReverseImpl| match reverse {
+            Ok((statements, fields, _)) => {
+                let fields = fields.iter().map(|(key, value)| quote! { #key: #value, });
+                quote! {
+                    #(#statements)*
+                    Ok(Self {
+                        #(#fields)*
+                        #phantom
+                    })
+                }
+            }
+            Err(reason) => {
+                let message = format!(
+                    "Cannot downgrade {} from {} to {}: {}",
+                    ident,
+                    item.to.value(),
+                    item.from.value(),
+                    reason
+                );
+                quote! {
+                    Err(::hifumi::__private::anyhow!(#message))
+                }
+            }
+        };
+
+        let owned = body(generate_newer_version_impl(
+            |key| (quote! { &__new.#key }, quote! { __new.#key }),
+            new_struct_fields.clone(),
+            item.changes.clone(),
+        ));
+        impl_downgrades.push(quote! {
+            impl #impl_generics ::core::convert::TryFrom<#to_ident #ty_generics> for #from_ident #ty_generics #where_clause {
+                type Error = ::hifumi::__private::Error;
+
+                #[allow(unused_variables)]
+                fn try_from(__new: #to_ident #ty_generics) -> ::hifumi::__private::Result<Self> {
+                    #owned
+                }
+            }
+        });
+
+        // `serialize_as_version` starts from the current type by reference
+        if item.to.value() == graph.current {
+            let reverse = generate_newer_version_impl(
+                |key| {
+                    let member = key.member();
+                    (
+                        quote! { &__new.#member },
+                        quote! { ::core::clone::Clone::clone(&__new.#member) },
+                    )
+                },
+                new_struct_fields.clone(),
+                item.changes.clone(),
+            );
+            let cloned = match &reverse {
+                Ok((_, _, carried)) => carried
+                    .iter()
+                    .map(|key| new_struct_fields[key].clone())
+                    .collect(),
+                Err(_) => vec![],
+            };
+            let borrowed = body(reverse);
+            let borrowed_generics = borrowed_generics(&generics, &cloned);
+            let (borrowed_impl_generics, _, borrowed_where_clause) =
+                borrowed_generics.split_for_impl();
+
+            impl_downgrades.push(quote! {
+                impl #borrowed_impl_generics ::core::convert::TryFrom<&'__a #ident #ty_generics> for #from_ident #ty_generics #borrowed_where_clause {
+                    type Error = ::hifumi::__private::Error;
+
+                    #[allow(unused_variables)]
+                    fn try_from(__new: &'__a #ident #ty_generics) -> ::hifumi::__private::Result<Self> {
+                        #borrowed
+                    }
+                }
+            });
+        }
+    }
+
+    Ok(quote! {
+        #(#impl_downgrades)*
+    })
+}
+
+/// The match arms that rebuild an older version from a newer one, along with the
+/// types of the fields they carry over. With `borrowed`, the newer version is
+/// matched by reference and those fields are cloned.
+fn generate_newer_version_enum_impl(
+    ident: &Ident,
+    to_ident: &Ident,
+    (from_version, to_version): (String, String),
+    new_enum_variants: BTreeMap<Ident, VariantData>,
+    convert_rules: Vec<MigrationVariant>,
+    borrowed: bool,
+) -> Result<(Vec<TokenStream>, Vec<Type>)> {
+    let error = |reason: String| {
+        let message = format!(
+            "Cannot downgrade {} from {} to {}: {}",
+            ident, to_version, from_version, reason
+        );
+        quote! {
+            Err(::hifumi::__private::anyhow!(#message))
+        }
+    };
+
+    let construct = |shape: &VariantShape| match borrowed {
+        true => shape.cloned(),
+        false => shape.pattern(),
+    };
+
+    let mut enum_arms = new_enum_variants
+        .iter()
+        .map(|(variant, data)| {
+            let pattern = data.shape.pattern();
+            let value = construct(&data.shape);
+            (
+                variant.clone(),
+                (
+                    quote! {
+                        #to_ident::#variant #pattern => Ok(Self::#variant #value)
+                    },
+                    data.shape.types(),
+                ),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationVariant::Remove { .. } => {}
+            MigrationVariant::Add { variant } => {
+                let error = error(format!("the variant `{}` does not exist yet", variant));
+
+                enum_arms.insert(
+                    variant.clone(),
+                    (
+                        quote! {
+                            #to_ident::#variant { .. } => #error
+                        },
+                        vec![],
+                    ),
+                );
+            }
+            MigrationVariant::Rename {
+                source,
+                target,
+                reverse,
+            } => {
+                let shape = &new_enum_variants
+                    .get(target)
+                    .ok_or_else(|| anyhow!("Cannot find the renamed variant {}", target))?
+                    .shape;
+                let pattern = shape.pattern();
+                let value = construct(shape);
+                let source = match (source.as_slice(), reverse) {
+                    (_, Some(reverse)) => Some(reverse),
+                    ([source], None) => Some(source),
+                    _ => None,
+                };
+
+                let arm = match source {
+                    Some(source) => (
+                        quote! {
+                            #to_ident::#target #pattern => Ok(Self::#source #value)
+                        },
+                        shape.types(),
+                    ),
+                    None => {
+                        let error = error(format!(
+                            "the merged variant `{}` has no reverse rule",
+                            target
+                        ));
+                        (
+                            quote! {
+                                #to_ident::#target { .. } => #error
+                            },
+                            vec![],
+                        )
+                    }
+                };
+                enum_arms.insert(target.clone(), arm);
+            }
+            MigrationVariant::Fields { variant, changes } => {
+                let new_variant = new_enum_variants
+                    .get(variant)
+                    .ok_or_else(|| anyhow!("Cannot find the migrated variant {}", variant))?;
+                let new_fields = match &new_variant.shape {
+                    VariantShape::Named(fields) => fields.clone(),
+                    _ => {
+                        return Err(anyhow!(
+                            "Field migrations can only be applied to struct-like variants, but {} is not",
+                            variant
+                        ))
+                    }
+                };
+
+                let pattern = new_variant.shape.pattern();
+                // The pattern binds the fields by reference when the value is borrowed
+                let access = |key: &FieldKey| match borrowed {
+                    true => (
+                        quote! { #key },
+                        quote! { ::core::clone::Clone::clone(#key) },
+                    ),
+                    false => (quote! { &#key }, quote! { #key }),
+                };
+                let arm = match generate_newer_version_impl(
+                    access,
+                    new_fields.clone(),
+                    changes.clone(),
+                ) {
+                    Ok((statements, fields, carried)) => {
+                        let fields = fields.iter().map(|(key, value)| quote! { #key: #value });
+                        (
+                            quote! {
+                                #to_ident::#variant #pattern => {
+                                    #(#statements)*
+                                    Ok(Self::#variant {
+                                        #(#fields),*
+                                    })
+                                }
+                            },
+                            carried.iter().map(|key| new_fields[key].clone()).collect(),
+                        )
+                    }
+                    Err(reason) => {
+                        let error = error(reason);
+                        (
+                            quote! {
+                                #to_ident::#variant { .. } => #error
+                            },
+                            vec![],
+                        )
+                    }
+                };
+                enum_arms.insert(variant.clone(), arm);
+            }
+        }
+    }
+
+    let (arms, cloned): (Vec<_>, Vec<_>) = enum_arms.into_values().unzip();
+    Ok((arms, cloned.into_iter().flatten().collect()))
+}
+
+pub(crate) fn generate_impl_enum_downgrades(
+    ident: Ident,
+    generics: Generics,
+    old_enum_variants: &BTreeMap<String, BTreeMap<Ident, VariantData>>,
+    graph: &MigrationGraph,
+) -> Result<TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut impl_downgrades = vec![];
+
+    for item in graph.edges.iter() {
+        let new_enum_variants = old_enum_variants[&item.to.value()].clone();
+        let from_ident = generate_ident(&ident, item.from.value())?;
+        let to_ident = generate_ident(&ident, item.to.value())?;
+
+        let (enum_arms, _) = generate_newer_version_enum_impl(
+            &ident,
+            &to_ident,
+            (item.from.value(), item.to.value()),
+            new_enum_variants.clone(),
+            item.variants.clone(),
+            false,
+        )?;
+        let enum_phantom_arm = phantom_type(&generics).map(|_| {
+            quote! {
+                #to_ident::__Phantom(_, __never) => match __never {},
+            }
+        });
+
+        impl_downgrades.push(quote! {
+            impl #impl_generics ::core::convert::TryFrom<#to_ident #ty_generics> for #from_ident #ty_generics #where_clause {
+                type Error = ::hifumi::__private::Error;
+
+                #[allow(unused_variables)]
+                fn try_from(__new: #to_ident #ty_generics) -> ::hifumi::__private::Result<Self> {
+                    match __new {
+                        #(#enum_arms,)*
+                        #enum_phantom_arm
+                    }
+                }
+            }
+        });
+
+        // `serialize_as_version` starts from the current type by reference
+        if item.to.value() == graph.current {
+            let (enum_arms, cloned) = generate_newer_version_enum_impl(
+                &ident,
+                &ident,
+                (item.from.value(), item.to.value()),
+                new_enum_variants,
+                item.variants.clone(),
+                true,
+            )?;
+            let borrowed_generics = borrowed_generics(&generics, &cloned);
+            let (borrowed_impl_generics, _, borrowed_where_clause) =
+                borrowed_generics.split_for_impl();
+
+            impl_downgrades.push(quote! {
+                impl #borrowed_impl_generics ::core::convert::TryFrom<&'__a #ident #ty_generics> for #from_ident #ty_generics #borrowed_where_clause {
+                    type Error = ::hifumi::__private::Error;
+
+                    #[allow(unused_variables)]
+                    fn try_from(__new: &'__a #ident #ty_generics) -> ::hifumi::__private::Result<Self> {
+                        match __new {
+                            #(#enum_arms,)*
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    Ok(quote! {
+        #(#impl_downgrades)*
+    })
+}
@@ -1,32 +1,86 @@
 use anyhow::{anyhow, Result};
 use proc_macro2::TokenStream;
 use quote::quote;
-use std::collections::BTreeMap;
-use syn::{Ident, Type};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{Generics, Ident, Type};
 
 use crate::{
-    tools::{MigrationComment, MigrationField},
-    utils::generate_ident,
+    tools::{
+        FieldKey, MigrationComment, MigrationField, MigrationGraph, MigrationHop, MigrationVariant,
+        VariantData, VariantShape,
+    },
+    utils::{generate_ident, phantom_type},
 };
 
-use super::old_version_structs::infer_older_version_struct;
-
+/// Builds the value of every field of the newer version.
+///
+/// `accessor` is the prefix used to reach an older field, e.g. `__old.` for a struct
+/// or nothing at all for the bindings of an enum variant pattern. `prefix` is put in
+/// front of the field names reported by fallible converters.
 fn generate_older_version_impl(
-    old_struct_fields: BTreeMap<Ident, Type>,
+    accessor: TokenStream,
+    old_struct_fields: BTreeMap<FieldKey, Type>,
     convert_rules: Vec<MigrationField>,
-) -> Result<BTreeMap<Ident, TokenStream>> {
+    hop: &MigrationHop,
+    prefix: &str,
+) -> Result<BTreeMap<FieldKey, TokenStream>> {
+    // The newer fields are built in the order of their keys, so every older field is
+    // moved into the last of them that uses it and only cloned for the others
+    let consumed = convert_rules
+        .iter()
+        .flat_map(|rule| match rule {
+            MigrationField::Remove { value, .. } => vec![&value.0],
+            MigrationField::Rename { source, .. } => source.iter().map(|(key, _)| key).collect(),
+            MigrationField::Add { .. } | MigrationField::Copy { .. } => vec![],
+        })
+        .collect::<BTreeSet<_>>();
+    let mut last_use = old_struct_fields
+        .keys()
+        .filter(|key| !consumed.contains(key))
+        .map(|key| (key.clone(), key.clone()))
+        .collect::<BTreeMap<_, _>>();
+    for rule in convert_rules.iter() {
+        if let MigrationField::Copy { source, target, .. }
+        | MigrationField::Rename { source, target, .. } = rule
+        {
+            for (key, _) in source.iter() {
+                let user = last_use
+                    .entry(key.clone())
+                    .or_insert_with(|| target.0.clone());
+                *user = user.clone().max(target.0.clone());
+            }
+        }
+    }
+    let take = |key: &FieldKey, user: &FieldKey| {
+        if last_use.get(key) == Some(user) {
+            quote! { #accessor #key }
+        } else {
+            quote! { #accessor #key.clone() }
+        }
+    };
+
     let mut struct_fields = old_struct_fields
         .keys()
-        .map(|key| {
-            (
-                key.clone(),
-                quote! {
-                    #key: __old.#key.to_owned()
-                },
-            )
-        })
+        .map(|key| (key.clone(), take(key, key)))
         .collect::<BTreeMap<_, _>>();
 
+    // Drop every consumed field first, so that a key freed by one rule can be
+    // reused by another one regardless of their order
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Remove { value, .. } => {
+                let (ident, _) = value;
+                struct_fields.remove(ident);
+            }
+            MigrationField::Rename { source, .. } => {
+                for (ident, _) in source.iter() {
+                    struct_fields.remove(ident);
+                }
+            }
+            MigrationField::Add { .. } | MigrationField::Copy { .. } => {}
+        }
+    }
+
     for rule in convert_rules.iter() {
         match rule {
             MigrationField::Add { value, converter } => {
@@ -36,16 +90,20 @@ fn generate_older_version_impl(
                     Some(converter) => {
                         struct_fields.insert(
                             key.clone(),
-                            quote! {
-                                #key: { #converter }
-                            },
+                            converter.call(
+                                vec![],
+                                quote! { #ty },
+                                vec![],
+                                hop,
+                                format!("{}{}", prefix, key),
+                            ),
                         );
                     }
                     None => {
                         struct_fields.insert(
                             key.clone(),
                             quote! {
-                                #key: #ty::default()
+                                <#ty>::default()
                             },
                         );
                     }
@@ -55,6 +113,7 @@ fn generate_older_version_impl(
                 source,
                 target,
                 converter,
+                ..
             } => {
                 let (target_ident, target_ty) = target;
 
@@ -64,25 +123,20 @@ fn generate_older_version_impl(
                             .iter()
                             .map(|(key, ty)| quote! { #key: #ty })
                             .collect::<Vec<_>>();
-                        let converter = quote! {
-                            #[allow(unused_variables)]
-                            fn _converter(#(#params),*) -> #target_ty {
-                                #converter
-                            }
-                        };
                         let args = source
                             .iter()
-                            .map(|(key, _)| quote! { __old.#key.clone() })
+                            .map(|(key, _)| take(key, target_ident))
                             .collect::<Vec<_>>();
 
                         struct_fields.insert(
                             target_ident.clone(),
-                            quote! {
-                                #target_ident: {
-                                    #converter
-                                    _converter(#(#args),*)
-                                }
-                            },
+                            converter.call(
+                                params,
+                                quote! { #target_ty },
+                                args,
+                                hop,
+                                format!("{}{}", prefix, target_ident),
+                            ),
                         );
                     }
                     None => {
@@ -90,19 +144,13 @@ fn generate_older_version_impl(
                             let (source_ident, source_ty) = source.iter().next().unwrap();
 
                             if source_ty == target_ty {
-                                struct_fields.insert(
-                                    target_ident.clone(),
-                                    quote! {
-                                        #target_ident: __old.#source_ident.clone()
-                                    },
-                                );
+                                struct_fields
+                                    .insert(target_ident.clone(), take(source_ident, target_ident));
                             } else {
-                                struct_fields.insert(
-                                    target_ident.clone(),
-                                    quote! {
-                                        #target_ident: __old.#source_ident.clone().into()
-                                    },
-                                );
+                                struct_fields.insert(target_ident.clone(), {
+                                    let source = take(source_ident, target_ident);
+                                    quote! { #source.into() }
+                                });
                             }
                         } else {
                             return Err(anyhow!(
@@ -112,19 +160,13 @@ fn generate_older_version_impl(
                     }
                 }
             }
-            MigrationField::Remove { value } => {
-                let (ident, _) = value;
-                struct_fields.remove(ident);
-            }
+            MigrationField::Remove { .. } => {}
             MigrationField::Rename {
                 source,
                 target,
                 converter,
+                ..
             } => {
-                for (ident, _) in source.iter() {
-                    struct_fields.remove(ident);
-                }
-
                 let (target_ident, target_ty) = target;
 
                 match converter {
@@ -133,25 +175,20 @@ fn generate_older_version_impl(
                             .iter()
                             .map(|(key, ty)| quote! { #key: #ty })
                             .collect::<Vec<_>>();
-                        let converter = quote! {
-                            #[allow(unused_variables)]
-                            fn _converter(#(#params),*) -> #target_ty {
-                                #converter
-                            }
-                        };
                         let args = source
                             .iter()
-                            .map(|(key, _)| quote! { __old.#key.clone() })
+                            .map(|(key, _)| take(key, target_ident))
                             .collect::<Vec<_>>();
 
                         struct_fields.insert(
                             target_ident.clone(),
-                            quote! {
-                                #target_ident: {
-                                    #converter
-                                    _converter(#(#args),*)
-                                }
-                            },
+                            converter.call(
+                                params,
+                                quote! { #target_ty },
+                                args,
+                                hop,
+                                format!("{}{}", prefix, target_ident),
+                            ),
                         );
                     }
                     None => {
@@ -159,19 +196,13 @@ fn generate_older_version_impl(
                             let (source_ident, source_ty) = source.iter().next().unwrap();
 
                             if source_ty == target_ty {
-                                struct_fields.insert(
-                                    target_ident.clone(),
-                                    quote! {
-                                        #target_ident: __old.#source_ident
-                                    },
-                                );
+                                struct_fields
+                                    .insert(target_ident.clone(), take(source_ident, target_ident));
                             } else {
-                                struct_fields.insert(
-                                    target_ident.clone(),
-                                    quote! {
-                                        #target_ident: __old.#source_ident.into()
-                                    },
-                                );
+                                struct_fields.insert(target_ident.clone(), {
+                                    let source = take(source_ident, target_ident);
+                                    quote! { #source.into() }
+                                });
                             }
                         } else {
                             return Err(anyhow!("Must provide a converter while renaming field"));
@@ -185,77 +216,527 @@ fn generate_older_version_impl(
     Ok(struct_fields.into_iter().collect())
 }
 
+/// Implements a single hop of the chain, with `TryFrom` if any of its converters
+/// can fail. `body` builds the newer version from `__old`.
+///
+/// With a migration `context`, the hop also implements `MigrateWith`, which gives
+/// the converters the context as `__context`.
+fn generate_hop_impl(
+    generics: &Generics,
+    from: TokenStream,
+    to: TokenStream,
+    fallible: bool,
+    context: Option<&Type>,
+    body: TokenStream,
+) -> TokenStream {
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    match (fallible, context) {
+        (true, Some(context)) => quote! {
+            impl #impl_generics ::hifumi::__private::MigrateWith<#from, #context> for #to #where_clause {
+                #[allow(unused_variables)]
+                fn migrate_with(
+                    __old: #from,
+                    __context: Option<&#context>,
+                ) -> ::core::result::Result<Self, ::hifumi::MigrationError> {
+                    Ok(#body)
+                }
+            }
+
+            impl #impl_generics ::core::convert::TryFrom<#from> for #to #where_clause {
+                type Error = ::hifumi::MigrationError;
+
+                fn try_from(__old: #from) -> ::core::result::Result<Self, Self::Error> {
+                    <Self as ::hifumi::__private::MigrateWith<#from, #context>>::migrate_with(__old, None)
+                }
+            }
+        },
+        (true, None) => quote! {
+            impl #impl_generics ::core::convert::TryFrom<#from> for #to #where_clause {
+                type Error = ::hifumi::MigrationError;
+
+                #[allow(unused_variables)]
+                fn try_from(__old: #from) -> ::core::result::Result<Self, Self::Error> {
+                    Ok(#body)
+                }
+            }
+        },
+        (false, context) => {
+            let migrate_with =
+                context.map(|context| generate_migrate_with(generics, &from, &to, context));
+
+            quote! {
+                impl #impl_generics From<#from> for #to #where_clause {
+                    #[allow(unused_variables)]
+                    fn from(__old: #from) -> Self {
+                        #body
+                    }
+                }
+
+                #migrate_with
+            }
+        }
+    }
+}
+
+/// Implements `MigrateWith` for a migration that needs no context, through `From`.
+fn generate_migrate_with(
+    generics: &Generics,
+    from: &TokenStream,
+    to: &TokenStream,
+    context: &Type,
+) -> TokenStream {
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::hifumi::__private::MigrateWith<#from, #context> for #to #where_clause {
+            fn migrate_with(
+                __old: #from,
+                _: Option<&#context>,
+            ) -> ::core::result::Result<Self, ::hifumi::MigrationError> {
+                Ok(From::from(__old))
+            }
+        }
+    }
+}
+
+/// Converts a historical version straight into the current type through the next
+/// version, with `TryFrom` if any hop left on the way can fail.
+fn generate_chain_impl(
+    generics: &Generics,
+    from: TokenStream,
+    next: TokenStream,
+    current: TokenStream,
+    fallible: bool,
+    context: Option<&Type>,
+) -> TokenStream {
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    // The context is passed on to every hop of the route
+    let migrate_with = context.map(|context| {
+        quote! {
+            impl #impl_generics ::hifumi::__private::MigrateWith<#from, #context> for #current #where_clause {
+                fn migrate_with(
+                    __old: #from,
+                    __context: Option<&#context>,
+                ) -> ::core::result::Result<Self, ::hifumi::MigrationError> {
+                    let __value = <#next as ::hifumi::__private::MigrateWith<#from, #context>>::migrate_with(__old, __context)?;
+                    <Self as ::hifumi::__private::MigrateWith<#next, #context>>::migrate_with(__value, __context)
+                }
+            }
+        }
+    });
+
+    if fallible {
+        quote! {
+            impl #impl_generics ::core::convert::TryFrom<#from> for #current #where_clause {
+                type Error = ::hifumi::MigrationError;
+
+                // The `?` converts the error of the next hop, which may be `Infallible`
+                #[allow(clippy::needless_question_mark)]
+                fn try_from(__old: #from) -> ::core::result::Result<Self, Self::Error> {
+                    let __value: #next = ::core::convert::TryFrom::try_from(__old)?;
+                    Ok(::core::convert::TryFrom::try_from(__value)?)
+                }
+            }
+
+            #migrate_with
+        }
+    } else {
+        quote! {
+            impl #impl_generics From<#from> for #current #where_clause {
+                fn from(__old: #from) -> Self {
+                    <#next>::from(__old).into()
+                }
+            }
+
+            #migrate_with
+        }
+    }
+}
+
+/// Converts every historical version straight into the current type along its
+/// route, unless its route already is a single hop.
+fn generate_route_impls(
+    ident: &Ident,
+    generics: &Generics,
+    graph: &MigrationGraph,
+    context: Option<&Type>,
+) -> Result<Vec<TokenStream>> {
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    graph
+        .versions
+        .iter()
+        .skip(1)
+        .filter_map(|version| {
+            let item = graph.route(version)?;
+            (item.to.value() != graph.current).then_some((version, item))
+        })
+        .map(|(version, item)| {
+            let path = graph
+                .path(version, &graph.current)
+                .ok_or_else(|| anyhow!("Cannot find the route of version {}", version))?;
+            let from_ident = generate_ident(ident, version)?;
+            let next_ident = generate_ident(ident, item.to.value())?;
+
+            Ok(generate_chain_impl(
+                generics,
+                quote! { #from_ident #ty_generics },
+                quote! { #next_ident #ty_generics },
+                quote! { #ident #ty_generics },
+                path.iter().any(|item| item.fallible()),
+                context,
+            ))
+        })
+        .collect()
+}
+
 pub(crate) fn generate_impl_froms(
     ident: Ident,
+    generics: Generics,
     final_version: String,
-    final_struct_fields: BTreeMap<Ident, Type>,
-    versions: Vec<MigrationComment>,
+    old_struct_fields: &BTreeMap<String, BTreeMap<FieldKey, Type>>,
+    graph: &MigrationGraph,
+    context: Option<&Type>,
 ) -> Result<TokenStream> {
-    let mut temp_struct_fields = final_struct_fields.to_owned();
-    let mut temp_version = final_version.clone();
-    let mut impl_froms = vec![];
+    let final_struct_fields = &old_struct_fields[&final_version];
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let phantom = phantom_type(&generics).map(|_| {
+        quote! {
+            __phantom: ::core::marker::PhantomData,
+        }
+    });
 
-    while let Some(item) = versions.iter().find(|item| item.to.value() == temp_version) {
-        temp_version = item.from.value();
-        temp_struct_fields =
-            infer_older_version_struct(temp_struct_fields.clone(), item.changes.clone())?;
+    let hop = |item: &MigrationComment| MigrationHop {
+        ident: ident.to_string(),
+        from: item.from.value(),
+        to: item.to.value(),
+    };
 
+    let mut impl_froms = vec![];
+
+    for item in graph.edges.iter() {
+        // Only the route of a version may land on the user type, any other migration
+        // into the current version targets its historical twin instead
+        let lands_on_current = item.to.value() == final_version
+            && graph
+                .route(&item.from.value())
+                .is_some_and(|route| route.to.value() == final_version);
         let from_ident = generate_ident(&ident, item.from.value())?;
-        let to_ident = if item.to.value() == final_version {
+        let to_ident = if lands_on_current {
             ident.clone()
         } else {
             generate_ident(&ident, item.to.value())?
         };
 
-        let temp_struct_impl =
-            generate_older_version_impl(temp_struct_fields.clone(), item.changes.clone())?;
-        let temp_struct_impl_nearly = temp_struct_impl.values();
+        let temp_struct_impl = generate_older_version_impl(
+            quote! { __old. },
+            old_struct_fields[&item.from.value()].clone(),
+            item.changes.clone(),
+            &hop(item),
+            "",
+        )?;
+        let temp_struct_impl_nearly = temp_struct_impl.iter().map(|(key, value)| {
+            // Only the user's own type can be a tuple struct
+            if lands_on_current {
+                let member = key.member();
+                quote! { #member: #value, }
+            } else {
+                quote! { #key: #value, }
+            }
+        });
+        let temp_struct_phantom = if lands_on_current {
+            None
+        } else {
+            phantom.clone()
+        };
 
-        impl_froms.push(quote! {
-            impl From<#from_ident> for #to_ident {
-                fn from(__old: #from_ident) -> Self {
-                    Self {
-                        #(#temp_struct_impl_nearly),*
-                    }
+        impl_froms.push(generate_hop_impl(
+            &generics,
+            quote! { #from_ident #ty_generics },
+            quote! { #to_ident #ty_generics },
+            item.fallible(),
+            context,
+            quote! {
+                Self {
+                    #(#temp_struct_impl_nearly)*
+                    #temp_struct_phantom
+                }
+            },
+        ));
+    }
+
+    impl_froms.extend(generate_route_impls(&ident, &generics, graph, context)?);
+
+    let final_ident = generate_ident(&ident, &final_version)?;
+    let final_fields_from_final = final_struct_fields.keys().map(|key| {
+        let member = key.member();
+        quote! {
+            #member: __old.#key
+        }
+    });
+    let final_fields_from_current = final_struct_fields.keys().map(|key| {
+        let member = key.member();
+        quote! {
+            #key: __old.#member
+        }
+    });
+
+    impl_froms.push(quote! {
+        impl #impl_generics From<#final_ident #ty_generics> for #ident #ty_generics #where_clause {
+            fn from(__old: #final_ident #ty_generics) -> Self {
+                Self {
+                    #(#final_fields_from_final),*
                 }
             }
-        });
+        }
+    });
+    impl_froms.extend(context.map(|context| {
+        generate_migrate_with(
+            &generics,
+            &quote! { #final_ident #ty_generics },
+            &quote! { #ident #ty_generics },
+            context,
+        )
+    }));
+    impl_froms.push(quote! {
+        impl #impl_generics From<#ident #ty_generics> for #final_ident #ty_generics #where_clause {
+            fn from(__old: #ident #ty_generics) -> Self {
+                Self {
+                    #(#final_fields_from_current,)*
+                    #phantom
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#impl_froms)*
+    })
+}
+
+fn generate_older_version_enum_impl(
+    from_ident: &Ident,
+    old_enum_variants: BTreeMap<Ident, VariantData>,
+    convert_rules: Vec<MigrationVariant>,
+    hop: &MigrationHop,
+) -> Result<Vec<TokenStream>> {
+    let mut enum_arms = old_enum_variants
+        .iter()
+        .map(|(variant, data)| {
+            let pattern = data.shape.pattern();
+            (
+                variant.clone(),
+                quote! {
+                    #from_ident::#variant #pattern => Self::#variant #pattern
+                },
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationVariant::Add { .. } => {}
+            MigrationVariant::Remove {
+                variant,
+                shape,
+                converter,
+            } => {
+                let pattern = shape.pattern();
+
+                match converter {
+                    Some(converter) => {
+                        let args = shape
+                            .bindings()
+                            .into_iter()
+                            .map(|ident| quote! { #ident })
+                            .collect();
+                        let converter = converter.call(
+                            shape.params(),
+                            quote! { Self },
+                            args,
+                            hop,
+                            variant.to_string(),
+                        );
 
-        if item.to.value() != final_version {
-            impl_froms.push(quote! {
-                impl From<#from_ident> for #ident {
-                    fn from(__old: #from_ident) -> Self {
-                        #to_ident::from(__old).into()
+                        enum_arms.insert(
+                            variant.clone(),
+                            quote! {
+                                #from_ident::#variant #pattern => #converter
+                            },
+                        );
+                    }
+                    None => {
+                        enum_arms.insert(
+                            variant.clone(),
+                            quote! {
+                                #from_ident::#variant #pattern => Self::default()
+                            },
+                        );
                     }
                 }
-            });
+            }
+            MigrationVariant::Rename { source, target, .. } => {
+                for variant in source.iter() {
+                    let pattern = old_enum_variants
+                        .get(variant)
+                        .ok_or_else(|| anyhow!("Cannot find the renamed variant {}", variant))?
+                        .shape
+                        .pattern();
+
+                    enum_arms.insert(
+                        variant.clone(),
+                        quote! {
+                            #from_ident::#variant #pattern => Self::#target #pattern
+                        },
+                    );
+                }
+            }
+            MigrationVariant::Fields { variant, changes } => {
+                let old_variant = old_enum_variants
+                    .get(variant)
+                    .ok_or_else(|| anyhow!("Cannot find the migrated variant {}", variant))?;
+                let old_fields = match &old_variant.shape {
+                    VariantShape::Named(fields) => fields.clone(),
+                    _ => {
+                        return Err(anyhow!(
+                            "Field migrations can only be applied to struct-like variants, but {} is not",
+                            variant
+                        ))
+                    }
+                };
+
+                let pattern = old_variant.shape.pattern();
+                let fields = generate_older_version_impl(
+                    quote! {},
+                    old_fields,
+                    changes.clone(),
+                    hop,
+                    &format!("{}.", variant),
+                )?;
+                let fields = fields.iter().map(|(key, value)| quote! { #key: #value });
+
+                enum_arms.insert(
+                    variant.clone(),
+                    quote! {
+                        #from_ident::#variant #pattern => Self::#variant {
+                            #(#fields),*
+                        }
+                    },
+                );
+            }
         }
     }
 
-    let final_ident = generate_ident(&ident, &final_version)?;
-    let final_fields = final_struct_fields
-        .keys()
-        .map(|ident| {
+    Ok(enum_arms.into_values().collect())
+}
+
+pub(crate) fn generate_impl_enum_froms(
+    ident: Ident,
+    generics: Generics,
+    final_version: String,
+    old_enum_variants: &BTreeMap<String, BTreeMap<Ident, VariantData>>,
+    graph: &MigrationGraph,
+    context: Option<&Type>,
+) -> Result<TokenStream> {
+    let final_enum_variants = &old_enum_variants[&final_version];
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let phantom_arm = |from_ident: &Ident| {
+        phantom_type(&generics).map(|_| {
             quote! {
-                #ident: __old.#ident.to_owned()
+                #from_ident::__Phantom(_, __never) => match __never {},
             }
         })
-        .collect::<Vec<_>>();
+    };
+
+    let hop = |item: &MigrationComment| MigrationHop {
+        ident: ident.to_string(),
+        from: item.from.value(),
+        to: item.to.value(),
+    };
+
+    let mut impl_froms = vec![];
+
+    for item in graph.edges.iter() {
+        // Only the route of a version may land on the user type, any other migration
+        // into the current version targets its historical twin instead
+        let lands_on_current = item.to.value() == final_version
+            && graph
+                .route(&item.from.value())
+                .is_some_and(|route| route.to.value() == final_version);
+        let from_ident = generate_ident(&ident, item.from.value())?;
+        let to_ident = if lands_on_current {
+            ident.clone()
+        } else {
+            generate_ident(&ident, item.to.value())?
+        };
+
+        let enum_arms = generate_older_version_enum_impl(
+            &from_ident,
+            old_enum_variants[&item.from.value()].clone(),
+            item.variants.clone(),
+            &hop(item),
+        )?;
+
+        let enum_phantom_arm = phantom_arm(&from_ident);
+
+        impl_froms.push(generate_hop_impl(
+            &generics,
+            quote! { #from_ident #ty_generics },
+            quote! { #to_ident #ty_generics },
+            item.fallible(),
+            context,
+            quote! {
+                match __old {
+                    #(#enum_arms,)*
+                    #enum_phantom_arm
+                }
+            },
+        ));
+    }
+
+    impl_froms.extend(generate_route_impls(&ident, &generics, graph, context)?);
+
+    let final_ident = generate_ident(&ident, &final_version)?;
+    let final_arms = |from_ident: &Ident| {
+        final_enum_variants
+            .iter()
+            .map(|(variant, data)| {
+                let pattern = data.shape.pattern();
+                quote! {
+                    #from_ident::#variant #pattern => Self::#variant #pattern
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let final_arms_from_final = final_arms(&final_ident);
+    let final_arms_from_current = final_arms(&ident);
+    let final_phantom_arm = phantom_arm(&final_ident);
 
     impl_froms.push(quote! {
-        impl From<#final_ident> for #ident {
-            fn from(__old: #final_ident) -> Self {
-                Self {
-                    #(#final_fields),*
+        impl #impl_generics From<#final_ident #ty_generics> for #ident #ty_generics #where_clause {
+            fn from(__old: #final_ident #ty_generics) -> Self {
+                match __old {
+                    #(#final_arms_from_final,)*
+                    #final_phantom_arm
                 }
             }
         }
     });
+    impl_froms.extend(context.map(|context| {
+        generate_migrate_with(
+            &generics,
+            &quote! { #final_ident #ty_generics },
+            &quote! { #ident #ty_generics },
+            context,
+        )
+    }));
     impl_froms.push(quote! {
-        impl From<#ident> for #final_ident {
-            fn from(__old: #ident) -> Self {
-                Self {
-                    #(#final_fields),*
+        impl #impl_generics From<#ident #ty_generics> for #final_ident #ty_generics #where_clause {
+            fn from(__old: #ident #ty_generics) -> Self {
+                match __old {
+                    #(#final_arms_from_current),*
                 }
             }
         }
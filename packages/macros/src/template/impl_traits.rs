@@ -0,0 +1,112 @@
+use anyhow::Result;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Generics, Ident, LitStr};
+
+use crate::{
+    tools::MigrationGraph,
+    utils::{generate_ident, with_bound},
+};
+
+/// Implements `hifumi::Versioned` for the current type, and `hifumi::MigrateInto`
+/// and `hifumi::MigrateIntoOwned` from every historical version to each version it
+/// can be migrated into.
+pub(crate) fn generate_impl_traits(
+    ident: Ident,
+    generics: Generics,
+    graph: &MigrationGraph,
+) -> Result<TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let current_version = LitStr::new(&graph.current, Span::call_site());
+    let supported_versions = graph
+        .versions
+        .iter()
+        .map(|version| LitStr::new(version, Span::call_site()));
+
+    let impl_versioned = quote! {
+        impl #impl_generics ::hifumi::Versioned for #ident #ty_generics #where_clause {
+            const CURRENT_VERSION: &'static str = #current_version;
+            const SUPPORTED_VERSIONS: &'static [&'static str] = &[#(#supported_versions),*];
+        }
+    };
+
+    // The hop into the current version lands on the user type itself, so it stands
+    // in for the last historical version of every path
+    let version_ty = |version: &str| -> Result<TokenStream> {
+        if version == graph.current {
+            Ok(quote! { #ident #ty_generics })
+        } else {
+            let name = generate_ident(&ident, version)?;
+            Ok(quote! { #name #ty_generics })
+        }
+    };
+    let final_ident = generate_ident(&ident, &graph.current)?;
+
+    // `migrate_into` moves the value, while `migrate` migrates a copy of it and only
+    // exists for versions that can be cloned, which the lifetime keeps from being a
+    // trivial bound
+    let impl_migrate = |from_ty: &TokenStream, to_ty: &TokenStream, body: TokenStream| {
+        let clone_generics = with_bound(&generics, quote! { for<'__c> #from_ty }, quote! { Clone });
+        let (_, _, clone_where_clause) = clone_generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics ::hifumi::MigrateIntoOwned<#to_ty> for #from_ty #where_clause {
+                fn migrate_into(self) -> ::hifumi::__private::Result<#to_ty> {
+                    #body
+                }
+            }
+
+            impl #impl_generics ::hifumi::MigrateInto<#to_ty> for #from_ty #clone_where_clause {
+                fn migrate(&self) -> ::hifumi::__private::Result<#to_ty> {
+                    ::hifumi::MigrateIntoOwned::<#to_ty>::migrate_into(::core::clone::Clone::clone(self))
+                }
+            }
+        }
+    };
+
+    let mut impl_migrate_into = vec![];
+    for from in graph.versions.iter().skip(1) {
+        let from_ty = version_ty(from)?;
+
+        for to in graph.versions.iter().filter(|to| *to != from) {
+            let Some(path) = graph.path(from, to) else {
+                continue;
+            };
+            let hops = path
+                .iter()
+                .map(|item| {
+                    let ty = version_ty(&item.to.value())?;
+                    Ok(quote! {
+                        let __value: #ty = ::core::convert::TryFrom::try_from(__value)?;
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let to_ty = version_ty(to)?;
+
+            impl_migrate_into.push(impl_migrate(
+                &from_ty,
+                &to_ty,
+                quote! {
+                    let __value = self;
+                    #(#hops)*
+                    Ok(__value)
+                },
+            ));
+        }
+
+        impl_migrate_into.push(impl_migrate(
+            &from_ty,
+            &quote! { #final_ident #ty_generics },
+            quote! {
+                let __value: #ident #ty_generics =
+                    ::hifumi::MigrateIntoOwned::<#ident #ty_generics>::migrate_into(self)?;
+                Ok(__value.into())
+            },
+        ));
+    }
+
+    Ok(quote! {
+        #impl_versioned
+        #(#impl_migrate_into)*
+    })
+}
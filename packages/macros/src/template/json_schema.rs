@@ -0,0 +1,159 @@
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Generics, Ident};
+
+use crate::{
+    tools::{DeriveVersion, VersionRepr},
+    utils::{generate_ident, version_id, with_bound},
+};
+
+use super::schema_builder::{SchemaField, SchemaPayload, SchemaShape, VersionSchema};
+
+fn object(fields: &[SchemaField], additional: bool) -> TokenStream {
+    let properties = fields.iter().map(|field| {
+        let SchemaField { name, ty, required } = field;
+        quote! {
+            (#name, <#ty as ::hifumi::JsonSchema>::json_schema(), #required)
+        }
+    });
+
+    quote! {
+        ::hifumi::json_schema::object(vec![#(#properties),*], #additional)
+    }
+}
+
+/// Wraps the schema of `version` in its version tag, as `repr` writes it.
+fn tagged(attr: &DeriveVersion, version: &str, schema: TokenStream) -> TokenStream {
+    let tag = &attr.tag;
+
+    match &attr.repr {
+        VersionRepr::Internal => quote! {
+            ::hifumi::json_schema::internal(#tag, #version, #schema)
+        },
+        VersionRepr::Adjacent { content } => quote! {
+            ::hifumi::json_schema::adjacent(#tag, #content, #version, #schema)
+        },
+        VersionRepr::External => quote! {
+            ::hifumi::json_schema::external(#version, #schema)
+        },
+        VersionRepr::Envelope { id: false } => quote! {
+            ::hifumi::json_schema::envelope(#version, #schema)
+        },
+        VersionRepr::Xml => unreachable!("`json_schema` is rejected with `repr = \"xml\"`"),
+        VersionRepr::Envelope { id: true } => {
+            let id = version_id(version);
+            quote! {
+                ::hifumi::json_schema::envelope(#id, #schema)
+            }
+        }
+    }
+}
+
+/// Implements `hifumi::JsonSchema` for every historical version, and for the type
+/// itself with a `oneOf` of every version wrapped in its version tag.
+pub(crate) fn generate_json_schema(
+    attr: &DeriveVersion,
+    ident: &Ident,
+    generics: &Generics,
+    schemas: &[VersionSchema],
+) -> Result<TokenStream> {
+    let bounded = generics
+        .type_params()
+        .fold(generics.clone(), |generics, param| {
+            let param = &param.ident;
+            with_bound(
+                &generics,
+                quote! { #param },
+                quote! { ::hifumi::JsonSchema },
+            )
+        });
+    let (impl_generics, _, where_clause) = bounded.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let title = ident.to_string();
+
+    let mut impls = vec![];
+    let mut versions = vec![];
+    for VersionSchema { version, shape } in schemas {
+        let version_ident = generate_ident(ident, version)?;
+        let schema = match shape {
+            SchemaShape::Struct { fields, additional } => object(fields, *additional),
+            SchemaShape::Enum { variants } => {
+                let variants = variants.iter().map(|variant| {
+                    let name = &variant.name;
+                    let payload = match &variant.payload {
+                        SchemaPayload::Unit => quote! { None },
+                        SchemaPayload::Newtype(ty) => quote! {
+                            Some(<#ty as ::hifumi::JsonSchema>::json_schema())
+                        },
+                        SchemaPayload::Tuple(types) => quote! {
+                            Some(::hifumi::json_schema::tuple(vec![
+                                #(<#types as ::hifumi::JsonSchema>::json_schema()),*
+                            ]))
+                        },
+                        SchemaPayload::Struct(fields) => {
+                            let object = object(fields, true);
+                            quote! { Some(#object) }
+                        }
+                    };
+                    quote! {
+                        ::hifumi::json_schema::variant(#name, #payload)
+                    }
+                });
+                quote! {
+                    ::hifumi::json_schema::one_of(vec![#(#variants),*])
+                }
+            }
+        };
+
+        impls.push(quote! {
+            impl #impl_generics ::hifumi::JsonSchema for #version_ident #ty_generics #where_clause {
+                fn json_schema() -> ::hifumi::json_schema::Value {
+                    #schema
+                }
+            }
+        });
+        versions.push((
+            version,
+            tagged(
+                attr,
+                version,
+                quote! { <#version_ident #ty_generics as ::hifumi::JsonSchema>::json_schema() },
+            ),
+        ));
+    }
+
+    let any_version = versions.iter().map(|(_, schema)| schema);
+    let documents = versions.iter().map(|(version, schema)| {
+        quote! {
+            (#version, ::hifumi::json_schema::document(#title, Some(#version), #schema))
+        }
+    });
+
+    Ok(quote! {
+        #(#impls)*
+
+        impl #impl_generics ::hifumi::JsonSchema for #ident #ty_generics #where_clause {
+            fn json_schema() -> ::hifumi::json_schema::Value {
+                ::hifumi::json_schema::one_of(vec![#(#any_version),*])
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The JSON Schema document of every version, the current one first,
+            /// identified by its version.
+            pub fn json_schemas() -> Vec<(&'static str, ::hifumi::json_schema::Value)> {
+                vec![#(#documents),*]
+            }
+
+            /// The JSON Schema document accepting any version, tagged as declared.
+            pub fn json_schema_document() -> ::hifumi::json_schema::Value {
+                ::hifumi::json_schema::document(
+                    #title,
+                    None,
+                    <Self as ::hifumi::JsonSchema>::json_schema(),
+                )
+            }
+        }
+    })
+}
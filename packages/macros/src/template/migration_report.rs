@@ -0,0 +1,122 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::tools::{MigrationField, MigrationGraph, MigrationVariant};
+
+/// The `hifumi::AppliedRule`s of the field rules of a hop, whose fields are named
+/// with `prefix` in front.
+fn field_rules(changes: &[MigrationField], prefix: &str) -> Vec<TokenStream> {
+    let name = |key: &dyn ToString| format!("{}{}", prefix, key.to_string());
+
+    changes
+        .iter()
+        .map(|rule| match rule {
+            MigrationField::Add { value, converter } => {
+                let field = name(&value.0);
+                match converter {
+                    Some(_) => quote! {
+                        ::hifumi::AppliedRule::Converted { field: #field, from: &[] }
+                    },
+                    None => quote! {
+                        ::hifumi::AppliedRule::Defaulted { field: #field }
+                    },
+                }
+            }
+            MigrationField::Remove { value, .. } => {
+                let field = name(&value.0);
+                quote! {
+                    ::hifumi::AppliedRule::Removed { field: #field }
+                }
+            }
+            MigrationField::Rename {
+                source,
+                target,
+                converter,
+                ..
+            }
+            | MigrationField::Copy {
+                source,
+                target,
+                converter,
+                ..
+            } => {
+                let field = name(&target.0);
+                let from = source.iter().map(|(key, _)| name(key)).collect::<Vec<_>>();
+                match (converter, from.as_slice()) {
+                    (None, [from]) if matches!(rule, MigrationField::Rename { .. }) => quote! {
+                        ::hifumi::AppliedRule::Renamed { field: #field, from: #from }
+                    },
+                    (None, [from]) => quote! {
+                        ::hifumi::AppliedRule::Copied { field: #field, from: #from }
+                    },
+                    _ => quote! {
+                        ::hifumi::AppliedRule::Converted { field: #field, from: &[#(#from),*] }
+                    },
+                }
+            }
+        })
+        .collect()
+}
+
+/// Builds the `hifumi::MigrationReport` of a value read as `version`.
+pub(crate) fn generate_migration_report(graph: &MigrationGraph, version: &str) -> TokenStream {
+    let hops = graph
+        .path(version, &graph.current)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| {
+            let from = item.from.value();
+            let to = item.to.value();
+            let mut rules = field_rules(&item.changes, "");
+            for rule in item.variants.iter() {
+                match rule {
+                    MigrationVariant::Add { variant } => {
+                        let variant = variant.to_string();
+                        rules.push(quote! {
+                            ::hifumi::AppliedRule::VariantAdded { variant: #variant }
+                        });
+                    }
+                    MigrationVariant::Remove {
+                        variant, converter, ..
+                    } => {
+                        let variant = variant.to_string();
+                        let converted = converter.is_some();
+                        rules.push(quote! {
+                            ::hifumi::AppliedRule::VariantRemoved {
+                                variant: #variant,
+                                converted: #converted,
+                            }
+                        });
+                    }
+                    MigrationVariant::Rename { source, target, .. } => {
+                        let variant = target.to_string();
+                        let from = source.iter().map(ToString::to_string);
+                        rules.push(quote! {
+                            ::hifumi::AppliedRule::VariantRenamed {
+                                variant: #variant,
+                                from: &[#(#from),*],
+                            }
+                        });
+                    }
+                    MigrationVariant::Fields { variant, changes } => {
+                        rules.extend(field_rules(changes, &format!("{}.", variant)));
+                    }
+                }
+            }
+
+            quote! {
+                ::hifumi::MigrationHop {
+                    from: #from,
+                    to: #to,
+                    rules: vec![#(#rules),*],
+                }
+            }
+        });
+
+    quote! {
+        ::hifumi::MigrationReport {
+            source: #version,
+            hops: vec![#(#hops),*],
+        }
+    }
+}
@@ -1,8 +1,33 @@
+pub(crate) mod container_attrs;
+pub(crate) mod context_visitor;
 pub(crate) mod current_version_struct;
+pub(crate) mod impl_downgrade;
 pub(crate) mod impl_migration;
+pub(crate) mod impl_traits;
+pub(crate) mod json_schema;
+pub(crate) mod migration_report;
+pub(crate) mod old_version_enums;
 pub(crate) mod old_version_structs;
 pub(crate) mod schema_builder;
+pub(crate) mod serialize_proxy;
+pub(crate) mod specta;
+pub(crate) mod xml_schema;
 
+pub(crate) use container_attrs::{
+    describe_fields, describe_variants, generate_old_version_container_attrs,
+    generate_old_version_docs,
+};
+pub(crate) use context_visitor::generate_context_visitors;
 pub(crate) use current_version_struct::generate_current_version_struct;
-pub(crate) use impl_migration::generate_impl_froms;
-pub(crate) use old_version_structs::generate_old_version_structs;
+pub(crate) use impl_downgrade::{generate_impl_downgrades, generate_impl_enum_downgrades};
+pub(crate) use impl_migration::{generate_impl_enum_froms, generate_impl_froms};
+pub(crate) use impl_traits::generate_impl_traits;
+pub(crate) use json_schema::generate_json_schema;
+pub(crate) use old_version_enums::{generate_old_enum_versions, generate_old_version_enums};
+pub(crate) use old_version_structs::{
+    generate_old_version_attrs, generate_old_version_structs, generate_old_versions,
+    StructVisibility,
+};
+pub(crate) use schema_builder::{build_enum_schemas, build_struct_schemas};
+pub(crate) use specta::{generate_specta, generate_specta_derives};
+pub(crate) use xml_schema::generate_xml_schema;
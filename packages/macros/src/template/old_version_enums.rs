@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Result};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::BTreeMap;
+use syn::{Generics, Ident, Visibility};
+
+use crate::{
+    tools::{MigrationGraph, MigrationVariant, VariantData, VariantShape},
+    utils::{generate_ident, phantom_type},
+};
+
+use super::old_version_structs::{infer_older_version_attrs, infer_older_version_struct};
+
+pub(crate) fn infer_older_version_enum(
+    newer_enum_variants: BTreeMap<Ident, VariantData>,
+    convert_rules: Vec<MigrationVariant>,
+) -> Result<BTreeMap<Ident, VariantData>> {
+    let mut enum_variants = newer_enum_variants.clone();
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationVariant::Add { variant } => {
+                enum_variants
+                    .remove(variant)
+                    .ok_or_else(|| anyhow!("Cannot find the added variant {}", variant))?;
+            }
+            MigrationVariant::Remove { variant, shape, .. } => {
+                enum_variants.insert(
+                    variant.clone(),
+                    VariantData {
+                        attrs: vec![],
+                        shape: shape.clone(),
+                        field_attrs: BTreeMap::new(),
+                    },
+                );
+            }
+            MigrationVariant::Rename { source, target, .. } => {
+                let target_data = newer_enum_variants
+                    .get(target)
+                    .ok_or_else(|| anyhow!("Cannot find the renamed variant {}", target))?;
+                enum_variants.remove(target);
+
+                for ident in source.iter() {
+                    enum_variants.insert(
+                        ident.clone(),
+                        VariantData {
+                            // Merged variants cannot all keep attributes like `#[default]`
+                            attrs: if source.len() == 1 {
+                                target_data.attrs.clone()
+                            } else {
+                                vec![]
+                            },
+                            shape: target_data.shape.clone(),
+                            field_attrs: target_data.field_attrs.clone(),
+                        },
+                    );
+                }
+            }
+            MigrationVariant::Fields { variant, changes } => {
+                let variant_data = enum_variants
+                    .get_mut(variant)
+                    .ok_or_else(|| anyhow!("Cannot find the migrated variant {}", variant))?;
+
+                match &variant_data.shape {
+                    VariantShape::Named(fields) => {
+                        variant_data.shape = VariantShape::Named(infer_older_version_struct(
+                            fields.clone(),
+                            changes.clone(),
+                        )?);
+                        variant_data.field_attrs = infer_older_version_attrs(
+                            variant_data.field_attrs.clone(),
+                            changes,
+                        );
+                    }
+                    _ => {
+                        return Err(anyhow!(
+                            "Field migrations can only be applied to struct-like variants, but {} is not",
+                            variant
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(enum_variants)
+}
+
+/// Infers the variants of every version along its route, in the order of
+/// `graph.versions`, and checks that the other migrations leaving a version agree
+/// on its variants.
+pub(crate) fn generate_old_enum_versions(
+    final_enum_variants: BTreeMap<Ident, VariantData>,
+    graph: &MigrationGraph,
+) -> Result<Vec<(String, BTreeMap<Ident, VariantData>)>> {
+    let mut old_version_enums = BTreeMap::from([(graph.current.clone(), final_enum_variants)]);
+
+    for version in graph.versions.iter().skip(1) {
+        let item = graph
+            .route(version)
+            .ok_or_else(|| anyhow!("Cannot find the route of version {}", version))?;
+        let enum_variants = infer_older_version_enum(
+            old_version_enums[&item.to.value()].clone(),
+            item.variants.clone(),
+        )?;
+        old_version_enums.insert(version.clone(), enum_variants);
+    }
+
+    for version in graph.versions.iter().skip(1) {
+        let expected = variants_signature(&old_version_enums[version]);
+
+        for item in graph.outgoing(version).into_iter().skip(1) {
+            let enum_variants = infer_older_version_enum(
+                old_version_enums[&item.to.value()].clone(),
+                item.variants.clone(),
+            )?;
+
+            if variants_signature(&enum_variants) != expected {
+                return Err(syn::Error::new_spanned(
+                    &item.from,
+                    format!(
+                        "The migration to \"{}\" does not match the variants of version \"{}\" inferred from the migration to \"{}\"",
+                        item.to.value(),
+                        version,
+                        graph.route(version).map(|route| route.to.value()).unwrap_or_default()
+                    ),
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(graph
+        .versions
+        .iter()
+        .map(|version| (version.clone(), old_version_enums[version].clone()))
+        .collect())
+}
+
+fn variants_signature(enum_variants: &BTreeMap<Ident, VariantData>) -> Vec<(String, String)> {
+    enum_variants
+        .iter()
+        .map(|(variant, data)| {
+            (
+                variant.to_string(),
+                data.shape.declaration(&BTreeMap::new()).to_string(),
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn generate_old_version_enums(
+    ident: Ident,
+    generics: Generics,
+    vis: &Visibility,
+    old_version_enums: &[(String, BTreeMap<Ident, VariantData>)],
+    extra_macros: Vec<TokenStream>,
+    container_attrs: &BTreeMap<String, Vec<TokenStream>>,
+) -> Result<TokenStream> {
+    let where_clause = &generics.where_clause;
+    // The variant can never be constructed, it only marks the generics as used
+    let phantom = phantom_type(&generics).map(|ty| {
+        quote! {
+            #[serde(skip)]
+            __Phantom(#ty, ::core::convert::Infallible),
+        }
+    });
+    let old_version_enums = old_version_enums
+        .iter()
+        .map(|(version, variants)| {
+            let enum_name = generate_ident(&ident, version)?;
+            let container_attrs = &container_attrs[version];
+            if let Some(attr) = variants
+                .values()
+                .flat_map(|data| data.field_attrs.values().flatten())
+                .find(|attr| attr.path().is_ident("version"))
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[version(context)]` can only be used on the fields of structs",
+                )
+                .into());
+            }
+            let variants = variants.iter().map(|(ident, data)| {
+                let attrs = &data.attrs;
+                let declaration = data.declaration();
+
+                quote! {
+                    #(#attrs)*
+                    #ident #declaration,
+                }
+            });
+
+            Ok(quote! {
+                #[allow(non_camel_case_types, unused_variables, dead_code)]
+                #(#extra_macros)*
+                #[derive(::serde::Serialize, ::serde::Deserialize)]
+                #(#container_attrs)*
+                #vis enum #enum_name #generics #where_clause {
+                    #(#variants)*
+                    #phantom
+                }
+            })
+        })
+        .collect::<Vec<Result<TokenStream>>>()
+        .into_iter()
+        .collect::<Result<Vec<TokenStream>>>()?;
+
+    Ok(quote! {
+        #(#old_version_enums)*
+    })
+}
@@ -1,83 +1,227 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, ToTokens};
 use std::collections::BTreeMap;
-use syn::{Ident, Type};
+use syn::{Attribute, Generics, Ident, Type, Visibility};
 
-use crate::{tools::MigrationField, utils::generate_ident};
+use crate::{
+    tools::{FieldKey, MigrationField, MigrationGraph},
+    utils::{generate_ident, phantom_type, strip_version_attrs},
+};
 
 pub(crate) fn infer_older_version_struct(
-    newer_struct_fields: BTreeMap<Ident, Type>,
+    newer_struct_fields: BTreeMap<FieldKey, Type>,
     convert_rules: Vec<MigrationField>,
-) -> Result<BTreeMap<Ident, Type>> {
+) -> Result<BTreeMap<FieldKey, Type>> {
     let mut struct_fields = newer_struct_fields.clone();
 
+    // Drop every field introduced by this migration first, so that the rules can
+    // shift keys around (e.g. `+ 0: u32, 0 => 1: String`) regardless of their order
     for rule in convert_rules.iter() {
         match rule {
             MigrationField::Add { value, .. } => {
                 let (key, _) = value;
                 struct_fields.remove(key);
             }
-            MigrationField::Copy { source, target, .. } => {
+            MigrationField::Copy { target, .. } | MigrationField::Rename { target, .. } => {
                 let (target_ident, _) = target;
                 struct_fields.remove(target_ident);
+            }
+            MigrationField::Remove { .. } => {}
+        }
+    }
 
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Add { .. } => {}
+            MigrationField::Copy { source, .. } | MigrationField::Rename { source, .. } => {
                 for (ident, ty) in source.iter() {
-                    struct_fields.insert(ident.clone(), Type::Path(ty.clone()));
+                    struct_fields.insert(ident.clone(), ty.clone());
                 }
             }
-            MigrationField::Remove { value } => {
+            MigrationField::Remove { value, .. } => {
                 let (ident, ty) = value;
-                struct_fields.insert(ident.clone(), Type::Path(ty.clone()));
+                struct_fields.insert(ident.clone(), ty.clone());
             }
-            MigrationField::Rename { source, target, .. } => {
-                let (target_ident, _) = target;
-                struct_fields.remove(target_ident);
+        }
+    }
 
-                for (ident, ty) in source.iter() {
-                    struct_fields.insert(ident.clone(), Type::Path(ty.clone()));
+    Ok(struct_fields.into_iter().collect())
+}
+
+/// Infers the fields of every version along its route, in the order of
+/// `graph.versions`, and checks that the other migrations leaving a version agree
+/// on its fields.
+pub(crate) fn generate_old_versions(
+    final_struct_fields: BTreeMap<FieldKey, Type>,
+    graph: &MigrationGraph,
+) -> Result<Vec<(String, BTreeMap<FieldKey, Type>)>> {
+    let mut old_version_structs = BTreeMap::from([(graph.current.clone(), final_struct_fields)]);
+
+    for version in graph.versions.iter().skip(1) {
+        let item = graph
+            .route(version)
+            .ok_or_else(|| anyhow!("Cannot find the route of version {}", version))?;
+        let struct_fields = infer_older_version_struct(
+            old_version_structs[&item.to.value()].clone(),
+            item.changes.clone(),
+        )?;
+        old_version_structs.insert(version.clone(), struct_fields);
+    }
+
+    for version in graph.versions.iter().skip(1) {
+        let expected = fields_signature(&old_version_structs[version]);
+
+        for item in graph.outgoing(version).into_iter().skip(1) {
+            let struct_fields = infer_older_version_struct(
+                old_version_structs[&item.to.value()].clone(),
+                item.changes.clone(),
+            )?;
+
+            if fields_signature(&struct_fields) != expected {
+                return Err(syn::Error::new_spanned(
+                    &item.from,
+                    format!(
+                        "The migration to \"{}\" does not match the fields of version \"{}\" inferred from the migration to \"{}\"",
+                        item.to.value(),
+                        version,
+                        graph.route(version).map(|route| route.to.value()).unwrap_or_default()
+                    ),
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(graph
+        .versions
+        .iter()
+        .map(|version| (version.clone(), old_version_structs[version].clone()))
+        .collect())
+}
+
+/// Infers the attributes of every field of the older version. A field keeps its
+/// attributes as long as it is left untouched, while the fields brought back by a
+/// rule get the attributes written in front of that rule.
+pub(crate) fn infer_older_version_attrs(
+    newer_field_attrs: BTreeMap<FieldKey, Vec<Attribute>>,
+    convert_rules: &[MigrationField],
+) -> BTreeMap<FieldKey, Vec<Attribute>> {
+    let mut field_attrs = newer_field_attrs;
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Add { value, .. } => {
+                let (key, _) = value;
+                field_attrs.remove(key);
+            }
+            MigrationField::Copy { target, .. } | MigrationField::Rename { target, .. } => {
+                let (key, _) = target;
+                field_attrs.remove(key);
+            }
+            MigrationField::Remove { .. } => {}
+        }
+    }
+
+    for rule in convert_rules.iter() {
+        match rule {
+            MigrationField::Add { .. } => {}
+            MigrationField::Copy { attrs, source, .. } => {
+                // Copied fields still exist in the newer version, so they only
+                // change their attributes when asked to
+                for (key, _) in source.iter() {
+                    if !attrs.is_empty() || !field_attrs.contains_key(key) {
+                        field_attrs.insert(key.clone(), attrs.clone());
+                    }
                 }
             }
+            MigrationField::Rename { attrs, source, .. } => {
+                for (key, _) in source.iter() {
+                    field_attrs.insert(key.clone(), attrs.clone());
+                }
+            }
+            MigrationField::Remove { attrs, value, .. } => {
+                let (key, _) = value;
+                field_attrs.insert(key.clone(), attrs.clone());
+            }
         }
     }
 
-    Ok(struct_fields.into_iter().collect())
+    field_attrs
 }
 
-pub(crate) fn generate_old_versions(
-    final_version: String,
-    final_struct_fields: BTreeMap<Ident, Type>,
-    versions: Vec<(String, Vec<MigrationField>, String)>,
-) -> Result<Vec<(String, BTreeMap<Ident, Type>)>> {
-    let mut temp_struct_fields = final_struct_fields.to_owned();
-    let mut old_version_structs = vec![(final_version, temp_struct_fields.clone())];
-
-    for (from_version, convert_rules, _to_version) in versions.iter() {
-        temp_struct_fields =
-            infer_older_version_struct(temp_struct_fields.clone(), convert_rules.to_owned())?;
-        old_version_structs.push((from_version.clone(), temp_struct_fields.clone()));
+/// Infers the attributes of the fields of every version along its route.
+pub(crate) fn generate_old_version_attrs(
+    final_field_attrs: BTreeMap<FieldKey, Vec<Attribute>>,
+    graph: &MigrationGraph,
+) -> Result<BTreeMap<String, BTreeMap<FieldKey, Vec<Attribute>>>> {
+    let mut old_version_attrs = BTreeMap::from([(graph.current.clone(), final_field_attrs)]);
+
+    for version in graph.versions.iter().skip(1) {
+        let item = graph
+            .route(version)
+            .ok_or_else(|| anyhow!("Cannot find the route of version {}", version))?;
+        let field_attrs =
+            infer_older_version_attrs(old_version_attrs[&item.to.value()].clone(), &item.changes);
+        old_version_attrs.insert(version.clone(), field_attrs);
     }
 
-    Ok(old_version_structs)
+    Ok(old_version_attrs)
+}
+
+fn fields_signature(struct_fields: &BTreeMap<FieldKey, Type>) -> Vec<(String, String)> {
+    struct_fields
+        .iter()
+        .map(|(key, ty)| (key.to_string(), ty.to_token_stream().to_string()))
+        .collect()
+}
+
+/// The visibility of the historical structs and of their fields. A field keeps the
+/// visibility of the field with the same key on the current struct, while the
+/// fields the current struct no longer has get the visibility of the struct.
+pub(crate) struct StructVisibility<'a> {
+    pub vis: &'a Visibility,
+    pub fields: BTreeMap<FieldKey, Visibility>,
+}
+
+impl StructVisibility<'_> {
+    fn field(&self, key: &FieldKey) -> &Visibility {
+        self.fields.get(key).unwrap_or(self.vis)
+    }
 }
 
 pub(crate) fn generate_old_version_structs(
     ident: Ident,
-    final_version: String,
-    final_struct_fields: BTreeMap<Ident, Type>,
+    generics: Generics,
+    visibility: &StructVisibility,
+    old_version_structs: &[(String, BTreeMap<FieldKey, Type>)],
+    old_version_attrs: &BTreeMap<String, BTreeMap<FieldKey, Vec<Attribute>>>,
     extra_macros: Vec<TokenStream>,
-    versions: Vec<(String, Vec<MigrationField>, String)>,
+    container_attrs: &BTreeMap<String, Vec<TokenStream>>,
 ) -> Result<TokenStream> {
-    let old_version_structs =
-        generate_old_versions(final_version.clone(), final_struct_fields, versions)?;
-
+    let vis = visibility.vis;
+    let where_clause = &generics.where_clause;
+    let phantom = phantom_type(&generics).map(|ty| {
+        quote! {
+            #[serde(skip)]
+            __phantom: #ty,
+        }
+    });
     let old_version_structs = old_version_structs
         .iter()
         .map(|(version, fields)| {
             let struct_name = generate_ident(&ident, version)?;
-            let fields = fields.iter().map(|(ident, ty)| {
+            let container_attrs = &container_attrs[version];
+            let field_attrs = &old_version_attrs[version];
+            let fields = fields.iter().map(|(key, ty)| {
+                let serde_attr = key.serde_attr();
+                let mut attrs = field_attrs.get(key).cloned().unwrap_or_default();
+                strip_version_attrs(&mut attrs);
+                let vis = visibility.field(key);
                 quote! {
-                    #ident: #ty,
+                    #(#attrs)*
+                    #serde_attr
+                    #vis #key: #ty,
                 }
             });
 
@@ -85,8 +229,10 @@ pub(crate) fn generate_old_version_structs(
                 #[allow(non_camel_case_types, unused_variables, dead_code)]
                 #(#extra_macros)*
                 #[derive(::serde::Serialize, ::serde::Deserialize)]
-                struct #struct_name {
+                #(#container_attrs)*
+                #vis struct #struct_name #generics #where_clause {
                     #(#fields)*
+                    #phantom
                 }
             })
         })
@@ -1,2 +1,201 @@
-// TODO: 对于 JSON schema，version 宏的参数即为 $id 的值，并且每个版本均允许独立生成
-//       对于 XML schema，version 宏的参数即为 targetNamespace 的值，并且每个版本均允许独立生成
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use std::collections::BTreeMap;
+use syn::{parse::Parser, Attribute, Ident, Type};
+
+use crate::{
+    tools::{FieldKey, VariantData, VariantShape},
+    utils::{RenameRule, SerdeAttrs},
+};
+
+/// A field of a version, named as it is read.
+#[derive(Debug, Clone)]
+pub(crate) struct SchemaField {
+    pub name: String,
+    pub ty: Type,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum SchemaPayload {
+    Unit,
+    Newtype(Box<Type>),
+    Tuple(Vec<Type>),
+    Struct(Vec<SchemaField>),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SchemaVariant {
+    pub name: String,
+    pub payload: SchemaPayload,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum SchemaShape {
+    /// `additional` tells whether keys no field describes are accepted.
+    Struct {
+        fields: Vec<SchemaField>,
+        additional: bool,
+    },
+    Enum {
+        variants: Vec<SchemaVariant>,
+    },
+}
+
+/// The shape of a historical version as it is read, which the schema generators
+/// describe in their own formats.
+#[derive(Debug, Clone)]
+pub(crate) struct VersionSchema {
+    pub version: String,
+    pub shape: SchemaShape,
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        Type::Group(group) => is_option(&group.elem),
+        Type::Paren(paren) => is_option(&paren.elem),
+        _ => false,
+    }
+}
+
+pub(crate) fn parse_container_attrs(attrs: &[TokenStream]) -> Result<SerdeAttrs> {
+    let attrs = attrs
+        .iter()
+        .map(|tokens| Attribute::parse_outer.parse2(tokens.clone()))
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    Ok(SerdeAttrs::parse(&attrs)?)
+}
+
+pub(crate) fn field_name(
+    key: &FieldKey,
+    attrs: &SerdeAttrs,
+    rename_all: Option<RenameRule>,
+) -> String {
+    match (key, &attrs.rename, rename_all) {
+        (_, Some(rename), _) => rename.clone(),
+        (FieldKey::Index(index), _, _) => index.to_string(),
+        (FieldKey::Named(ident), _, Some(rule)) => rule.apply_to_field(&ident.to_string()),
+        (FieldKey::Named(ident), _, None) => ident.to_string(),
+    }
+}
+
+/// The shape of every version of a struct, from the field maps computed by
+/// `generate_old_versions` and `generate_old_version_attrs`.
+pub(crate) fn build_struct_schemas(
+    old_versions: &[(String, BTreeMap<FieldKey, Type>)],
+    old_version_attrs: &BTreeMap<String, BTreeMap<FieldKey, Vec<Attribute>>>,
+    container_attrs: &BTreeMap<String, Vec<TokenStream>>,
+) -> Result<Vec<VersionSchema>> {
+    old_versions
+        .iter()
+        .map(|(version, fields)| {
+            let container = parse_container_attrs(&container_attrs[version])?;
+            let mut additional = !container.deny_unknown_fields;
+
+            let mut schema_fields = vec![];
+            for (key, ty) in fields {
+                let attrs = SerdeAttrs::parse(
+                    old_version_attrs[version]
+                        .get(key)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                )?;
+                if attrs.skip {
+                    continue;
+                }
+                // The keys of a flattened field cannot be listed
+                if attrs.flatten {
+                    additional = true;
+                    continue;
+                }
+
+                schema_fields.push(SchemaField {
+                    name: field_name(key, &attrs, container.rename_all),
+                    ty: ty.clone(),
+                    required: !attrs.optional && !is_option(ty),
+                });
+            }
+
+            Ok(VersionSchema {
+                version: version.clone(),
+                shape: SchemaShape::Struct {
+                    fields: schema_fields,
+                    additional,
+                },
+            })
+        })
+        .collect()
+}
+
+/// The shape of every version of an enum, from the variant maps computed by
+/// `generate_old_enum_versions`.
+pub(crate) fn build_enum_schemas(
+    old_versions: &[(String, BTreeMap<Ident, VariantData>)],
+    container_attrs: &BTreeMap<String, Vec<TokenStream>>,
+) -> Result<Vec<VersionSchema>> {
+    old_versions
+        .iter()
+        .map(|(version, variants)| {
+            let container = parse_container_attrs(&container_attrs[version])?;
+
+            let mut schema_variants = vec![];
+            for (ident, data) in variants {
+                let attrs = SerdeAttrs::parse(&data.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                let name = match (attrs.rename.clone(), container.rename_all) {
+                    (Some(rename), _) => rename,
+                    (None, Some(rule)) => rule.apply_to_variant(&ident.to_string()),
+                    (None, None) => ident.to_string(),
+                };
+                let payload = match &data.shape {
+                    VariantShape::Unit => SchemaPayload::Unit,
+                    VariantShape::Tuple(types) if types.len() == 1 => {
+                        SchemaPayload::Newtype(Box::new(types[0].clone()))
+                    }
+                    VariantShape::Tuple(types) => SchemaPayload::Tuple(types.clone()),
+                    VariantShape::Named(fields) => {
+                        let mut schema_fields = vec![];
+                        for (key, ty) in fields {
+                            let field_attrs = SerdeAttrs::parse(
+                                data.field_attrs
+                                    .get(key)
+                                    .map(Vec::as_slice)
+                                    .unwrap_or_default(),
+                            )?;
+                            if field_attrs.skip || field_attrs.flatten {
+                                continue;
+                            }
+
+                            schema_fields.push(SchemaField {
+                                name: field_name(key, &field_attrs, attrs.rename_all),
+                                ty: ty.clone(),
+                                required: !field_attrs.optional && !is_option(ty),
+                            });
+                        }
+                        SchemaPayload::Struct(schema_fields)
+                    }
+                };
+
+                schema_variants.push(SchemaVariant { name, payload });
+            }
+
+            Ok(VersionSchema {
+                version: version.clone(),
+                shape: SchemaShape::Enum {
+                    variants: schema_variants,
+                },
+            })
+        })
+        .collect()
+}
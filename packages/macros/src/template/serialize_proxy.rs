@@ -0,0 +1,171 @@
+use anyhow::Result;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Attribute, Fields, GenericParam, Generics, Ident, Lifetime, LifetimeParam, LitStr,
+    Visibility,
+};
+
+use crate::tools::{FieldKey, MigrationData};
+
+fn serde_attrs(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|attr| attr.path().is_ident("serde"));
+}
+
+/// Orders named fields by their keys, as the historical versions declare them, so
+/// that formats which write fields and variants by position agree with them.
+fn sort_fields(fields: &mut Fields) {
+    if let Fields::Named(fields) = fields {
+        let mut named = fields.named.clone().into_iter().collect::<Vec<_>>();
+        named.sort_by_key(|field| field.ident.as_ref().map(ToString::to_string));
+        fields.named = named.into_iter().collect();
+    }
+}
+
+/// The generics of the proxy, which borrows the current type for `'__a`.
+pub(crate) fn proxy_generics(generics: &Generics) -> Generics {
+    let mut generics = generics.clone();
+    generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new("'__a", Span::call_site()))),
+    );
+    generics
+}
+
+/// Borrows the current type as its current version, so that it is serialized
+/// without being cloned. The proxy is built with `__TestRef::new(self)`.
+///
+/// Named structs and enums are mirrored by a `#[serde(remote = "...")]` copy of the
+/// type, which reads every field in place with the same attributes and order as the
+/// historical twin of the current version. A tuple struct is serialized as a map
+/// like its historical versions, which a remote derive cannot express, so it is
+/// mirrored by a struct of references instead.
+pub(crate) fn generate_serialize_proxy(
+    ident: &Ident,
+    data: &MigrationData,
+    container_attrs: &[TokenStream],
+) -> Result<TokenStream> {
+    let proxy_ident = format_ident!("__{}Ref", ident);
+    let remote_ident = format_ident!("__{}Remote", ident);
+    let remote = LitStr::new(&ident.to_string(), Span::call_site());
+
+    let generics = data.generics();
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let proxy_generics = proxy_generics(generics);
+    let (proxy_impl_generics, proxy_ty_generics, _) = proxy_generics.split_for_impl();
+
+    if let MigrationData::Struct(item) = data {
+        if let Fields::Unnamed(fields) = &item.fields {
+            let fields = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                let key = FieldKey::Index(index as u32);
+                let serde_attr = key.serde_attr();
+                let mut attrs = field.attrs.clone();
+                serde_attrs(&mut attrs);
+                let ty = &field.ty;
+
+                (
+                    quote! {
+                        #(#attrs)*
+                        #serde_attr
+                        #key: &'__a #ty,
+                    },
+                    {
+                        let member = key.member();
+                        quote! { #key: &value.#member, }
+                    },
+                )
+            });
+            let (declarations, initializers): (Vec<_>, Vec<_>) = fields.unzip();
+
+            return Ok(quote! {
+                #[doc(hidden)]
+                #[allow(non_camel_case_types)]
+                #[derive(::serde::Serialize)]
+                #(#container_attrs)*
+                struct #proxy_ident #proxy_generics #where_clause {
+                    #(#declarations)*
+                }
+
+                impl #proxy_impl_generics #proxy_ident #proxy_ty_generics #where_clause {
+                    fn new(value: &'__a #ident #ty_generics) -> Self {
+                        Self {
+                            #(#initializers)*
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    let mirror = match data.clone() {
+        MigrationData::Struct(mut item) => {
+            item.attrs = vec![];
+            item.vis = Visibility::Inherited;
+            item.ident = remote_ident.clone();
+            sort_fields(&mut item.fields);
+            for field in item.fields.iter_mut() {
+                serde_attrs(&mut field.attrs);
+                field.vis = Visibility::Inherited;
+            }
+            quote! { #item }
+        }
+        MigrationData::Enum(mut item) => {
+            item.attrs = vec![];
+            item.vis = Visibility::Inherited;
+            item.ident = remote_ident.clone();
+            let mut variants = item.variants.into_iter().collect::<Vec<_>>();
+            variants.sort_by_key(|variant| variant.ident.to_string());
+            item.variants = variants.into_iter().collect();
+            for variant in item.variants.iter_mut() {
+                variant.discriminant = None;
+                serde_attrs(&mut variant.attrs);
+                sort_fields(&mut variant.fields);
+                for field in variant.fields.iter_mut() {
+                    serde_attrs(&mut field.attrs);
+                }
+            }
+            quote! { #item }
+        }
+    };
+
+    let mut serialize_generics = proxy_generics.clone();
+    let type_params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause_mut = serialize_generics.make_where_clause();
+    for param in type_params {
+        where_clause_mut
+            .predicates
+            .push(parse_quote! { #param: ::serde::Serialize });
+    }
+    let (serialize_impl_generics, _, serialize_where_clause) = serialize_generics.split_for_impl();
+
+    Ok(quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types, dead_code)]
+        #[derive(::serde::Serialize)]
+        #[serde(remote = #remote)]
+        #(#container_attrs)*
+        #mirror
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        struct #proxy_ident #proxy_generics (&'__a #ident #ty_generics) #where_clause;
+
+        impl #proxy_impl_generics #proxy_ident #proxy_ty_generics #where_clause {
+            fn new(value: &'__a #ident #ty_generics) -> Self {
+                Self(value)
+            }
+        }
+
+        impl #serialize_impl_generics ::serde::Serialize for #proxy_ident #proxy_ty_generics #serialize_where_clause {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                #remote_ident::serialize(self.0, serializer)
+            }
+        }
+    })
+}
@@ -0,0 +1,221 @@
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parenthesized,
+    parse::{ParseStream, Parser},
+    punctuated::Punctuated,
+    GenericParam, Generics, Ident, Path, Token,
+};
+
+use crate::{
+    tools::{DeriveVersion, VersionRepr},
+    utils::{generate_ident, version_id, with_bound},
+};
+
+use super::schema_builder::VersionSchema;
+
+/// The attributes deriving `specta::Type` on every historical version, through the
+/// `specta` re-exported by hifumi.
+///
+/// The type itself gets its own implementation, so it cannot derive `Type` as well.
+pub(crate) fn generate_specta_derives(
+    extra_macros: &[(Ident, TokenStream)],
+) -> syn::Result<Vec<TokenStream>> {
+    for (_, tokens) in extra_macros.iter().filter(|(key, _)| key == "derive") {
+        let derives = (|input: ParseStream| {
+            let content;
+            parenthesized!(content in input);
+            Punctuated::<Path, Token![,]>::parse_terminated(&content)
+        })
+        .parse2(tokens.clone())?;
+
+        if let Some(path) = derives.iter().find(|path| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Type")
+        }) {
+            return Err(syn::Error::new_spanned(
+                path,
+                "`specta` implements `specta::Type` with the version tag, remove this derive",
+            ));
+        }
+    }
+
+    Ok(vec![
+        quote! { #[derive(hifumi::__private::specta::Type)] },
+        quote! { #[specta(crate = "hifumi::__private::specta")] },
+    ])
+}
+
+/// Wraps the type of `version` in its version tag, as `repr` writes it.
+fn tagged(attr: &DeriveVersion, version: &str, ty: TokenStream) -> TokenStream {
+    let tag = &attr.tag;
+
+    match &attr.repr {
+        VersionRepr::Internal => quote! {
+            ::hifumi::__private::typescript::internal(#tag, #version, #ty)
+        },
+        VersionRepr::Adjacent { content } => quote! {
+            ::hifumi::__private::typescript::adjacent(#tag, #content, #version, #ty)
+        },
+        VersionRepr::External => quote! {
+            ::hifumi::__private::typescript::external(#version, #ty)
+        },
+        VersionRepr::Envelope { id: false } => quote! {
+            ::hifumi::__private::typescript::envelope(
+                ::hifumi::__private::specta::LiteralType::String(#version.to_string()),
+                #ty,
+            )
+        },
+        VersionRepr::Envelope { id: true } => {
+            let id = version_id(version);
+            quote! {
+                ::hifumi::__private::typescript::envelope(
+                    ::hifumi::__private::specta::LiteralType::u32(#id),
+                    #ty,
+                )
+            }
+        }
+        VersionRepr::Xml => unreachable!("`specta` is rejected with `repr = \"xml\"`"),
+    }
+}
+
+/// Implements `specta::Type` for the type as it is serialized, a union of every
+/// version wrapped in its version tag. The historical versions derive it, and are
+/// inlined into the union.
+pub(crate) fn generate_specta(
+    attr: &DeriveVersion,
+    ident: &Ident,
+    generics: &Generics,
+    schemas: &[VersionSchema],
+) -> Result<TokenStream> {
+    let bounded = generics
+        .type_params()
+        .fold(generics.clone(), |generics, param| {
+            let param = &param.ident;
+            with_bound(
+                &generics,
+                quote! { #param },
+                quote! { ::hifumi::__private::specta::Type + 'static },
+            )
+        });
+    let (impl_generics, _, where_clause) = bounded.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let name = ident.to_string();
+
+    // A generic is looked up by its position among every parameter, as the derive does
+    let type_params = generics
+        .params
+        .iter()
+        .enumerate()
+        .filter_map(|(index, param)| match param {
+            GenericParam::Type(param) => Some((index, &param.ident)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let generic_names = type_params
+        .iter()
+        .map(|(_, param)| param.to_string())
+        .collect::<Vec<_>>();
+    let reference_generics = type_params.iter().map(|(index, param)| {
+        quote! {
+            generics.get(#index).cloned().map_or_else(
+                || <#param as ::hifumi::__private::specta::Type>::reference(
+                    ::hifumi::__private::specta::DefOpts {
+                        parent_inline: false,
+                        type_map: opts.type_map,
+                    },
+                    &[],
+                ),
+                Ok,
+            )?
+        }
+    });
+
+    let versions = schemas
+        .iter()
+        .map(|VersionSchema { version, .. }| {
+            let version_ident = generate_ident(ident, version)?;
+            Ok(tagged(
+                attr,
+                version,
+                quote! {
+                    <#version_ident #ty_generics as ::hifumi::__private::specta::Type>::inline(
+                        ::hifumi::__private::specta::DefOpts {
+                            parent_inline: false,
+                            type_map: opts.type_map,
+                        },
+                        generics,
+                    )?
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        const _: () = {
+            const SID: ::hifumi::__private::specta::TypeSid = hifumi::__private::specta::sid!(
+                @with_specta_path; #name; hifumi::__private::specta
+            );
+            const IMPL_LOCATION: ::hifumi::__private::specta::ImplLocation =
+                hifumi::__private::specta::impl_location!(
+                    @with_specta_path; hifumi::__private::specta
+                );
+
+            impl #impl_generics ::hifumi::__private::specta::Type for #ident #ty_generics #where_clause {
+                fn inline(
+                    opts: ::hifumi::__private::specta::DefOpts,
+                    generics: &[::hifumi::__private::specta::DataType],
+                ) -> ::std::result::Result<
+                    ::hifumi::__private::specta::DataType,
+                    ::hifumi::__private::specta::ExportError,
+                > {
+                    Ok(::hifumi::__private::specta::DataType::Named(
+                        <Self as ::hifumi::__private::specta::NamedType>::named_data_type(
+                            opts, generics,
+                        )?,
+                    ))
+                }
+
+                fn category_impl(
+                    opts: ::hifumi::__private::specta::DefOpts,
+                    generics: &[::hifumi::__private::specta::DataType],
+                ) -> ::std::result::Result<
+                    ::hifumi::__private::specta::TypeCategory,
+                    ::hifumi::__private::specta::ExportError,
+                > {
+                    Ok(::hifumi::__private::specta::TypeCategory::Reference(
+                        ::hifumi::__private::specta::DataTypeReference {
+                            name: #name,
+                            sid: SID,
+                            generics: vec![#(#reference_generics),*],
+                        },
+                    ))
+                }
+
+                fn definition_generics() -> Vec<::hifumi::__private::specta::GenericType> {
+                    vec![#(::hifumi::__private::specta::GenericType(#generic_names)),*]
+                }
+            }
+
+            impl #impl_generics ::hifumi::__private::specta::NamedType for #ident #ty_generics #where_clause {
+                fn named_data_type(
+                    opts: ::hifumi::__private::specta::DefOpts,
+                    generics: &[::hifumi::__private::specta::DataType],
+                ) -> ::std::result::Result<
+                    ::hifumi::__private::specta::NamedDataType,
+                    ::hifumi::__private::specta::ExportError,
+                > {
+                    Ok(::hifumi::__private::typescript::union(
+                        #name,
+                        SID,
+                        IMPL_LOCATION,
+                        vec![#(#generic_names),*],
+                        vec![#(#versions),*],
+                    ))
+                }
+            }
+        };
+    })
+}
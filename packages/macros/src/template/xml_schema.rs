@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Result};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{GenericArgument, Generics, Ident, PathArguments, Type};
+
+use super::schema_builder::{SchemaField, SchemaPayload, SchemaShape, VersionSchema};
+
+/// How a field type is written as elements: its built-in XSD type if it has one,
+/// and how often the element may occur.
+struct Occurs {
+    ty: &'static str,
+    optional: bool,
+    repeated: bool,
+}
+
+fn generic_argument(args: &PathArguments) -> Option<&Type> {
+    match args {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn occurs(ty: &Type) -> Occurs {
+    let any = Occurs {
+        ty: "xs:anyType",
+        optional: false,
+        repeated: false,
+    };
+
+    let segment = match ty {
+        Type::Reference(reference) => return occurs(&reference.elem),
+        Type::Group(group) => return occurs(&group.elem),
+        Type::Paren(paren) => return occurs(&paren.elem),
+        Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => segment,
+            None => return any,
+        },
+        _ => return any,
+    };
+
+    let name = segment.ident.to_string();
+    if let Some(inner) = generic_argument(&segment.arguments) {
+        let inner = occurs(inner);
+        return match name.as_str() {
+            "Option" => Occurs {
+                optional: true,
+                ..inner
+            },
+            "Vec" | "VecDeque" | "LinkedList" | "BTreeSet" | "HashSet" | "BinaryHeap" => Occurs {
+                optional: true,
+                repeated: true,
+                ..inner
+            },
+            "Box" | "Rc" | "Arc" | "Cow" => inner,
+            _ => any,
+        };
+    }
+
+    let ty = match name.as_str() {
+        "bool" => "xs:boolean",
+        "i8" => "xs:byte",
+        "i16" => "xs:short",
+        "i32" => "xs:int",
+        "i64" | "isize" => "xs:long",
+        "i128" => "xs:integer",
+        "u8" => "xs:unsignedByte",
+        "u16" => "xs:unsignedShort",
+        "u32" => "xs:unsignedInt",
+        "u64" | "usize" => "xs:unsignedLong",
+        "u128" => "xs:nonNegativeInteger",
+        "f32" => "xs:float",
+        "f64" => "xs:double",
+        "String" | "str" | "char" => "xs:string",
+        _ => return any,
+    };
+    Occurs {
+        ty,
+        optional: false,
+        repeated: false,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Checks that a field or variant is named by an XML name, as quick-xml writes it.
+fn xml_name(name: &str) -> Result<&str> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if valid {
+        Ok(name)
+    } else {
+        Err(anyhow!(
+            "`xml_schema` cannot describe `{}`, which is not an XML name",
+            name
+        ))
+    }
+}
+
+/// Writes an XSD document line by line.
+#[derive(Default)]
+struct Writer {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Writer {
+    fn line(&mut self, line: impl AsRef<str>) {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.depth), line.as_ref()));
+    }
+
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.depth += 1;
+    }
+
+    fn close(&mut self, line: impl AsRef<str>) {
+        self.depth -= 1;
+        self.line(line);
+    }
+
+    fn element(&mut self, name: &str, ty: &Type, required: bool) -> Result<()> {
+        let Occurs {
+            ty,
+            optional,
+            repeated,
+        } = occurs(ty);
+        let min = if optional || !required {
+            r#" minOccurs="0""#
+        } else {
+            ""
+        };
+        let max = if repeated {
+            r#" maxOccurs="unbounded""#
+        } else {
+            ""
+        };
+
+        self.line(format!(
+            r#"<xs:element name="{}" type="{}"{}{}/>"#,
+            xml_name(name)?,
+            ty,
+            min,
+            max
+        ));
+        Ok(())
+    }
+
+    /// The attribute holding the version, fixed to the version the document
+    /// describes.
+    fn version_attribute(&mut self, version: Option<(&str, &str)>) -> Result<()> {
+        if let Some((name, version)) = version {
+            self.line(format!(
+                r#"<xs:attribute name="{}" type="xs:string" fixed="{}" use="required"/>"#,
+                xml_name(name)?,
+                escape(version)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fields renamed to `@name` are attributes, the others are child elements in
+    /// the order they are written. The root element also carries the version
+    /// attribute, unless the version is its namespace.
+    fn fields(&mut self, fields: &[SchemaField], version: Option<(&str, &str)>) -> Result<()> {
+        let (attributes, elements): (Vec<_>, Vec<_>) =
+            fields.iter().partition(|field| field.name.starts_with('@'));
+        if fields.is_empty() && version.is_none() {
+            self.line("<xs:complexType/>");
+            return Ok(());
+        }
+
+        self.open("<xs:complexType>");
+        if !elements.is_empty() {
+            self.open("<xs:sequence>");
+            for SchemaField { name, ty, required } in elements {
+                self.element(name, ty, *required)?;
+            }
+            self.close("</xs:sequence>");
+        }
+        for SchemaField { name, ty, required } in attributes {
+            let Occurs { ty, optional, .. } = occurs(ty);
+            let usage = if *required && !optional {
+                r#" use="required""#
+            } else {
+                ""
+            };
+            self.line(format!(
+                r#"<xs:attribute name="{}" type="{}"{}/>"#,
+                xml_name(&name[1..])?,
+                ty,
+                usage
+            ));
+        }
+        self.version_attribute(version)?;
+        self.close("</xs:complexType>");
+        Ok(())
+    }
+}
+
+/// The XSD of a version, whose `targetNamespace` is the version, or which declares
+/// the version as the attribute `version_attribute` of the root element with
+/// `repr = "xml"` and another `tag`. The root element is named after the type, and
+/// holds the fields of a struct, or the element of the variant an enum holds.
+fn generate_document(
+    ident: &Ident,
+    schema: &VersionSchema,
+    version_attribute: Option<&str>,
+) -> Result<String> {
+    let version = escape(&schema.version);
+    let attribute = version_attribute.map(|name| (name, schema.version.as_str()));
+    let mut writer = Writer::default();
+
+    writer.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    match version_attribute {
+        Some(_) => writer.open(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">"#),
+        None => writer.open(format!(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" targetNamespace="{}" xmlns="{}" elementFormDefault="qualified">"#,
+            version, version
+        )),
+    }
+    writer.open(format!(r#"<xs:element name="{}">"#, ident));
+    match &schema.shape {
+        SchemaShape::Struct { fields, .. } => writer.fields(fields, attribute)?,
+        SchemaShape::Enum { variants } if variants.is_empty() && attribute.is_none() => {
+            writer.line("<xs:complexType/>")
+        }
+        SchemaShape::Enum { variants } if variants.is_empty() => {
+            writer.open("<xs:complexType>");
+            writer.version_attribute(attribute)?;
+            writer.close("</xs:complexType>");
+        }
+        SchemaShape::Enum { variants } => {
+            writer.open("<xs:complexType>");
+            writer.open("<xs:choice>");
+            for variant in variants {
+                let name = xml_name(&variant.name)?;
+                match &variant.payload {
+                    SchemaPayload::Unit => {
+                        writer.open(format!(r#"<xs:element name="{}">"#, name));
+                        writer.line("<xs:complexType/>");
+                        writer.close("</xs:element>");
+                    }
+                    SchemaPayload::Newtype(ty) => writer.element(name, ty, true)?,
+                    // Every item is written as an element named after the variant, and
+                    // elements of one name must have one type
+                    SchemaPayload::Tuple(_) => {
+                        return Err(anyhow!(
+                            "`xml_schema` cannot describe the tuple variant `{}`, whose items are all written as elements named `{}`",
+                            name,
+                            name
+                        ))
+                    }
+                    SchemaPayload::Struct(fields) => {
+                        writer.open(format!(r#"<xs:element name="{}">"#, name));
+                        writer.fields(fields, None)?;
+                        writer.close("</xs:element>");
+                    }
+                }
+            }
+            writer.close("</xs:choice>");
+            writer.version_attribute(attribute)?;
+            writer.close("</xs:complexType>");
+        }
+    }
+    writer.close("</xs:element>");
+    writer.close("</xs:schema>");
+
+    Ok(writer.lines.join("\n") + "\n")
+}
+
+/// Adds `xml_schemas` and `write_xml_schemas` to the type, with the XSD of every
+/// version built while the macro expands.
+pub(crate) fn generate_xml_schema(
+    ident: &Ident,
+    generics: &Generics,
+    schemas: &[VersionSchema],
+    version_attribute: Option<&str>,
+) -> Result<TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let name = ident.to_string();
+
+    let documents = schemas
+        .iter()
+        .map(|schema| {
+            let version = &schema.version;
+            let document = generate_document(ident, schema, version_attribute)?;
+            Ok(quote! { (#version, #document) })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The XML Schema of every version, the current one first, whose
+            /// `targetNamespace` is the version.
+            pub fn xml_schemas() -> &'static [(&'static str, &'static str)] {
+                &[#(#documents),*]
+            }
+
+            /// Writes the XML Schema of every version into `dir` as
+            /// `{Type}-{version}.xsd`, returning the paths written.
+            pub fn write_xml_schemas(
+                dir: impl AsRef<::std::path::Path>,
+            ) -> ::std::io::Result<Vec<::std::path::PathBuf>> {
+                ::hifumi::__private::write_xml_schemas(dir.as_ref(), #name, Self::xml_schemas())
+            }
+        }
+    })
+}
@@ -0,0 +1,139 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Ident, Token, Type,
+};
+
+/// The version hop a converter runs in, used to describe its failures.
+#[derive(Debug, Clone)]
+pub struct MigrationHop {
+    pub ident: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A converter block, `{ ... }`, or `{ ... }?` when it returns a `Result`.
+///
+/// A converter that needs the migration context names it first, as in
+/// `{ |ctx: &Lookup| ... }`, and `context` is that name with the type behind the
+/// reference.
+#[derive(Debug, Clone)]
+pub struct Converter {
+    pub body: TokenStream,
+    pub fallible: bool,
+    pub context: Option<(Ident, Box<Type>)>,
+}
+
+impl Converter {
+    /// Whether the converter can fail, either by itself or because it is run without
+    /// the migration context it names.
+    pub fn can_fail(&self) -> bool {
+        self.fallible || self.context.is_some()
+    }
+
+    /// Calls the converter with `args` bound to `params`.
+    ///
+    /// It becomes a closure rather than a nested fn, so that it can use the generics.
+    /// The error of a fallible converter is turned into a `hifumi::MigrationError`
+    /// for `target` and returned with `?`, as is a missing migration context.
+    pub fn call(
+        &self,
+        params: Vec<TokenStream>,
+        ty: TokenStream,
+        args: Vec<TokenStream>,
+        hop: &MigrationHop,
+        target: String,
+    ) -> TokenStream {
+        let body = &self.body;
+        let MigrationHop { ident, from, to } = hop;
+
+        // The context is taken from the `__context` argument of the migration
+        let (context_param, context_arg, context) = match &self.context {
+            Some((name, context_ty)) => {
+                let missing = format!(
+                    "No migration context of type `{}` was given",
+                    quote! { #context_ty }
+                );
+                (
+                    Some(quote! { #name: &#context_ty, }),
+                    Some(quote! { #name, }),
+                    Some(quote! {
+                        let #name = __context.ok_or_else(|| {
+                            ::hifumi::MigrationError::new(#ident, #from, #to, #target, #missing)
+                        })?;
+                    }),
+                )
+            }
+            None => (None, None, None),
+        };
+
+        if self.fallible {
+            quote! {
+                {
+                    #[allow(unused_variables)]
+                    let _converter = |#context_param #(#params),*| -> ::core::result::Result<#ty, _> {
+                        #body
+                    };
+                    #context
+                    _converter(#context_arg #(#args),*).map_err(|error| {
+                        ::hifumi::MigrationError::new(#ident, #from, #to, #target, error)
+                    })?
+                }
+            }
+        } else {
+            quote! {
+                {
+                    #[allow(unused_variables)]
+                    let _converter = |#context_param #(#params),*| -> #ty {
+                        #body
+                    };
+                    #context
+                    _converter(#context_arg #(#args),*)
+                }
+            }
+        }
+    }
+}
+
+impl Parse for Converter {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        braced!(content in input);
+
+        // { |ctx: &Lookup| ... }
+        let context = if content.peek(Token![|]) {
+            content.parse::<Token![|]>()?;
+            let name = content.parse::<Ident>()?;
+            content.parse::<Token![:]>()?;
+            let ty = match content.parse::<Type>()? {
+                Type::Reference(reference) if reference.mutability.is_none() => reference.elem,
+                ty => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "The migration context is borrowed, as in `|ctx: &Lookup|`",
+                    ))
+                }
+            };
+            content.parse::<Token![|]>()?;
+            Some((name, ty))
+        } else {
+            None
+        };
+        let body = content.parse::<TokenStream>()?;
+
+        let fallible = if input.peek(Token![?]) {
+            input.parse::<Token![?]>()?;
+            true
+        } else {
+            false
+        };
+
+        Ok(Self {
+            body,
+            fallible,
+            context,
+        })
+    }
+}
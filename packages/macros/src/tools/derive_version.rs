@@ -1,17 +1,79 @@
+use proc_macro2::TokenStream;
+use quote::quote;
 use syn::{
+    parenthesized,
     parse::{Parse, ParseStream},
-    Expr,
+    token, Expr, Ident, LitStr, Path, Token, Type,
 };
 
+use super::MigrationData;
+use crate::utils::version_id;
+
+/// 版本号在序列化数据中的表示方式
+#[derive(Debug, Clone)]
+pub enum VersionRepr {
+    /// `{ "$version": "0.2", ... }`
+    Internal,
+    /// `{ "$version": "0.2", "data": { ... } }`
+    Adjacent { content: String },
+    /// `{ "0.2": { ... } }`
+    External,
+    /// `("0.2", { ... })`，`id` 为真时版本号写为 `hifumi::version_id` 计算的数字，
+    /// 不依赖 `deserialize_any`，可用于 bincode、postcard 等非自描述格式
+    Envelope { id: bool },
+    /// `<Test xmlns="0.2">...</Test>`，版本号写在根元素的属性中，属性名为 `tag`，
+    /// 默认为 `xmlns`，用于 quick-xml 等 XML 格式
+    Xml,
+}
+
+/// 缺少版本号的数据的处理方式
+#[derive(Debug, Clone)]
+pub enum Untagged {
+    /// `untagged_default = "0.1"` - 视为指定的历史版本
+    Default(LitStr),
+    /// `untagged = "structural"` - 从新到旧依次尝试每个版本，取第一个能成功反序列化的
+    Structural,
+}
+
 /// 版本属性的解析结果
 ///
 /// 支持以下两种形式：
 /// - `#[version("0.1")]` - 显式指定版本
 /// - `#[version]` - 自动使用 `CARGO_PKG_VERSION` 环境变量
+///
+/// 版本号之后还可以附加以下选项：
+/// - `tag = "schemaVersion"` - 版本号所在的键，默认为 `$version`
+/// - `repr = "internal" | "adjacent" | "external" | "envelope" | "envelope_id" | "xml"` - 版本号的表示方式，默认为 `internal`
+/// - `content = "data"` - `adjacent` 表示方式下数据所在的键，默认为 `data`
+/// - `untagged_default = "0.1"` 或 `untagged = "structural"` - 缺少版本号的数据的处理方式
+/// - `context(Lookup)` - 迁移上下文的类型，转换器以 `{ |ctx: &Lookup| ... }` 的形式使用
+/// - `preserve_unknown` - 保留所有版本中未知的键，并在序列化时原样写回
+/// - `validate = path::to::fn` - 反序列化并迁移完成后对值进行校验的函数
+/// - `json_schema` - 为每个版本生成 JSON Schema，其中的版本号须与声明的版本完全一致，需要启用 `json-schema` 特性
+/// - `xml_schema` - 为每个版本生成以版本号为 targetNamespace 的 XML Schema
+/// - `specta` - 为类型实现 `specta::Type`，导出带版本号的所有版本，需要启用 `specta` 特性
 #[derive(Debug, Clone)]
 pub struct DeriveVersion {
     /// 版本字符串，如果为 None 则使用 CARGO_PKG_VERSION
     pub version: Option<String>,
+    /// 版本号所在的键
+    pub tag: String,
+    /// 版本号的表示方式
+    pub repr: VersionRepr,
+    /// 缺少版本号的数据的处理方式，为 None 时直接报错
+    pub untagged: Option<Untagged>,
+    /// 迁移上下文的类型，只有以 `|ctx: &Lookup|` 开头的转换器才会得到它
+    pub context: Option<Type>,
+    /// 设置了 `preserve_unknown` 时为该选项本身，用于定位错误
+    pub preserve_unknown: Option<Ident>,
+    /// 反序列化并迁移完成后调用的校验函数，以 `&T` 为参数
+    pub validate: Option<Path>,
+    /// 是否为每个版本实现 `hifumi::JsonSchema`
+    pub json_schema: bool,
+    /// 是否为每个版本生成 XML Schema
+    pub xml_schema: bool,
+    /// 是否为类型实现带版本号的 `specta::Type`
+    pub specta: bool,
 }
 
 impl DeriveVersion {
@@ -21,23 +83,333 @@ impl DeriveVersion {
             std::env::var("CARGO_PKG_VERSION").unwrap_or_else(|_| "0.0.0".to_string())
         })
     }
+
+    /// 是否先读出版本号，再以对应版本直接读取数据，此时不生成外层枚举
+    pub fn reads_version_first(&self) -> bool {
+        matches!(self.repr, VersionRepr::Envelope { .. } | VersionRepr::Xml)
+    }
+
+    /// 外层枚举上的 serde 标签属性
+    pub fn serde_tag(&self) -> TokenStream {
+        let tag = &self.tag;
+
+        match &self.repr {
+            VersionRepr::Internal => quote! { #[serde(tag = #tag)] },
+            VersionRepr::Adjacent { content } => {
+                quote! { #[serde(tag = #tag, content = #content)] }
+            }
+            VersionRepr::External | VersionRepr::Envelope { .. } | VersionRepr::Xml => quote! {},
+        }
+    }
+
+    /// 以 `version` 版本序列化 `value` 的表达式，`variant` 为外层枚举中该版本的变体
+    pub fn serialize_version(
+        &self,
+        data: &MigrationData,
+        variant: TokenStream,
+        version: &str,
+        value: TokenStream,
+    ) -> TokenStream {
+        match &self.repr {
+            VersionRepr::Envelope { id: false } => quote! {
+                ::serde::Serialize::serialize(&(#version, &#value), serializer)
+            },
+            VersionRepr::Envelope { id: true } => {
+                let id = version_id(version);
+                quote! {
+                    ::serde::Serialize::serialize(&(#id, &#value), serializer)
+                }
+            }
+            VersionRepr::Xml => {
+                let name = data.ident().to_string();
+                let key = format!("@{}", self.tag);
+                match data {
+                    MigrationData::Struct(_) => quote! {
+                        ::hifumi::__private::serialize_xml(serializer, #name, #key, #version, &#value)
+                    },
+                    MigrationData::Enum(_) => quote! {
+                        ::hifumi::__private::serialize_xml_enum(serializer, #name, #key, #version, &#value)
+                    },
+                }
+            }
+            _ => quote! {
+                ::serde::Serialize::serialize(&#variant(#value), serializer)
+            },
+        }
+    }
+
+    /// 读取版本号并返回剩余数据的反序列化器的表达式，设置了 `untagged` 时版本号为 `Option`
+    pub fn deserialize_version(&self) -> TokenStream {
+        let tag = &self.tag;
+
+        match (&self.repr, &self.untagged) {
+            (VersionRepr::Internal, None) => quote! {
+                ::hifumi::__private::deserialize_tagged(deserializer, #tag)
+            },
+            (VersionRepr::Internal, Some(_)) => quote! {
+                ::hifumi::__private::deserialize_maybe_tagged(deserializer, #tag)
+            },
+            (VersionRepr::Adjacent { content }, None) => quote! {
+                ::hifumi::__private::deserialize_adjacent(deserializer, #tag, #content)
+            },
+            (VersionRepr::Adjacent { content }, Some(_)) => quote! {
+                ::hifumi::__private::deserialize_maybe_adjacent(deserializer, #tag, #content)
+            },
+            (VersionRepr::External, _) => quote! {
+                ::hifumi::__private::deserialize_external(deserializer)
+            },
+            (VersionRepr::Envelope { .. } | VersionRepr::Xml, _) => {
+                unreachable!("envelopes and XML are read through their own seeds")
+            }
+        }
+    }
 }
 
 impl Parse for DeriveVersion {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut ret = Self {
+            version: None,
+            tag: "$version".to_string(),
+            repr: VersionRepr::Internal,
+            untagged: None,
+            context: None,
+            preserve_unknown: None,
+            validate: None,
+            json_schema: false,
+            xml_schema: false,
+            specta: false,
+        };
+
         // 如果输入为空，则使用 CARGO_PKG_VERSION
         if input.is_empty() {
-            return Ok(Self { version: None });
+            return Ok(ret);
         }
 
-        Ok(Self {
-            version: Some(match input.parse::<Expr>()? {
+        if !input.peek(Ident) {
+            ret.version = Some(match input.parse::<Expr>()? {
                 Expr::Lit(lit) => match lit.lit {
                     syn::Lit::Str(s) => s.value(),
                     _ => return Err(syn::Error::new(input.span(), "Expected a string literal")),
                 },
                 _ => return Err(syn::Error::new(input.span(), "Expected a string literal")),
-            }),
-        })
+            });
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        let mut tag = None;
+        let mut repr = None;
+        let mut content = None;
+        let mut untagged = None;
+        let mut seen = Vec::<Ident>::new();
+        while !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            if seen.contains(&key) {
+                return Err(syn::Error::new_spanned(
+                    &key,
+                    format!("Duplicate option `{}`", key),
+                ));
+            }
+            seen.push(key.clone());
+
+            if key == "context" && input.peek(token::Paren) {
+                // context(Lookup)
+                let content;
+                parenthesized!(content in input);
+                ret.context = Some(content.parse::<Type>()?);
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+
+            if key == "preserve_unknown" && !input.peek(Token![=]) {
+                ret.preserve_unknown = Some(key);
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+
+            if (key == "json_schema" || key == "xml_schema" || key == "specta")
+                && !input.peek(Token![=])
+            {
+                if key == "json_schema" {
+                    ret.json_schema = true;
+                } else if key == "xml_schema" {
+                    ret.xml_schema = true;
+                } else {
+                    ret.specta = true;
+                }
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+
+            input.parse::<Token![=]>()?;
+
+            if key == "validate" {
+                // validate = path::to::fn
+                ret.validate = Some(input.parse::<Path>()?);
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+
+            let value = input.parse::<LitStr>()?;
+
+            if key == "tag" {
+                ret.tag = value.value();
+                tag = Some(value);
+            } else if key == "repr" {
+                repr = Some(value);
+            } else if key == "content" {
+                content = Some(value);
+            } else if key == "untagged_default" || key == "untagged" {
+                if untagged.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        key,
+                        "`untagged_default` and `untagged` cannot be used together",
+                    ));
+                }
+                if key == "untagged" && value.value() != "structural" {
+                    return Err(syn::Error::new_spanned(value, "Expected \"structural\""));
+                }
+                untagged = Some(value.clone());
+                ret.untagged = Some(if key == "untagged" {
+                    Untagged::Structural
+                } else {
+                    Untagged::Default(value)
+                });
+            } else {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "Expected one of `tag`, `repr`, `content`, `untagged_default`, `untagged`, `validate`, `context(...)`, `preserve_unknown`, `json_schema`, `xml_schema` or `specta`",
+                ));
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        // The schema pins the version tag to the declared versions, so untagged data
+        // cannot be described by it
+        if let (true, Some(untagged)) = (ret.json_schema, &untagged) {
+            return Err(syn::Error::new_spanned(
+                untagged,
+                "`json_schema` cannot be used with untagged data, the schema only accepts the declared versions as tags",
+            ));
+        }
+
+        ret.repr = match repr.as_ref().map(LitStr::value).as_deref() {
+            None | Some("internal") => VersionRepr::Internal,
+            Some("adjacent") => VersionRepr::Adjacent {
+                content: content
+                    .take()
+                    .map(|content| content.value())
+                    .unwrap_or_else(|| "data".to_string()),
+            },
+            Some("external") => {
+                if let Some(tag) = tag {
+                    return Err(syn::Error::new_spanned(
+                        tag,
+                        "`tag` cannot be used with `repr = \"external\"`, which keys the data by its version",
+                    ));
+                }
+                if let Some(untagged) = untagged {
+                    return Err(syn::Error::new_spanned(
+                        untagged,
+                        "Untagged data cannot be told apart from a version key with `repr = \"external\"`",
+                    ));
+                }
+                VersionRepr::External
+            }
+            Some(value @ ("envelope" | "envelope_id")) => {
+                if let Some(tag) = tag {
+                    return Err(syn::Error::new_spanned(
+                        tag,
+                        format!(
+                            "`tag` cannot be used with `repr = \"{}\"`, which writes the version before the data",
+                            value
+                        ),
+                    ));
+                }
+                if let Some(untagged) = untagged {
+                    return Err(syn::Error::new_spanned(
+                        untagged,
+                        format!(
+                            "Untagged data cannot be told apart from a version with `repr = \"{}\"`",
+                            value
+                        ),
+                    ));
+                }
+                if let Some(preserve_unknown) = &ret.preserve_unknown {
+                    return Err(syn::Error::new_spanned(
+                        preserve_unknown,
+                        format!(
+                            "`preserve_unknown` cannot be used with `repr = \"{}\"`, whose formats cannot hold keys no field describes",
+                            value
+                        ),
+                    ));
+                }
+                VersionRepr::Envelope {
+                    id: value == "envelope_id",
+                }
+            }
+            Some("xml") => {
+                if let Some(untagged) = untagged {
+                    return Err(syn::Error::new_spanned(
+                        untagged,
+                        "Untagged data cannot be read with `repr = \"xml\"`, whose root element has to carry its version",
+                    ));
+                }
+                if let Some(preserve_unknown) = &ret.preserve_unknown {
+                    return Err(syn::Error::new_spanned(
+                        preserve_unknown,
+                        "`preserve_unknown` cannot be used with `repr = \"xml\"`",
+                    ));
+                }
+                if ret.json_schema {
+                    return Err(syn::Error::new_spanned(
+                        repr,
+                        "`json_schema` cannot be used with `repr = \"xml\"`, use `xml_schema` instead",
+                    ));
+                }
+                if ret.specta {
+                    return Err(syn::Error::new_spanned(
+                        repr,
+                        "`specta` cannot be used with `repr = \"xml\"`, whose shape TypeScript cannot describe",
+                    ));
+                }
+                ret.tag = match &tag {
+                    Some(tag) => tag.value().trim_start_matches('@').to_string(),
+                    None => "xmlns".to_string(),
+                };
+                VersionRepr::Xml
+            }
+            Some(_) => {
+                return Err(syn::Error::new_spanned(
+                    repr,
+                    "Expected one of \"internal\", \"adjacent\", \"external\", \"envelope\", \"envelope_id\" or \"xml\"",
+                ))
+            }
+        };
+        if let Some(content) = content {
+            return Err(syn::Error::new_spanned(
+                content,
+                "`content` can only be used with `repr = \"adjacent\"`",
+            ));
+        }
+
+        Ok(ret)
     }
 }
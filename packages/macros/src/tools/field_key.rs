@@ -0,0 +1,72 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use std::fmt;
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, Index, LitInt, LitStr, Member,
+};
+
+/// The key of a field, either a name or a position in a tuple struct.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldKey {
+    Index(u32),
+    Named(Ident),
+}
+
+impl FieldKey {
+    /// The ident used for the field in generated historical structs, and for the
+    /// local binding of the field inside converters.
+    ///
+    /// Positional fields are named `_0`, `_1`, ...
+    pub fn ident(&self) -> Ident {
+        match self {
+            Self::Index(index) => format_ident!("_{}", index),
+            Self::Named(ident) => ident.clone(),
+        }
+    }
+
+    /// The member used to access the field on the user's own type.
+    pub fn member(&self) -> Member {
+        match self {
+            Self::Index(index) => Member::Unnamed(Index::from(*index as usize)),
+            Self::Named(ident) => Member::Named(ident.clone()),
+        }
+    }
+
+    /// Positional fields keep their index as the serialized key.
+    pub fn serde_attr(&self) -> TokenStream {
+        match self {
+            Self::Index(index) => {
+                let name = LitStr::new(&index.to_string(), proc_macro2::Span::call_site());
+                quote! { #[serde(rename = #name)] }
+            }
+            Self::Named(_) => quote! {},
+        }
+    }
+}
+
+impl fmt::Display for FieldKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{}", index),
+            Self::Named(ident) => write!(f, "{}", ident),
+        }
+    }
+}
+
+impl ToTokens for FieldKey {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.ident().to_tokens(tokens)
+    }
+}
+
+impl Parse for FieldKey {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitInt) {
+            let index = input.parse::<LitInt>()?;
+            Ok(Self::Index(index.base10_parse()?))
+        } else {
+            Ok(Self::Named(input.parse()?))
+        }
+    }
+}
@@ -1,17 +1,101 @@
 use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
 use syn::{
     bracketed, parenthesized,
-    parse::{Parse, ParseStream},
-    Ident, ItemStruct, Token,
+    parse::{Parse, ParseStream, Parser},
+    parse_quote, Attribute, Field, Fields, Generics, Ident, Item, ItemEnum, ItemStruct, Token,
+    Visibility,
 };
 
 use super::MigrationComment;
 
+#[derive(Debug, Clone)]
+pub enum MigrationData {
+    Struct(ItemStruct),
+    Enum(ItemEnum),
+}
+
+impl MigrationData {
+    pub fn ident(&self) -> &Ident {
+        match self {
+            Self::Struct(item) => &item.ident,
+            Self::Enum(item) => &item.ident,
+        }
+    }
+
+    pub fn vis(&self) -> &Visibility {
+        match self {
+            Self::Struct(item) => &item.vis,
+            Self::Enum(item) => &item.vis,
+        }
+    }
+
+    pub fn generics(&self) -> &Generics {
+        match self {
+            Self::Struct(item) => &item.generics,
+            Self::Enum(item) => &item.generics,
+        }
+    }
+}
+
+impl MigrationData {
+    /// Makes sure the struct has an `unknown_fields` field, which every version
+    /// flattens the keys it does not describe into.
+    ///
+    /// The field is added with the visibility of the struct, unless the user has
+    /// declared it already.
+    pub fn add_unknown_fields(&mut self, option: &Ident) -> syn::Result<()> {
+        let item = match self {
+            Self::Struct(item) => item,
+            Self::Enum(_) => {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "`preserve_unknown` can only be used on structs with named fields",
+                ))
+            }
+        };
+        let Fields::Named(fields) = &mut item.fields else {
+            return Err(syn::Error::new_spanned(
+                option,
+                "`preserve_unknown` can only be used on structs with named fields",
+            ));
+        };
+
+        let flatten: Attribute = parse_quote! { #[serde(flatten)] };
+        match fields.named.iter_mut().find(|field| {
+            field
+                .ident
+                .as_ref()
+                .is_some_and(|ident| ident == "unknown_fields")
+        }) {
+            Some(field) => field.attrs.push(flatten),
+            None => {
+                let vis = &item.vis;
+                fields.named.push(Field::parse_named.parse2(quote! {
+                    #flatten
+                    #vis unknown_fields: ::hifumi::UnknownFields
+                })?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ToTokens for MigrationData {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Self::Struct(item) => item.to_tokens(tokens),
+            Self::Enum(item) => item.to_tokens(tokens),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Migration {
     pub versions: Vec<MigrationComment>,
     pub extra_macros: Vec<(Ident, TokenStream)>,
-    pub struct_data: ItemStruct,
+    pub data: MigrationData,
 }
 
 impl Parse for Migration {
@@ -29,9 +113,10 @@ impl Parse for Migration {
             if key == "migration" {
                 let inner_content;
                 parenthesized!(inner_content in content);
-                let item = inner_content.parse::<MigrationComment>()?;
 
-                versions.push(item);
+                // The rules can only be parsed once we know whether they describe
+                // the fields of a struct or the variants of an enum
+                versions.push(inner_content.parse::<TokenStream>()?);
             } else {
                 let tokens: TokenStream = content.parse()?;
 
@@ -39,12 +124,29 @@ impl Parse for Migration {
             }
         }
 
-        let struct_data: ItemStruct = input.parse()?;
+        let data = match input.parse::<Item>()? {
+            Item::Struct(item) => MigrationData::Struct(item),
+            Item::Enum(item) => MigrationData::Enum(item),
+            item => {
+                return Err(syn::Error::new_spanned(
+                    item,
+                    "#[version] can only be applied to structs and enums",
+                ))
+            }
+        };
+
+        let versions = versions
+            .into_iter()
+            .map(|tokens| match data {
+                MigrationData::Struct(_) => syn::parse2::<MigrationComment>(tokens),
+                MigrationData::Enum(_) => MigrationComment::parse_variants.parse2(tokens),
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
 
         Ok(Self {
             versions,
             extra_macros,
-            struct_data,
+            data,
         })
     }
 }
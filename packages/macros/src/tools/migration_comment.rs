@@ -1,47 +1,155 @@
 use syn::{
     braced,
     parse::{Parse, ParseStream},
-    token, LitStr, Token,
+    token, Attribute, Block, Ident, LitStr, Token,
 };
 
-use super::MigrationField;
+use super::{Converter, MigrationField, MigrationVariant};
 
 #[derive(Debug, Clone)]
 pub struct MigrationComment {
     pub from: LitStr,
     pub to: LitStr,
+    /// Whether this migration is the route of `from` to the current version, even
+    /// if a shorter one exists.
+    pub preferred: bool,
+    /// The container attributes of the `from` version, written as inner attributes
+    /// at the start of the block.
+    pub attrs: Vec<Attribute>,
     pub changes: Vec<MigrationField>,
+    pub variants: Vec<MigrationVariant>,
+    /// The `post { ... }` block, run on the migrated value of every payload whose
+    /// route passes through this hop.
+    pub post: Option<Block>,
 }
 
-impl Parse for MigrationComment {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        // "Ver1" => "Ver2" { ... }
+impl MigrationComment {
+    /// Whether any converter of this hop can fail, in which case the hop is
+    /// implemented with `TryFrom` instead of `From`.
+    pub fn fallible(&self) -> bool {
+        self.changes.iter().any(MigrationField::fallible)
+            || self.variants.iter().any(|rule| match rule {
+                MigrationVariant::Remove {
+                    converter: Some(converter),
+                    ..
+                } => converter.can_fail(),
+                MigrationVariant::Fields { changes, .. } => {
+                    changes.iter().any(MigrationField::fallible)
+                }
+                _ => false,
+            })
+    }
+
+    /// Every converter of this hop, including those of the variants.
+    pub fn converters(&self) -> Vec<&Converter> {
+        let fields = self.changes.iter().filter_map(MigrationField::converter);
+        let variants = self.variants.iter().flat_map(|rule| match rule {
+            MigrationVariant::Remove {
+                converter: Some(converter),
+                ..
+            } => vec![converter],
+            MigrationVariant::Fields { changes, .. } => changes
+                .iter()
+                .filter_map(MigrationField::converter)
+                .collect(),
+            _ => vec![],
+        });
+
+        fields.chain(variants).collect()
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn parse_rules<T: Parse>(
+        input: ParseStream,
+    ) -> syn::Result<(bool, LitStr, LitStr, Vec<Attribute>, Vec<T>, Option<Block>)> {
+        // prefer? "Ver1" => "Ver2" { #![...] ... post { ... } }
+
+        let preferred = input.peek(Ident);
+        if preferred {
+            let keyword = input.parse::<Ident>()?;
+            if keyword != "prefer" {
+                return Err(syn::Error::new_spanned(
+                    keyword,
+                    "Expected `prefer` or the version to migrate from",
+                ));
+            }
+        }
 
         let from = input.parse::<LitStr>()?;
         input.parse::<Token![=>]>()?;
         let to = input.parse::<LitStr>()?;
 
+        let mut attrs = vec![];
+        let mut rules = vec![];
+        let mut post = None;
         if input.peek(token::Brace) {
             let content;
             braced!(content in input);
+            attrs = content.call(Attribute::parse_inner)?;
 
-            let mut changes = vec![];
             while !content.is_empty() {
-                let change = content.parse::<MigrationField>()?;
-                changes.push(change);
+                let fork = content.fork();
+                if fork.parse::<Ident>().is_ok_and(|ident| ident == "post")
+                    && fork.peek(token::Brace)
+                {
+                    let keyword = content.parse::<Ident>()?;
+                    if post.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            keyword,
+                            "A migration can only have one `post` block",
+                        ));
+                    }
+                    post = Some(content.parse::<Block>()?);
+                } else {
+                    let rule = content.parse::<T>()?;
+                    rules.push(rule);
+                }
 
                 if content.peek(Token![,]) {
                     content.parse::<Token![,]>()?;
                 }
             }
+        }
 
-            Ok(Self { from, to, changes })
-        } else {
-            Ok(Self {
-                from,
-                to,
-                changes: vec![],
-            })
+        Ok((preferred, from, to, attrs, rules, post))
+    }
+
+    /// Parses a migration block attached to an enum, whose rules describe variants.
+    pub fn parse_variants(input: ParseStream) -> syn::Result<Self> {
+        let (preferred, from, to, attrs, variants, post) =
+            Self::parse_rules::<MigrationVariant>(input)?;
+        if let Some(post) = post {
+            return Err(syn::Error::new_spanned(
+                post,
+                "`post` blocks can only be used on structs, use `validate = ...` for enums",
+            ));
         }
+
+        Ok(Self {
+            from,
+            to,
+            preferred,
+            attrs,
+            changes: vec![],
+            variants,
+            post: None,
+        })
+    }
+}
+
+impl Parse for MigrationComment {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (preferred, from, to, attrs, changes, post) =
+            Self::parse_rules::<MigrationField>(input)?;
+
+        Ok(Self {
+            from,
+            to,
+            preferred,
+            attrs,
+            changes,
+            variants: vec![],
+            post,
+        })
     }
 }
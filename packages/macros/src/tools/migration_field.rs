@@ -2,32 +2,106 @@ use proc_macro2::TokenStream;
 use syn::{
     braced, parenthesized,
     parse::{Parse, ParseStream},
-    token, Ident, Token, TypePath,
+    token, Attribute, Token, Type,
 };
 
+use super::{Converter, FieldKey};
+
+/// A rule of a migration block describing fields.
+///
+/// `attrs` are the attributes written in front of the rule, which are given to the
+/// fields it brings back in the older version instead of the attributes of the
+/// newer version.
 #[derive(Debug, Clone)]
 pub enum MigrationField {
     Add {
-        value: (Ident, TypePath),
-        converter: Option<TokenStream>,
+        value: (FieldKey, Type),
+        converter: Option<Converter>,
     },
     Remove {
-        value: (Ident, TypePath),
+        attrs: Vec<Attribute>,
+        value: (FieldKey, Type),
+        reverse: Option<TokenStream>,
     },
     Rename {
-        source: Vec<(Ident, TypePath)>,
-        target: (Ident, TypePath),
-        converter: Option<TokenStream>,
+        attrs: Vec<Attribute>,
+        source: Vec<(FieldKey, Type)>,
+        target: (FieldKey, Type),
+        converter: Option<Converter>,
+        reverse: Option<TokenStream>,
     },
     Copy {
-        source: Vec<(Ident, TypePath)>,
-        target: (Ident, TypePath),
-        converter: Option<TokenStream>,
+        attrs: Vec<Attribute>,
+        source: Vec<(FieldKey, Type)>,
+        target: (FieldKey, Type),
+        converter: Option<Converter>,
     },
 }
 
 impl Parse for MigrationField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let rule_attrs = input.call(Attribute::parse_outer)?;
+        let mut rule = Self::parse_forward(input)?;
+
+        match &mut rule {
+            Self::Remove { attrs, .. } | Self::Rename { attrs, .. } | Self::Copy { attrs, .. } => {
+                *attrs = rule_attrs;
+            }
+            Self::Add { .. } => {
+                if let Some(attr) = rule_attrs.first() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "Attributes describe the field in the older version, which an added field does not have",
+                    ));
+                }
+            }
+        }
+
+        if input.peek(Token![<=]) {
+            // ... <= { ... },
+            let token = input.parse::<Token![<=]>()?;
+
+            let content;
+            braced!(content in input);
+            let converter = content.parse::<TokenStream>()?;
+
+            match &mut rule {
+                Self::Remove { reverse, .. } | Self::Rename { reverse, .. } => {
+                    *reverse = Some(converter);
+                }
+                Self::Add { .. } | Self::Copy { .. } => {
+                    return Err(syn::Error::new_spanned(
+                        token,
+                        "Reverse rules can only be given for removed or converted fields",
+                    ));
+                }
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+impl MigrationField {
+    pub fn fallible(&self) -> bool {
+        match self {
+            Self::Add { converter, .. }
+            | Self::Rename { converter, .. }
+            | Self::Copy { converter, .. } => converter.as_ref().is_some_and(Converter::can_fail),
+            Self::Remove { .. } => false,
+        }
+    }
+
+    pub fn converter(&self) -> Option<&Converter> {
+        match self {
+            Self::Add { converter, .. }
+            | Self::Rename { converter, .. }
+            | Self::Copy { converter, .. } => converter.as_ref(),
+            Self::Remove { .. } => None,
+        }
+    }
+
+    fn parse_forward(input: ParseStream) -> syn::Result<Self> {
         if input.peek(Token![+]) {
             input.parse::<Token![+]>()?;
 
@@ -38,9 +112,9 @@ impl Parse for MigrationField {
 
                 let mut source = vec![];
                 while !content.is_empty() {
-                    let key = content.parse::<Ident>()?;
+                    let key = content.parse::<FieldKey>()?;
                     content.parse::<Token![:]>()?;
-                    let ty = content.parse::<TypePath>()?;
+                    let ty = content.parse::<Type>()?;
 
                     source.push((key, ty));
 
@@ -51,39 +125,37 @@ impl Parse for MigrationField {
 
                 input.parse::<Token![=>]>()?;
 
-                let target_ident = input.parse::<Ident>()?;
+                let target_ident = input.parse::<FieldKey>()?;
                 input.parse::<Token![:]>()?;
-                let ty = input.parse::<TypePath>()?;
+                let ty = input.parse::<Type>()?;
 
-                let content;
-                braced!(content in input);
-                let converter = content.parse::<TokenStream>()?;
+                let converter = input.parse::<Converter>()?;
 
                 Ok(Self::Copy {
+                    attrs: vec![],
                     source,
                     target: (target_ident, ty),
                     converter: Some(converter),
                 })
             } else {
-                let source_ident = input.parse::<Ident>()?;
+                let source_ident = input.parse::<FieldKey>()?;
                 if input.peek(Token![:]) {
                     input.parse::<Token![:]>()?;
-                    let source_ty = input.parse::<TypePath>()?;
+                    let source_ty = input.parse::<Type>()?;
 
                     if input.peek(Token![=>]) {
                         input.parse::<Token![=>]>()?;
 
-                        let target_ident = input.parse::<Ident>()?;
+                        let target_ident = input.parse::<FieldKey>()?;
                         input.parse::<Token![:]>()?;
-                        let target_ty = input.parse::<TypePath>()?;
+                        let target_ty = input.parse::<Type>()?;
 
                         if input.peek(token::Brace) {
                             // + a: ty => b: ty { ... },
-                            let content;
-                            braced!(content in input);
-                            let converter = content.parse::<TokenStream>()?;
+                            let converter = input.parse::<Converter>()?;
 
                             Ok(Self::Copy {
+                                attrs: vec![],
                                 source: vec![(source_ident, source_ty)],
                                 target: (target_ident, target_ty),
                                 converter: Some(converter),
@@ -91,6 +163,7 @@ impl Parse for MigrationField {
                         } else {
                             // + a: ty => b: ty,
                             Ok(Self::Copy {
+                                attrs: vec![],
                                 source: vec![(source_ident, source_ty)],
                                 target: (target_ident, target_ty),
                                 converter: None,
@@ -98,9 +171,7 @@ impl Parse for MigrationField {
                         }
                     } else if input.peek(token::Brace) {
                         // + a: ty { ... },
-                        let content;
-                        braced!(content in input);
-                        let converter = content.parse::<TokenStream>()?;
+                        let converter = input.parse::<Converter>()?;
 
                         Ok(Self::Add {
                             value: (source_ident, source_ty),
@@ -115,17 +186,16 @@ impl Parse for MigrationField {
                     }
                 } else {
                     input.parse::<Token![=>]>()?;
-                    let target_ident = input.parse::<Ident>()?;
+                    let target_ident = input.parse::<FieldKey>()?;
                     input.parse::<Token![:]>()?;
-                    let ty = input.parse::<TypePath>()?;
+                    let ty = input.parse::<Type>()?;
 
                     if input.peek(token::Brace) {
                         // + a => b: ty { ... },
-                        let content;
-                        braced!(content in input);
-                        let converter = content.parse::<TokenStream>()?;
+                        let converter = input.parse::<Converter>()?;
 
                         Ok(Self::Copy {
+                            attrs: vec![],
                             source: vec![(source_ident, ty.clone())],
                             target: (target_ident, ty),
                             converter: Some(converter),
@@ -133,6 +203,7 @@ impl Parse for MigrationField {
                     } else {
                         // + a => b: ty,
                         Ok(Self::Copy {
+                            attrs: vec![],
                             source: vec![(source_ident, ty.clone())],
                             target: (target_ident, ty),
                             converter: None,
@@ -144,11 +215,15 @@ impl Parse for MigrationField {
             input.parse::<Token![-]>()?;
 
             // - a: ty,
-            let key = input.parse::<Ident>()?;
+            let key = input.parse::<FieldKey>()?;
             input.parse::<Token![:]>()?;
-            let ty = input.parse::<TypePath>()?;
+            let ty = input.parse::<Type>()?;
 
-            Ok(Self::Remove { value: (key, ty) })
+            Ok(Self::Remove {
+                attrs: vec![],
+                value: (key, ty),
+                reverse: None,
+            })
         } else if input.peek(token::Paren) {
             // (a: ty, b: ty, ...) => c: ty { ... },
             let content;
@@ -156,9 +231,9 @@ impl Parse for MigrationField {
 
             let mut source = vec![];
             while !content.is_empty() {
-                let key = content.parse::<Ident>()?;
+                let key = content.parse::<FieldKey>()?;
                 content.parse::<Token![:]>()?;
-                let ty = content.parse::<TypePath>()?;
+                let ty = content.parse::<Type>()?;
 
                 source.push((key, ty));
 
@@ -169,98 +244,104 @@ impl Parse for MigrationField {
 
             input.parse::<Token![=>]>()?;
 
-            let target_ident = input.parse::<Ident>()?;
+            let target_ident = input.parse::<FieldKey>()?;
             input.parse::<Token![:]>()?;
-            let target_ty = input.parse::<TypePath>()?;
+            let target_ty = input.parse::<Type>()?;
 
-            let content;
-            braced!(content in input);
-            let converter = content.parse::<TokenStream>()?;
+            let converter = input.parse::<Converter>()?;
 
             Ok(Self::Rename {
+                attrs: vec![],
                 source,
                 target: (target_ident, target_ty),
                 converter: Some(converter),
+                reverse: None,
             })
         } else {
-            let source_key = input.parse::<Ident>()?;
+            let source_key = input.parse::<FieldKey>()?;
 
             if input.peek(Token![:]) {
                 input.parse::<Token![:]>()?;
-                let source_ty = input.parse::<TypePath>()?;
+                let source_ty = input.parse::<Type>()?;
 
                 input.parse::<Token![=>]>()?;
 
-                if input.peek2(Token![:]) {
-                    let target_key = input.parse::<Ident>()?;
+                if input.peek2(Token![:]) && !input.peek2(Token![::]) {
+                    let target_key = input.parse::<FieldKey>()?;
                     input.parse::<Token![:]>()?;
-                    let target_ty = input.parse::<TypePath>()?;
+                    let target_ty = input.parse::<Type>()?;
 
                     if input.peek(token::Brace) {
                         // a: ty => b: ty { ... },
-                        let content;
-                        braced!(content in input);
-                        let converter = content.parse::<TokenStream>()?;
+                        let converter = input.parse::<Converter>()?;
 
                         Ok(Self::Rename {
+                            attrs: vec![],
                             source: vec![(source_key, source_ty)],
                             target: (target_key, target_ty),
                             converter: Some(converter),
+                            reverse: None,
                         })
                     } else {
                         // a: ty => b: ty,
                         Ok(Self::Rename {
+                            attrs: vec![],
                             source: vec![(source_key, source_ty)],
                             target: (target_key, target_ty),
                             converter: None,
+                            reverse: None,
                         })
                     }
                 } else {
-                    let target_ty = input.parse::<TypePath>()?;
+                    let target_ty = input.parse::<Type>()?;
                     if input.peek(token::Brace) {
                         // a: ty => ty { ... },
-                        let content;
-                        braced!(content in input);
-                        let converter = content.parse::<TokenStream>()?;
+                        let converter = input.parse::<Converter>()?;
 
                         Ok(Self::Rename {
+                            attrs: vec![],
                             source: vec![(source_key.clone(), source_ty.clone())],
                             target: (source_key, target_ty),
                             converter: Some(converter),
+                            reverse: None,
                         })
                     } else {
                         // a: ty => ty,
                         Ok(Self::Rename {
+                            attrs: vec![],
                             source: vec![(source_key.clone(), source_ty.clone())],
                             target: (source_key, target_ty),
                             converter: None,
+                            reverse: None,
                         })
                     }
                 }
             } else {
                 input.parse::<Token![=>]>()?;
-                let target_key = input.parse::<Ident>()?;
+                let target_key = input.parse::<FieldKey>()?;
                 input.parse::<Token![:]>()?;
-                let ty = input.parse::<TypePath>()?;
+                let ty = input.parse::<Type>()?;
                 if input.peek(token::Brace) {
                     // a => b: ty { ... },
 
-                    let content;
-                    braced!(content in input);
-                    let converter = content.parse::<TokenStream>()?;
+                    let converter = input.parse::<Converter>()?;
 
                     Ok(Self::Rename {
+                        attrs: vec![],
                         source: vec![(source_key, ty.clone())],
                         target: (target_key, ty),
                         converter: Some(converter),
+                        reverse: None,
                     })
                 } else {
                     // a => b: ty,
 
                     Ok(Self::Rename {
+                        attrs: vec![],
                         source: vec![(source_key, ty.clone())],
                         target: (target_key, ty),
                         converter: None,
+                        reverse: None,
                     })
                 }
             }
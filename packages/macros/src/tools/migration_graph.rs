@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::MigrationComment;
+
+fn push_error(errors: &mut Option<syn::Error>, error: syn::Error) {
+    match errors {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+/// The directed graph formed by the `#[migration]` blocks of a type.
+///
+/// Every historical version must lead to the current version, possibly through
+/// several branches. Each of them follows a single route there: the migration marked
+/// with `prefer`, or else the one with the fewest hops, the first declared winning ties.
+#[derive(Debug, Clone)]
+pub struct MigrationGraph {
+    pub current: String,
+    /// Every migration, in declaration order.
+    pub edges: Vec<MigrationComment>,
+    /// Every version, the current one first and then ordered by the length of their
+    /// routes, so that a version always comes after the next version of its route.
+    pub versions: Vec<String>,
+    /// The migration each historical version takes towards the current version.
+    routes: BTreeMap<String, usize>,
+}
+
+impl MigrationGraph {
+    /// Checks the graph and chooses the route of every version.
+    ///
+    /// Self-loops, duplicate migrations, cycles, conflicting preferences and versions
+    /// that never lead to the current version are all reported at once, each at the
+    /// offending literal.
+    pub fn resolve(current: &str, versions: &[MigrationComment]) -> syn::Result<Self> {
+        let mut errors = None;
+
+        let mut edges = vec![];
+        for item in versions.iter() {
+            let (from, to) = (item.from.value(), item.to.value());
+
+            if from == to {
+                push_error(
+                    &mut errors,
+                    syn::Error::new_spanned(
+                        &item.to,
+                        format!("Version \"{}\" cannot be migrated into itself", to),
+                    ),
+                );
+            } else if edges
+                .iter()
+                .any(|edge: &MigrationComment| edge.from.value() == from && edge.to.value() == to)
+            {
+                push_error(
+                    &mut errors,
+                    syn::Error::new_spanned(
+                        &item.to,
+                        format!(
+                            "The migration from \"{}\" to \"{}\" is already declared",
+                            from, to
+                        ),
+                    ),
+                );
+            } else if let Some(first) = edges
+                .iter()
+                .find(|edge| item.preferred && edge.preferred && edge.from.value() == from)
+            {
+                push_error(
+                    &mut errors,
+                    syn::Error::new_spanned(
+                        &item.from,
+                        format!(
+                            "Version \"{}\" already prefers the migration to \"{}\"",
+                            from,
+                            first.to.value()
+                        ),
+                    ),
+                );
+            } else {
+                edges.push(item.clone());
+            }
+        }
+
+        let outgoing = |version: String| {
+            edges
+                .iter()
+                .filter(move |item| item.from.value() == version)
+        };
+
+        // Following the migrations forwards must never lead back to a visited version
+        let mut finished = BTreeSet::new();
+        let mut in_cycle = BTreeSet::new();
+        for item in edges.iter() {
+            if finished.contains(&item.from.value()) {
+                continue;
+            }
+
+            let mut stack = vec![(item.from.value(), outgoing(item.from.value()))];
+            while let Some((version, next)) = stack.last_mut() {
+                let version = version.clone();
+                match next.next() {
+                    Some(edge) => {
+                        let to = edge.to.value();
+                        if let Some(position) = stack.iter().position(|(item, _)| *item == to) {
+                            let cycle = stack[position..]
+                                .iter()
+                                .map(|(item, _)| item.clone())
+                                .collect::<Vec<_>>();
+                            push_error(
+                                &mut errors,
+                                syn::Error::new_spanned(
+                                    &edge.from,
+                                    format!(
+                                        "This migration closes a cycle between versions {}",
+                                        cycle
+                                            .iter()
+                                            .map(|version| format!("\"{}\"", version))
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    ),
+                                ),
+                            );
+                            in_cycle.extend(cycle);
+                        } else if !finished.contains(&to) {
+                            stack.push((to.clone(), outgoing(to)));
+                        }
+                    }
+                    None => {
+                        finished.insert(version);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        // Walk backwards from the current version to find every version leading there
+        let mut reachable = BTreeSet::from([current.to_string()]);
+        let mut queue = VecDeque::from([current.to_string()]);
+        while let Some(version) = queue.pop_front() {
+            for item in edges.iter().filter(|item| item.to.value() == version) {
+                if reachable.insert(item.from.value()) {
+                    queue.push_back(item.from.value());
+                }
+            }
+        }
+
+        let mut unreachable = BTreeSet::new();
+        for item in edges.iter() {
+            let to = item.to.value();
+            if reachable.contains(&to) || in_cycle.contains(&to) || !unreachable.insert(to.clone())
+            {
+                continue;
+            }
+
+            push_error(
+                &mut errors,
+                syn::Error::new_spanned(
+                    &item.to,
+                    format!(
+                        "Version \"{}\" does not lead to the current version \"{}\"",
+                        to, current
+                    ),
+                ),
+            );
+        }
+
+        if let Some(errors) = errors {
+            return Err(errors);
+        }
+
+        let mut graph = Self {
+            current: current.to_string(),
+            edges,
+            versions: vec![],
+            routes: BTreeMap::new(),
+        };
+
+        let mut lengths = BTreeMap::from([(current.to_string(), 0)]);
+        let mut declared = vec![current.to_string()];
+        for item in graph.edges.iter() {
+            for version in [item.to.value(), item.from.value()] {
+                if !declared.contains(&version) {
+                    declared.push(version);
+                }
+            }
+        }
+        for version in declared.iter() {
+            graph.route_length(version, &mut lengths);
+        }
+
+        let mut versions = declared.into_iter().enumerate().collect::<Vec<_>>();
+        versions.sort_by_key(|(index, version)| (lengths[version], *index));
+        graph.versions = versions.into_iter().map(|(_, version)| version).collect();
+
+        Ok(graph)
+    }
+
+    /// Picks the route of a version and returns how many hops it takes, once the
+    /// graph is known to be acyclic and to lead to the current version.
+    fn route_length(&mut self, version: &str, lengths: &mut BTreeMap<String, usize>) -> usize {
+        if let Some(length) = lengths.get(version) {
+            return *length;
+        }
+
+        let outgoing = self
+            .edges
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.from.value() == version)
+            .map(|(index, item)| (index, item.to.value(), item.preferred))
+            .collect::<Vec<_>>();
+        let candidates = match outgoing.iter().find(|(_, _, preferred)| *preferred) {
+            Some(preferred) => vec![preferred.clone()],
+            None => outgoing,
+        };
+
+        let (length, index) = candidates
+            .into_iter()
+            .map(|(index, to, _)| (self.route_length(&to, lengths) + 1, index))
+            .min_by_key(|(length, _)| *length)
+            .expect("Every historical version has been checked to lead somewhere");
+
+        self.routes.insert(version.to_string(), index);
+        lengths.insert(version.to_string(), length);
+        length
+    }
+
+    /// The migration a historical version takes towards the current version.
+    pub fn route(&self, version: &str) -> Option<&MigrationComment> {
+        self.routes.get(version).map(|index| &self.edges[*index])
+    }
+
+    /// Every migration leaving a version, its route first.
+    pub fn outgoing(&self, version: &str) -> Vec<&MigrationComment> {
+        let route = self.route(version);
+        let route_to = route.map(|item| item.to.value());
+        route
+            .into_iter()
+            .chain(
+                self.edges.iter().filter(|item| {
+                    item.from.value() == version && Some(item.to.value()) != route_to
+                }),
+            )
+            .collect()
+    }
+
+    /// The migrations leading from one version to another, following the route of
+    /// `from` when it passes through `to` and the fewest hops otherwise.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<&MigrationComment>> {
+        let mut path = vec![];
+        let mut version = from.to_string();
+        while let Some(item) = self.route(&version) {
+            path.push(item);
+            version = item.to.value();
+            if version == to {
+                return Some(path);
+            }
+        }
+
+        let mut previous = BTreeMap::<String, &MigrationComment>::new();
+        let mut queue = VecDeque::from([from.to_string()]);
+        while let Some(version) = queue.pop_front() {
+            if version == to {
+                let mut path = vec![];
+                let mut version = version;
+                while let Some(item) = previous.get(&version) {
+                    path.push(*item);
+                    version = item.from.value();
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for item in self
+                .edges
+                .iter()
+                .filter(|item| item.from.value() == version)
+            {
+                let next = item.to.value();
+                if next != from && !previous.contains_key(&next) {
+                    previous.insert(next.clone(), item);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+}
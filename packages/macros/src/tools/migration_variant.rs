@@ -0,0 +1,319 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::BTreeMap;
+use syn::{
+    braced, parenthesized,
+    parse::{Parse, ParseStream},
+    token, Attribute, Fields, Ident, Token, Type, Variant,
+};
+
+use super::{Converter, FieldKey, MigrationField};
+
+/// The payload shape of an enum variant.
+#[derive(Debug, Clone)]
+pub enum VariantShape {
+    Unit,
+    Tuple(Vec<Type>),
+    Named(BTreeMap<FieldKey, Type>),
+}
+
+impl VariantShape {
+    pub fn from_fields(fields: &Fields) -> Self {
+        match fields {
+            Fields::Unit => Self::Unit,
+            Fields::Unnamed(fields) => Self::Tuple(
+                fields
+                    .unnamed
+                    .iter()
+                    .map(|field| field.ty.clone())
+                    .collect(),
+            ),
+            Fields::Named(fields) => Self::Named(
+                fields
+                    .named
+                    .iter()
+                    .filter_map(|field| {
+                        field
+                            .ident
+                            .as_ref()
+                            .map(|ident| (FieldKey::Named(ident.clone()), field.ty.clone()))
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The idents bound by [`Self::pattern`], in declaration order.
+    pub fn bindings(&self) -> Vec<Ident> {
+        match self {
+            Self::Unit => vec![],
+            Self::Tuple(types) => (0..types.len()).map(|i| format_ident!("_{}", i)).collect(),
+            Self::Named(fields) => fields.keys().map(FieldKey::ident).collect(),
+        }
+    }
+
+    /// A pattern (or constructor) that binds every payload field to a local.
+    ///
+    /// Tuple fields are bound as `_0`, `_1`, ...; named fields by their name.
+    pub fn pattern(&self) -> TokenStream {
+        let bindings = self.bindings();
+        match self {
+            Self::Unit => quote! {},
+            Self::Tuple(_) => quote! { ( #(#bindings),* ) },
+            Self::Named(_) => quote! { { #(#bindings),* } },
+        }
+    }
+
+    /// A constructor that clones every field bound by [`Self::pattern`], which
+    /// borrows them when it matches a reference.
+    pub fn cloned(&self) -> TokenStream {
+        let bindings = self.bindings();
+        match self {
+            Self::Unit => quote! {},
+            Self::Tuple(_) => quote! { ( #(::core::clone::Clone::clone(#bindings)),* ) },
+            Self::Named(_) => {
+                quote! { { #(#bindings: ::core::clone::Clone::clone(#bindings)),* } }
+            }
+        }
+    }
+
+    /// The types of the fields, in the order of [`Self::bindings`].
+    pub fn types(&self) -> Vec<Type> {
+        match self {
+            Self::Unit => vec![],
+            Self::Tuple(types) => types.clone(),
+            Self::Named(fields) => fields.values().cloned().collect(),
+        }
+    }
+
+    /// Typed parameters for a converter that receives the bound fields.
+    pub fn params(&self) -> Vec<TokenStream> {
+        self.bindings()
+            .iter()
+            .zip(self.types())
+            .map(|(ident, ty)| quote! { #ident: #ty })
+            .collect()
+    }
+
+    /// The variant body used when declaring a generated enum, with the attributes
+    /// of each field in front of it.
+    pub fn declaration(&self, field_attrs: &BTreeMap<FieldKey, Vec<Attribute>>) -> TokenStream {
+        match self {
+            Self::Unit => quote! {},
+            Self::Tuple(types) => {
+                let fields = types.iter().enumerate().map(|(index, ty)| {
+                    let attrs = field_attrs
+                        .get(&FieldKey::Index(index as u32))
+                        .into_iter()
+                        .flatten();
+                    quote! { #(#attrs)* #ty }
+                });
+                quote! { ( #(#fields),* ) }
+            }
+            Self::Named(fields) => {
+                let fields = fields.iter().map(|(key, ty)| {
+                    let serde_attr = key.serde_attr();
+                    let attrs = field_attrs.get(key).into_iter().flatten();
+                    quote! { #(#attrs)* #serde_attr #key: #ty }
+                });
+                quote! { { #(#fields),* } }
+            }
+        }
+    }
+}
+
+impl Parse for VariantShape {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(token::Paren) {
+            // Name(ty, ty, ...)
+            let content;
+            parenthesized!(content in input);
+
+            let mut types = vec![];
+            while !content.is_empty() {
+                types.push(content.parse::<Type>()?);
+
+                if content.peek(Token![,]) {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+
+            Ok(Self::Tuple(types))
+        } else if input.peek(token::Brace) {
+            // Name { a: ty, b: ty, ... }
+            let content;
+            braced!(content in input);
+
+            let mut fields = BTreeMap::new();
+            while !content.is_empty() {
+                let key = content.parse::<FieldKey>()?;
+                content.parse::<Token![:]>()?;
+                let ty = content.parse::<Type>()?;
+
+                fields.insert(key, ty);
+
+                if content.peek(Token![,]) {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+
+            Ok(Self::Named(fields))
+        } else {
+            Ok(Self::Unit)
+        }
+    }
+}
+
+/// A variant of a historical enum version.
+#[derive(Debug, Clone)]
+pub struct VariantData {
+    pub attrs: Vec<Attribute>,
+    pub shape: VariantShape,
+    /// The attributes of the payload fields, keyed like the fields of a struct.
+    pub field_attrs: BTreeMap<FieldKey, Vec<Attribute>>,
+}
+
+impl VariantData {
+    pub fn from_variant(variant: &Variant) -> Self {
+        Self {
+            attrs: variant.attrs.clone(),
+            shape: VariantShape::from_fields(&variant.fields),
+            field_attrs: variant
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| match &field.ident {
+                    Some(ident) => (FieldKey::Named(ident.clone()), field.attrs.clone()),
+                    None => (FieldKey::Index(index as u32), field.attrs.clone()),
+                })
+                .collect(),
+        }
+    }
+
+    /// The variant body used when declaring a generated enum.
+    pub fn declaration(&self) -> TokenStream {
+        self.shape.declaration(&self.field_attrs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MigrationVariant {
+    Add {
+        variant: Ident,
+    },
+    Remove {
+        variant: Ident,
+        shape: VariantShape,
+        converter: Option<Converter>,
+    },
+    Rename {
+        source: Vec<Ident>,
+        target: Ident,
+        reverse: Option<Ident>,
+    },
+    Fields {
+        variant: Ident,
+        changes: Vec<MigrationField>,
+    },
+}
+
+impl Parse for MigrationVariant {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![+]) {
+            // + Name,
+            input.parse::<Token![+]>()?;
+            let variant = input.parse::<Ident>()?;
+
+            Ok(Self::Add { variant })
+        } else if input.peek(Token![-]) {
+            input.parse::<Token![-]>()?;
+            let variant = input.parse::<Ident>()?;
+            let shape = input.parse::<VariantShape>()?;
+
+            if input.peek(Token![=>]) {
+                // - Name(ty, ...) => { ... },
+                input.parse::<Token![=>]>()?;
+                let converter = input.parse::<Converter>()?;
+
+                Ok(Self::Remove {
+                    variant,
+                    shape,
+                    converter: Some(converter),
+                })
+            } else {
+                // - Name(ty, ...),
+                Ok(Self::Remove {
+                    variant,
+                    shape,
+                    converter: None,
+                })
+            }
+        } else if input.peek(token::Paren) {
+            // (A, B, ...) => C,
+            let content;
+            parenthesized!(content in input);
+
+            let mut source = vec![];
+            while !content.is_empty() {
+                source.push(content.parse::<Ident>()?);
+
+                if content.peek(Token![,]) {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+
+            input.parse::<Token![=>]>()?;
+            let target = input.parse::<Ident>()?;
+
+            // (A, B, ...) => C <= A,
+            let reverse = if input.peek(Token![<=]) {
+                input.parse::<Token![<=]>()?;
+                let reverse = input.parse::<Ident>()?;
+                if !source.contains(&reverse) {
+                    return Err(syn::Error::new_spanned(
+                        reverse,
+                        "A merged variant can only be reversed into one of its sources",
+                    ));
+                }
+                Some(reverse)
+            } else {
+                None
+            };
+
+            Ok(Self::Rename {
+                source,
+                target,
+                reverse,
+            })
+        } else {
+            let variant = input.parse::<Ident>()?;
+
+            if input.peek(token::Brace) {
+                // Name { + a: ty, b => c: ty, ... },
+                let content;
+                braced!(content in input);
+
+                let mut changes = vec![];
+                while !content.is_empty() {
+                    changes.push(content.parse::<MigrationField>()?);
+
+                    if content.peek(Token![,]) {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+
+                Ok(Self::Fields { variant, changes })
+            } else {
+                // A => B,
+                input.parse::<Token![=>]>()?;
+                let target = input.parse::<Ident>()?;
+
+                Ok(Self::Rename {
+                    source: vec![variant],
+                    target,
+                    reverse: None,
+                })
+            }
+        }
+    }
+}
@@ -1,9 +1,17 @@
+pub(crate) mod converter;
 pub(crate) mod derive_version;
+pub(crate) mod field_key;
 pub(crate) mod migration;
 pub(crate) mod migration_comment;
 pub(crate) mod migration_field;
+pub(crate) mod migration_graph;
+pub(crate) mod migration_variant;
 
-pub(crate) use derive_version::DeriveVersion;
-pub(crate) use migration::Migration;
+pub(crate) use converter::{Converter, MigrationHop};
+pub(crate) use derive_version::{DeriveVersion, Untagged, VersionRepr};
+pub(crate) use field_key::FieldKey;
+pub(crate) use migration::{Migration, MigrationData};
 pub(crate) use migration_comment::MigrationComment;
 pub(crate) use migration_field::MigrationField;
+pub(crate) use migration_graph::MigrationGraph;
+pub(crate) use migration_variant::{MigrationVariant, VariantData, VariantShape};
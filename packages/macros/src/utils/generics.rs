@@ -0,0 +1,47 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{parse_quote, GenericParam, Generics, Lifetime, LifetimeParam};
+
+/// A `PhantomData` type that uses every type and lifetime parameter.
+///
+/// Historical versions share the generics of the current type but may no longer
+/// have a field that uses some of them, so they carry this marker instead.
+/// Returns `None` when there is nothing to mark.
+pub fn phantom_type(generics: &Generics) -> Option<TokenStream> {
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime);
+    let types = generics.type_params().map(|param| &param.ident);
+
+    if generics.lifetimes().next().is_none() && generics.type_params().next().is_none() {
+        return None;
+    }
+
+    Some(quote! {
+        ::core::marker::PhantomData<( #(&#lifetimes (),)* #(fn() -> #types,)* )>
+    })
+}
+
+/// The generics of a `Deserialize<'de>` impl, where `'de` outlives every lifetime
+/// of the type so that borrowed fields like `&'a str` can be deserialized.
+pub fn deserialize_generics(generics: &Generics) -> Generics {
+    let mut de_lifetime = LifetimeParam::new(Lifetime::new("'de", Span::call_site()));
+    de_lifetime.bounds = generics
+        .lifetimes()
+        .map(|param| param.lifetime.clone())
+        .collect();
+
+    let mut generics = generics.clone();
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(de_lifetime));
+    generics
+}
+
+/// Adds `#ty: #bound` to the where clause of the generics.
+pub fn with_bound(generics: &Generics, ty: TokenStream, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { #ty: #bound });
+    generics
+}
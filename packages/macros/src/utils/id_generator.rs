@@ -1,21 +1,62 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use proc_macro2::Span;
-use sqids::Sqids;
+use std::collections::BTreeMap;
 use syn::Ident;
 
-pub fn generate_ident(prefix: impl ToString, id: impl ToString) -> Result<Ident> {
-    let sqids = Sqids::builder().min_length(10).build()?;
-    let id = sqids.encode(
-        id.to_string()
-            .as_bytes()
-            .iter()
-            .map(|&b| b as u64)
-            .collect::<Vec<_>>()
-            .as_slice(),
-    )?;
+use crate::tools::MigrationGraph;
+
+/// The name of the historical type of `prefix` at `version`, e.g. `TestV0_1` for
+/// version `0.1` of `Test`.
+///
+/// Every run of characters that cannot appear in an ident becomes a single `_`.
+pub fn generate_ident(prefix: impl ToString, version: impl ToString) -> Result<Ident> {
+    let version = version.to_string();
+    let parts = version
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        return Err(anyhow!(
+            "Version \"{}\" cannot be used in a type name",
+            version
+        ));
+    }
 
     Ok(Ident::new(
-        &format!("_{}_{}", prefix.to_string(), id),
+        &format!("{}V{}", prefix.to_string(), parts.join("_")),
         Span::call_site(),
     ))
 }
+
+/// Checks that every version of the graph gets a type name of its own, pointing at
+/// the migration that declares a clashing version otherwise.
+pub fn check_version_idents(ident: &Ident, graph: &MigrationGraph) -> syn::Result<()> {
+    let literal = |version: &str| {
+        graph
+            .edges
+            .iter()
+            .flat_map(|item| [&item.from, &item.to])
+            .find(|literal| literal.value() == version)
+    };
+    let error = |version: &str, message: String| match literal(version) {
+        Some(literal) => syn::Error::new_spanned(literal, message),
+        None => syn::Error::new(ident.span(), message),
+    };
+
+    let mut names = BTreeMap::new();
+    for version in graph.versions.iter() {
+        let name = generate_ident(ident, version).map_err(|e| error(version, e.to_string()))?;
+
+        if let Some(other) = names.insert(name.to_string(), version) {
+            return Err(error(
+                version,
+                format!(
+                    "Versions \"{}\" and \"{}\" would both be named {}",
+                    other, version, name
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -1,3 +1,11 @@
+pub(crate) mod generics;
 pub(crate) mod id_generator;
+pub(crate) mod serde_attrs;
+pub(crate) mod version;
+pub(crate) mod version_attrs;
 
-pub(crate) use id_generator::generate_ident;
+pub(crate) use generics::{deserialize_generics, phantom_type, with_bound};
+pub(crate) use id_generator::{check_version_idents, generate_ident};
+pub(crate) use serde_attrs::{RenameRule, SerdeAttrs, SerdeDefault};
+pub(crate) use version::{canonicalize_versions, newest_first, normalize_version, version_id};
+pub(crate) use version_attrs::{is_context_field, strip_version_attrs};
@@ -0,0 +1,192 @@
+use syn::{Attribute, Expr, ExprPath, LitStr, Path, Token};
+
+/// A `rename_all` rule of serde, applied to the names it does not rename itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    /// Renames a field, written in `snake_case`.
+    pub fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal | Self::Camel => {
+                let mut pascal = String::new();
+                let mut capitalize = self == Self::Pascal;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                pascal
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// Renames a variant, written in `PascalCase`.
+    pub fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::Pascal => variant.to_string(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => variant[..1].to_ascii_lowercase() + &variant[1..],
+            Self::Snake | Self::ScreamingSnake | Self::Kebab | Self::ScreamingKebab => {
+                let mut snake = String::new();
+                for (index, c) in variant.char_indices() {
+                    if index > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+                match self {
+                    Self::ScreamingSnake => snake.to_ascii_uppercase(),
+                    Self::Kebab => snake.replace('_', "-"),
+                    Self::ScreamingKebab => snake.replace('_', "-").to_ascii_uppercase(),
+                    _ => snake,
+                }
+            }
+        }
+    }
+}
+
+/// The value `default` fills a missing field with.
+#[derive(Debug, Clone)]
+pub enum SerdeDefault {
+    /// `default`, which calls `Default::default()`.
+    Trait,
+    /// `default = "path"`.
+    Path(ExprPath),
+}
+
+/// The serde attributes that decide how a field, variant or container is read.
+/// Where serialization and deserialization differ, the deserialization side wins.
+#[derive(Debug, Clone, Default)]
+pub struct SerdeAttrs {
+    pub rename: Option<String>,
+    pub rename_all: Option<RenameRule>,
+    pub aliases: Vec<String>,
+    /// The field may be missing, because of `default` or `skip_serializing_if`.
+    pub optional: bool,
+    pub default: Option<SerdeDefault>,
+    pub skip: bool,
+    pub flatten: bool,
+    pub deny_unknown_fields: bool,
+    /// The first option that reads the value through code of its own, such as
+    /// `with` or `from`, which only serde's derive follows.
+    pub custom: Option<Path>,
+}
+
+impl SerdeAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut ret = Self::default();
+
+        // `rename = "..."` or `rename(deserialize = "...")`
+        fn name(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Option<String>> {
+            if meta.input.peek(Token![=]) {
+                return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+            }
+
+            let mut name = None;
+            meta.parse_nested_meta(|inner| {
+                let value = inner.value()?.parse::<LitStr>()?.value();
+                if inner.path.is_ident("deserialize") {
+                    name = Some(value);
+                }
+                Ok(())
+            })?;
+            Ok(name)
+        }
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if let Some(rename) = name(&meta)? {
+                        ret.rename = Some(rename);
+                    }
+                } else if meta.path.is_ident("rename_all") {
+                    if let Some(rule) = name(&meta)? {
+                        ret.rename_all = Some(RenameRule::parse(&rule).ok_or_else(|| {
+                            meta.error(format!("Unknown rename rule \"{}\"", rule))
+                        })?);
+                    }
+                } else if meta.path.is_ident("alias") {
+                    ret.aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    ret.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    ret.flatten = true;
+                } else if meta.path.is_ident("deny_unknown_fields") {
+                    ret.deny_unknown_fields = true;
+                } else if meta.path.is_ident("default") {
+                    ret.optional = true;
+                    ret.default = Some(if meta.input.peek(Token![=]) {
+                        SerdeDefault::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        SerdeDefault::Trait
+                    });
+                } else if meta.path.is_ident("skip_serializing_if") {
+                    ret.optional = true;
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.input.peek(Token![=]) {
+                    if ret.custom.is_none()
+                        && [
+                            "with",
+                            "deserialize_with",
+                            "from",
+                            "try_from",
+                            "remote",
+                            "tag",
+                        ]
+                        .iter()
+                        .any(|name| meta.path.is_ident(name))
+                    {
+                        ret.custom = Some(meta.path.clone());
+                    }
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.path.is_ident("transparent") || meta.path.is_ident("untagged") {
+                    ret.custom.get_or_insert_with(|| meta.path.clone());
+                } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                    meta.parse_nested_meta(|inner| {
+                        if inner.input.peek(Token![=]) {
+                            inner.value()?.parse::<Expr>()?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(ret)
+    }
+}
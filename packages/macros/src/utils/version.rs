@@ -0,0 +1,65 @@
+use semver::Version;
+use syn::LitStr;
+
+use crate::tools::MigrationComment;
+
+/// The canonical form of a plain version, so that `"0.2"` and `"0.2.0"` compare equal.
+/// Ranges and other schemes are kept as they are.
+pub fn normalize_version(version: &str) -> String {
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(split);
+    let padding = match core.split('.').count() {
+        1 => ".0.0",
+        2 => ".0",
+        _ => "",
+    };
+
+    match Version::parse(&format!("{}{}{}", core, padding, suffix)) {
+        Ok(version) => version.to_string(),
+        Err(_) => version.to_string(),
+    }
+}
+
+/// The versions ordered from the newest to the oldest by semver, followed by the
+/// ones that are not plain versions in the order they were given.
+pub fn newest_first(versions: &[String]) -> Vec<&String> {
+    let mut versions = versions
+        .iter()
+        .map(|version| (Version::parse(&normalize_version(version)).ok(), version))
+        .collect::<Vec<_>>();
+    versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    versions.into_iter().map(|(_, version)| version).collect()
+}
+
+/// The numeric id of a version with `repr = "envelope_id"`, the 32-bit FNV-1a hash
+/// of the normalized version. It has to match `hifumi::version_id`.
+pub fn version_id(version: &str) -> u32 {
+    let version = version.trim();
+    let version = normalize_version(version.strip_prefix('v').unwrap_or(version));
+
+    version.bytes().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Rewrites every version of the migrations to the first spelling of an equivalent
+/// version, the current one first, so that the chain can be walked by plain string
+/// comparison while still linking `"0.2"` to `"0.2.0"`.
+pub fn canonicalize_versions(current: &str, versions: &mut [MigrationComment]) {
+    let mut spellings = vec![(normalize_version(current), current.to_string())];
+
+    let mut canonicalize = |version: &mut LitStr| {
+        let normalized = normalize_version(&version.value());
+
+        match spellings.iter().find(|(key, _)| *key == normalized) {
+            Some((_, spelling)) => *version = LitStr::new(spelling, version.span()),
+            None => spellings.push((normalized, version.value())),
+        }
+    };
+
+    for item in versions.iter_mut() {
+        canonicalize(&mut item.to);
+        canonicalize(&mut item.from);
+    }
+}
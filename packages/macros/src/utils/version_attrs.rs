@@ -0,0 +1,25 @@
+use syn::Attribute;
+
+/// Whether the attributes of a field mark it with `#[version(context)]`, which
+/// reads it with the migration context of the type.
+pub fn is_context_field(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut ret = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("version")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("context") {
+                ret = true;
+                Ok(())
+            } else {
+                Err(meta.error("Expected `context`"))
+            }
+        })?;
+    }
+
+    Ok(ret)
+}
+
+/// Removes `#[version(...)]` from the attributes of a field, since only the macro
+/// reads it.
+pub fn strip_version_attrs(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|attr| !attr.path().is_ident("version"));
+}
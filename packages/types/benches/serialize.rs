@@ -0,0 +1,148 @@
+use criterion::{black_box, criterion_group, BatchSize, Criterion};
+use hifumi::{version, MigrateInto, MigrateIntoOwned};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts the bytes held by the process, and the most it held at once.
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    let current = CURRENT.fetch_add(size, Ordering::SeqCst) + size;
+    PEAK.fetch_max(current, Ordering::SeqCst);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+            grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Prints the most bytes held at once while `f` runs, on top of what was held
+/// before it.
+fn peak_allocation<R>(name: &str, f: impl FnOnce() -> R) {
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    drop(black_box(f()));
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    println!(
+        "{name:<40} peak allocation: {:.2} MiB",
+        peak as f64 / 1024. / 1024.
+    );
+}
+
+#[version("0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    rows => cells: Vec<Vec<String>>,
+})]
+#[migration("0.2" => "0.3" {
+    + title: String,
+})]
+struct Sheet {
+    title: String,
+    cells: Vec<Vec<String>>,
+}
+
+fn sheet() -> Sheet {
+    Sheet {
+        title: "sheet".to_string(),
+        cells: (0..1_000)
+            .map(|row| (0..100).map(|column| format!("{row}:{column}")).collect())
+            .collect(),
+    }
+}
+
+fn serialize(c: &mut Criterion) {
+    let sheet = sheet();
+    // What `Serialize` did before the proxy: build the historical twin of the
+    // current version from an owned copy of the value
+    let serialize_owned = |sheet: &Sheet| serde_json::to_vec(&SheetV0_3::from(sheet.to_owned()));
+
+    peak_allocation("serialize by reference", || {
+        serde_json::to_vec(&sheet).unwrap()
+    });
+    peak_allocation("serialize From(self.to_owned())", || {
+        serialize_owned(&sheet).unwrap()
+    });
+
+    c.bench_function("serialize by reference", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&sheet)).unwrap())
+    });
+    c.bench_function("serialize From(self.to_owned())", |b| {
+        b.iter(|| serialize_owned(black_box(&sheet)).unwrap())
+    });
+}
+
+fn migrate(c: &mut Criterion) {
+    let payload = serde_json::to_value(sheet()).unwrap();
+    let mut payload = payload.as_object().unwrap().clone();
+    payload.insert("$version".to_string(), "0.1".into());
+    payload.remove("title");
+    let rows = payload.remove("cells").unwrap();
+    payload.insert("rows".to_string(), rows);
+    let payload = serde_json::Value::Object(payload);
+
+    c.bench_function("migrate 0.1 to 0.3", |b| {
+        b.iter_batched(
+            || payload.clone(),
+            |payload| serde_json::from_value::<Sheet>(payload).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let old = SheetV0_1 {
+        rows: sheet().cells,
+    };
+
+    peak_allocation("migrate a clone", || {
+        MigrateInto::<Sheet>::migrate(&old).unwrap()
+    });
+    let owned = old.clone();
+    peak_allocation("migrate by move", || {
+        MigrateIntoOwned::<Sheet>::migrate_into(owned).unwrap()
+    });
+
+    c.bench_function("migrate a clone", |b| {
+        b.iter(|| MigrateInto::<Sheet>::migrate(black_box(&old)).unwrap())
+    });
+    c.bench_function("migrate by move", |b| {
+        b.iter_batched(
+            || old.clone(),
+            |old| MigrateIntoOwned::<Sheet>::migrate_into(black_box(old)).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, serialize, migrate);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,862 @@
+//! A buffer for self-describing data, so that the `$version` tag can be read before
+//! the rest of the payload is deserialized into the matching historical version.
+//!
+//! Borrowed strings and bytes stay borrowed, so zero-copy fields keep working.
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Serialize, Serializer,
+};
+use std::{fmt, marker::PhantomData};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content<'de> {
+    Bool(bool),
+
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+
+    F32(f32),
+    F64(f64),
+
+    Char(char),
+    String(String),
+    Str(&'de str),
+    ByteBuf(Vec<u8>),
+    Bytes(&'de [u8]),
+
+    None,
+    Some(Box<Content<'de>>),
+
+    Unit,
+    Newtype(Box<Content<'de>>),
+    Seq(Vec<Content<'de>>),
+    Map(Vec<(Content<'de>, Content<'de>)>),
+}
+
+impl<'de> Content<'de> {
+    /// Copies the borrowed strings and bytes, so that the content outlives the input.
+    pub fn into_owned(self) -> Content<'static> {
+        match self {
+            Self::Bool(value) => Content::Bool(value),
+            Self::U8(value) => Content::U8(value),
+            Self::U16(value) => Content::U16(value),
+            Self::U32(value) => Content::U32(value),
+            Self::U64(value) => Content::U64(value),
+            Self::I8(value) => Content::I8(value),
+            Self::I16(value) => Content::I16(value),
+            Self::I32(value) => Content::I32(value),
+            Self::I64(value) => Content::I64(value),
+            Self::F32(value) => Content::F32(value),
+            Self::F64(value) => Content::F64(value),
+            Self::Char(value) => Content::Char(value),
+            Self::String(value) => Content::String(value),
+            Self::Str(value) => Content::String(value.to_owned()),
+            Self::ByteBuf(value) => Content::ByteBuf(value),
+            Self::Bytes(value) => Content::ByteBuf(value.to_owned()),
+            Self::None => Content::None,
+            Self::Some(value) => Content::Some(Box::new(value.into_owned())),
+            Self::Unit => Content::Unit,
+            Self::Newtype(value) => Content::Newtype(Box::new(value.into_owned())),
+            Self::Seq(items) => Content::Seq(items.into_iter().map(Content::into_owned).collect()),
+            Self::Map(entries) => Content::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            Self::Str(value) => Some(value),
+            Self::ByteBuf(value) => std::str::from_utf8(value).ok(),
+            Self::Bytes(value) => std::str::from_utf8(value).ok(),
+            _ => None,
+        }
+    }
+
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            Self::Bool(value) => de::Unexpected::Bool(*value),
+            Self::U8(value) => de::Unexpected::Unsigned(*value as u64),
+            Self::U16(value) => de::Unexpected::Unsigned(*value as u64),
+            Self::U32(value) => de::Unexpected::Unsigned(*value as u64),
+            Self::U64(value) => de::Unexpected::Unsigned(*value),
+            Self::I8(value) => de::Unexpected::Signed(*value as i64),
+            Self::I16(value) => de::Unexpected::Signed(*value as i64),
+            Self::I32(value) => de::Unexpected::Signed(*value as i64),
+            Self::I64(value) => de::Unexpected::Signed(*value),
+            Self::F32(value) => de::Unexpected::Float(*value as f64),
+            Self::F64(value) => de::Unexpected::Float(*value),
+            Self::Char(value) => de::Unexpected::Char(*value),
+            Self::String(value) => de::Unexpected::Str(value),
+            Self::Str(value) => de::Unexpected::Str(value),
+            Self::ByteBuf(value) => de::Unexpected::Bytes(value),
+            Self::Bytes(value) => de::Unexpected::Bytes(value),
+            Self::None | Self::Some(_) => de::Unexpected::Option,
+            Self::Unit => de::Unexpected::Unit,
+            Self::Newtype(_) => de::Unexpected::NewtypeStruct,
+            Self::Seq(_) => de::Unexpected::Seq,
+            Self::Map(_) => de::Unexpected::Map,
+        }
+    }
+}
+
+struct ContentVisitor<'de> {
+    marker: PhantomData<Content<'de>>,
+}
+
+impl<'de> Visitor<'de> for ContentVisitor<'de> {
+    type Value = Content<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Content::Bool(value))
+    }
+
+    fn visit_i8<E>(self, value: i8) -> Result<Self::Value, E> {
+        Ok(Content::I8(value))
+    }
+
+    fn visit_i16<E>(self, value: i16) -> Result<Self::Value, E> {
+        Ok(Content::I16(value))
+    }
+
+    fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E> {
+        Ok(Content::I32(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Content::I64(value))
+    }
+
+    fn visit_u8<E>(self, value: u8) -> Result<Self::Value, E> {
+        Ok(Content::U8(value))
+    }
+
+    fn visit_u16<E>(self, value: u16) -> Result<Self::Value, E> {
+        Ok(Content::U16(value))
+    }
+
+    fn visit_u32<E>(self, value: u32) -> Result<Self::Value, E> {
+        Ok(Content::U32(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Content::U64(value))
+    }
+
+    fn visit_f32<E>(self, value: f32) -> Result<Self::Value, E> {
+        Ok(Content::F32(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Content::F64(value))
+    }
+
+    fn visit_char<E>(self, value: char) -> Result<Self::Value, E> {
+        Ok(Content::Char(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Content::String(value.to_owned()))
+    }
+
+    fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E> {
+        Ok(Content::Str(value))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Content::String(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(value.to_owned()))
+    }
+
+    fn visit_borrowed_bytes<E>(self, value: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Content::Bytes(value))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer).map(|value| Content::Some(Box::new(value)))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer).map(|value| Content::Newtype(Box::new(value)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Content::Seq(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Content::Map(entries))
+    }
+
+    fn visit_enum<A>(self, _data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        Err(de::Error::custom(
+            "enum input cannot be buffered while looking for the version tag",
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for Content<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ContentVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+// Writes the content back as it was read, so that buffered data survives a round trip
+impl Serialize for Content<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Bool(value) => serializer.serialize_bool(*value),
+            Self::U8(value) => serializer.serialize_u8(*value),
+            Self::U16(value) => serializer.serialize_u16(*value),
+            Self::U32(value) => serializer.serialize_u32(*value),
+            Self::U64(value) => serializer.serialize_u64(*value),
+            Self::I8(value) => serializer.serialize_i8(*value),
+            Self::I16(value) => serializer.serialize_i16(*value),
+            Self::I32(value) => serializer.serialize_i32(*value),
+            Self::I64(value) => serializer.serialize_i64(*value),
+            Self::F32(value) => serializer.serialize_f32(*value),
+            Self::F64(value) => serializer.serialize_f64(*value),
+            Self::Char(value) => serializer.serialize_char(*value),
+            Self::String(value) => serializer.serialize_str(value),
+            Self::Str(value) => serializer.serialize_str(value),
+            Self::ByteBuf(value) => serializer.serialize_bytes(value),
+            Self::Bytes(value) => serializer.serialize_bytes(value),
+            Self::None => serializer.serialize_none(),
+            Self::Some(value) => serializer.serialize_some(value),
+            Self::Unit => serializer.serialize_unit(),
+            Self::Newtype(value) => value.serialize(serializer),
+            Self::Seq(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Deserializes a buffered [`Content`], as if it was read from the original input.
+pub struct ContentDeserializer<'de, E> {
+    content: Content<'de>,
+    human_readable: bool,
+    marker: PhantomData<E>,
+}
+
+// Derived `Clone` would needlessly require `E: Clone`
+impl<'de, E> Clone for ContentDeserializer<'de, E> {
+    fn clone(&self) -> Self {
+        Self::new(self.content.clone(), self.human_readable)
+    }
+}
+
+impl<'de, E> ContentDeserializer<'de, E> {
+    pub fn new(content: Content<'de>, human_readable: bool) -> Self {
+        Self {
+            content,
+            human_readable,
+            marker: PhantomData,
+        }
+    }
+}
+
+fn visit_content_seq<'de, V, E>(
+    items: Vec<Content<'de>>,
+    human_readable: bool,
+    visitor: V,
+) -> Result<V::Value, E>
+where
+    V: Visitor<'de>,
+    E: de::Error,
+{
+    let mut seq = SeqDeserializer::new(
+        items
+            .into_iter()
+            .map(|item| ContentDeserializer::new(item, human_readable)),
+    );
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_content_map<'de, V, E>(
+    entries: Vec<(Content<'de>, Content<'de>)>,
+    human_readable: bool,
+    visitor: V,
+) -> Result<V::Value, E>
+where
+    V: Visitor<'de>,
+    E: de::Error,
+{
+    let mut map = MapDeserializer::new(entries.into_iter().map(|(key, value)| {
+        (
+            ContentDeserializer::new(key, human_readable),
+            ContentDeserializer::new(value, human_readable),
+        )
+    }));
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de, E> IntoDeserializer<'de, E> for ContentDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de, E> Deserializer<'de> for ContentDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        let human_readable = self.human_readable;
+
+        match self.content {
+            Content::Bool(value) => visitor.visit_bool(value),
+            Content::U8(value) => visitor.visit_u8(value),
+            Content::U16(value) => visitor.visit_u16(value),
+            Content::U32(value) => visitor.visit_u32(value),
+            Content::U64(value) => visitor.visit_u64(value),
+            Content::I8(value) => visitor.visit_i8(value),
+            Content::I16(value) => visitor.visit_i16(value),
+            Content::I32(value) => visitor.visit_i32(value),
+            Content::I64(value) => visitor.visit_i64(value),
+            Content::F32(value) => visitor.visit_f32(value),
+            Content::F64(value) => visitor.visit_f64(value),
+            Content::Char(value) => visitor.visit_char(value),
+            Content::String(value) => visitor.visit_string(value),
+            Content::Str(value) => visitor.visit_borrowed_str(value),
+            Content::ByteBuf(value) => visitor.visit_byte_buf(value),
+            Content::Bytes(value) => visitor.visit_borrowed_bytes(value),
+            Content::None => visitor.visit_none(),
+            Content::Some(value) => {
+                visitor.visit_some(ContentDeserializer::new(*value, human_readable))
+            }
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(value) => {
+                visitor.visit_newtype_struct(ContentDeserializer::new(*value, human_readable))
+            }
+            Content::Seq(items) => visit_content_seq(items, human_readable, visitor),
+            Content::Map(entries) => visit_content_map(entries, human_readable, visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::None | Content::Unit => visitor.visit_none(),
+            Content::Some(value) => {
+                visitor.visit_some(ContentDeserializer::new(*value, self.human_readable))
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Unit => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            // Internally tagged payloads keep nothing but the tag for unit structs
+            Content::Map(ref entries) if entries.is_empty() => visitor.visit_unit(),
+            _ => self.deserialize_unit(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &str, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Newtype(value) => {
+                visitor.visit_newtype_struct(ContentDeserializer::new(*value, self.human_readable))
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        let human_readable = self.human_readable;
+        let (variant, value) = match self.content {
+            Content::Map(mut entries) => {
+                if entries.len() != 1 {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Map,
+                        &"map with a single key",
+                    ));
+                }
+                let (variant, value) = entries.remove(0);
+                (variant, Some(value))
+            }
+            variant @ (Content::String(_) | Content::Str(_)) => (variant, None),
+            other => {
+                return Err(de::Error::invalid_type(
+                    other.unexpected(),
+                    &"string or map",
+                ))
+            }
+        };
+
+        visitor.visit_enum(EnumDeserializer {
+            variant,
+            value,
+            human_readable,
+            marker: PhantomData,
+        })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        drop(self);
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct EnumDeserializer<'de, E> {
+    variant: Content<'de>,
+    value: Option<Content<'de>>,
+    human_readable: bool,
+    marker: PhantomData<E>,
+}
+
+impl<'de, E> EnumAccess<'de> for EnumDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+    type Variant = VariantDeserializer<'de, E>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), E>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(ContentDeserializer::<E>::new(
+            self.variant,
+            self.human_readable,
+        ))?;
+
+        Ok((
+            variant,
+            VariantDeserializer {
+                value: self.value,
+                human_readable: self.human_readable,
+                marker: PhantomData,
+            },
+        ))
+    }
+}
+
+struct VariantDeserializer<'de, E> {
+    value: Option<Content<'de>>,
+    human_readable: bool,
+    marker: PhantomData<E>,
+}
+
+impl<'de, E> VariantAccess<'de> for VariantDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn unit_variant(self) -> Result<(), E> {
+        match self.value {
+            Some(value) => {
+                Deserialize::deserialize(ContentDeserializer::<E>::new(value, self.human_readable))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, E>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(ContentDeserializer::new(value, self.human_readable)),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Content::Seq(items)) => visit_content_seq(items, self.human_readable, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"tuple variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Content::Map(entries)) => visit_content_map(entries, self.human_readable, visitor),
+            Some(Content::Seq(items)) => visit_content_seq(items, self.human_readable, visitor),
+            Some(value) => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"struct variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+/// Buffers a map, splitting off the string value of the `tag` key.
+struct TaggedContentVisitor<'de> {
+    tag: &'static str,
+    marker: PhantomData<Content<'de>>,
+}
+
+impl<'de> Visitor<'de> for TaggedContentVisitor<'de> {
+    type Value = (Option<String>, Vec<(Content<'de>, Content<'de>)>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a map with a `{}` key", self.tag)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut version = None;
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+
+        while let Some(key) = map.next_key::<Content<'de>>()? {
+            if key.as_str() == Some(self.tag) {
+                if version.is_some() {
+                    return Err(de::Error::duplicate_field(self.tag));
+                }
+                let value = map.next_value::<Content<'de>>()?;
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| {
+                        de::Error::invalid_type(value.unexpected(), &"a version string")
+                    })?
+                    .to_owned();
+                version = Some(value);
+            } else {
+                entries.push((key, map.next_value()?));
+            }
+        }
+
+        Ok((version, entries))
+    }
+}
+
+/// Reads a map whose version is stored under the `tag` key, returning the version
+/// and a deserializer for the remaining entries.
+pub fn deserialize_tagged<'de, D>(
+    deserializer: D,
+    tag: &'static str,
+) -> Result<(String, ContentDeserializer<'de, D::Error>), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let human_readable = deserializer.is_human_readable();
+    let (version, entries) = deserializer.deserialize_map(TaggedContentVisitor {
+        tag,
+        marker: PhantomData,
+    })?;
+    let version = version.ok_or_else(|| de::Error::missing_field(tag))?;
+
+    Ok((
+        version,
+        ContentDeserializer::new(Content::Map(entries), human_readable),
+    ))
+}
+
+/// Reads a map whose version is stored under the `tag` key and whose data is stored
+/// under the `content` key, returning the version and a deserializer for the data.
+pub fn deserialize_adjacent<'de, D>(
+    deserializer: D,
+    tag: &'static str,
+    content: &'static str,
+) -> Result<(String, ContentDeserializer<'de, D::Error>), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let human_readable = deserializer.is_human_readable();
+    let (version, entries) = deserializer.deserialize_map(TaggedContentVisitor {
+        tag,
+        marker: PhantomData,
+    })?;
+    let version = version.ok_or_else(|| de::Error::missing_field(tag))?;
+
+    let data = take_entry(entries, content)?;
+
+    Ok((version, ContentDeserializer::new(data, human_readable)))
+}
+
+/// Takes the value of the `key` entry out of a buffered map.
+fn take_entry<'de, E>(
+    entries: Vec<(Content<'de>, Content<'de>)>,
+    key: &'static str,
+) -> Result<Content<'de>, E>
+where
+    E: de::Error,
+{
+    let mut value = None;
+    for (item, data) in entries {
+        if item.as_str() == Some(key) {
+            if value.is_some() {
+                return Err(de::Error::duplicate_field(key));
+            }
+            value = Some(data);
+        }
+    }
+
+    value.ok_or_else(|| de::Error::missing_field(key))
+}
+
+/// Splits the string value of the `tag` key off buffered content, which is left as
+/// it is when it is not a map or has no such key.
+fn split_tag<'de, E>(
+    content: Content<'de>,
+    tag: &'static str,
+) -> Result<(Option<String>, Content<'de>), E>
+where
+    E: de::Error,
+{
+    let entries = match content {
+        Content::Map(entries) => entries,
+        content => return Ok((None, content)),
+    };
+
+    let mut version = None;
+    let mut rest = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        if key.as_str() == Some(tag) {
+            if version.is_some() {
+                return Err(de::Error::duplicate_field(tag));
+            }
+            let value = value
+                .as_str()
+                .ok_or_else(|| de::Error::invalid_type(value.unexpected(), &"a version string"))?
+                .to_owned();
+            version = Some(value);
+        } else {
+            rest.push((key, value));
+        }
+    }
+
+    Ok((version, Content::Map(rest)))
+}
+
+/// The version found in the data, if any, and a deserializer for the rest of it.
+pub type MaybeTagged<'de, E> = (Option<String>, ContentDeserializer<'de, E>);
+
+/// Like [`deserialize_tagged`], but accepts data without the `tag` key, which is
+/// then returned as it is.
+pub fn deserialize_maybe_tagged<'de, D>(
+    deserializer: D,
+    tag: &'static str,
+) -> Result<MaybeTagged<'de, D::Error>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let human_readable = deserializer.is_human_readable();
+    let (version, content) = split_tag(Content::deserialize(deserializer)?, tag)?;
+
+    Ok((version, ContentDeserializer::new(content, human_readable)))
+}
+
+/// Like [`deserialize_adjacent`], but accepts data without the `tag` key, which is
+/// then returned as it is instead of being read from the `content` key.
+pub fn deserialize_maybe_adjacent<'de, D>(
+    deserializer: D,
+    tag: &'static str,
+    content: &'static str,
+) -> Result<MaybeTagged<'de, D::Error>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let human_readable = deserializer.is_human_readable();
+    let (version, data) = match split_tag(Content::deserialize(deserializer)?, tag)? {
+        (Some(version), Content::Map(entries)) => (Some(version), take_entry(entries, content)?),
+        (version, data) => (version, data),
+    };
+
+    Ok((version, ContentDeserializer::new(data, human_readable)))
+}
+
+/// Buffers a map with a single entry, keyed by the version of its value.
+struct ExternalContentVisitor<'de> {
+    marker: PhantomData<Content<'de>>,
+}
+
+impl<'de> Visitor<'de> for ExternalContentVisitor<'de> {
+    type Value = (String, Content<'de>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map with a single version key")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let key = map
+            .next_key::<Content<'de>>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let version = key
+            .as_str()
+            .ok_or_else(|| de::Error::invalid_type(key.unexpected(), &"a version string"))?
+            .to_owned();
+        let value = map.next_value::<Content<'de>>()?;
+
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+
+        Ok((version, value))
+    }
+}
+
+/// Reads a map whose only key is the version of its value, returning the version
+/// and a deserializer for the value.
+pub fn deserialize_external<'de, D>(
+    deserializer: D,
+) -> Result<(String, ContentDeserializer<'de, D::Error>), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let human_readable = deserializer.is_human_readable();
+    let (version, content) = deserializer.deserialize_map(ExternalContentVisitor {
+        marker: PhantomData,
+    })?;
+
+    Ok((version, ContentDeserializer::new(content, human_readable)))
+}
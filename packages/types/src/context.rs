@@ -0,0 +1,404 @@
+//! Outside data made available to the converters while a value is deserialized.
+//!
+//! The context is handed down as an argument, from the seed into the generated
+//! migrations. Fields marked with `#[version(context)]` are read by a generated
+//! visitor rather than by serde's derive, which hands the context on to the
+//! versioned types inside them.
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any, Deserialize,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+use crate::MigrationError;
+
+/// A `DeserializeSeed` that deserializes `T` with `context` available to the
+/// converters of its migrations.
+pub struct WithContext<'c, T, C: ?Sized> {
+    context: Option<&'c C>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'c, T, C: ?Sized> WithContext<'c, T, C> {
+    pub fn new(context: &'c C) -> Self {
+        Self::forward(Some(context))
+    }
+
+    pub(crate) fn context(&self) -> Option<&'c C> {
+        self.context
+    }
+
+    /// Hands on the context of the value being deserialized, if it was given one.
+    #[doc(hidden)]
+    pub fn forward(context: Option<&'c C>) -> Self {
+        Self {
+            context,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T, C: ?Sized> DeserializeSeed<'de> for WithContext<'_, T, C>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    type Value = T;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_in_context(deserializer, self.context)
+    }
+}
+
+/// Deserializes a type declared with `context(...)`, or a version of one with fields
+/// marked `#[version(context)]`. The context is `None` when it is read through
+/// plain `Deserialize`.
+#[doc(hidden)]
+pub trait DeserializeInContext<'de, C: ?Sized>: Sized {
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+/// Migrates `T` into `Self`, with the context of the converters if one was given.
+#[doc(hidden)]
+pub trait MigrateWith<T, C: ?Sized>: Sized {
+    fn migrate_with(value: T, context: Option<&C>) -> Result<Self, MigrationError>;
+}
+
+impl<'de, T, C: ?Sized> DeserializeInContext<'de, C> for Box<T>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_in_context(deserializer, context).map(Box::new)
+    }
+}
+
+struct OptionVisitor<'c, T, C: ?Sized> {
+    context: Option<&'c C>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'de, T, C: ?Sized> Visitor<'de> for OptionVisitor<'_, T, C>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    type Value = Option<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("option")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_in_context(deserializer, self.context).map(Some)
+    }
+}
+
+impl<'de, T, C: ?Sized> DeserializeInContext<'de, C> for Option<T>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_option(OptionVisitor {
+            context,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Caps the capacity taken from a size hint, which the input may lie about.
+fn cautious(hint: Option<usize>) -> usize {
+    hint.unwrap_or(0).min(4096)
+}
+
+struct SeqVisitor<'c, T, C: ?Sized> {
+    context: Option<&'c C>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'de, T, C: ?Sized> Visitor<'de> for SeqVisitor<'_, T, C>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(cautious(seq.size_hint()));
+        while let Some(value) = seq.next_element_seed(WithContext::forward(self.context))? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+impl<'de, T, C: ?Sized> DeserializeInContext<'de, C> for Vec<T>
+where
+    T: DeserializeInContext<'de, C>,
+{
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SeqVisitor {
+            context,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Reads a map whose keys are read as they are and whose values get the context.
+struct MapVisitor<'c, M, C: ?Sized> {
+    context: Option<&'c C>,
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<'de, K, V, C: ?Sized> Visitor<'de> for MapVisitor<'_, BTreeMap<K, V>, C>
+where
+    K: Deserialize<'de> + Ord,
+    V: DeserializeInContext<'de, C>,
+{
+    type Value = BTreeMap<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut values = BTreeMap::new();
+        while let Some(key) = map.next_key()? {
+            values.insert(
+                key,
+                map.next_value_seed(WithContext::forward(self.context))?,
+            );
+        }
+        Ok(values)
+    }
+}
+
+impl<'de, K, V, C: ?Sized> DeserializeInContext<'de, C> for BTreeMap<K, V>
+where
+    K: Deserialize<'de> + Ord,
+    V: DeserializeInContext<'de, C>,
+{
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor::<Self, C> {
+            context,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<'de, K, V, S, C: ?Sized> Visitor<'de> for MapVisitor<'_, HashMap<K, V, S>, C>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: DeserializeInContext<'de, C>,
+    S: BuildHasher + Default,
+{
+    type Value = HashMap<K, V, S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut values = HashMap::with_capacity_and_hasher(cautious(map.size_hint()), S::default());
+        while let Some(key) = map.next_key()? {
+            values.insert(
+                key,
+                map.next_value_seed(WithContext::forward(self.context))?,
+            );
+        }
+        Ok(values)
+    }
+}
+
+impl<'de, K, V, S, C: ?Sized> DeserializeInContext<'de, C> for HashMap<K, V, S>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: DeserializeInContext<'de, C>,
+    S: BuildHasher + Default,
+{
+    fn deserialize_in_context<D>(deserializer: D, context: Option<&C>) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor::<Self, C> {
+            context,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// A key of a struct read by a generated visitor: the index of the field it names,
+/// or the key itself when it names none.
+#[doc(hidden)]
+pub enum FieldName {
+    Known(usize),
+    Unknown(String),
+}
+
+/// Reads a key of a struct whose fields accept the names in `names`, in the order
+/// they are declared. Unknown keys are only kept when `keep_unknown` is set.
+#[doc(hidden)]
+pub struct FieldIdentifier {
+    names: &'static [&'static [&'static str]],
+    keep_unknown: bool,
+}
+
+impl FieldIdentifier {
+    pub fn new(names: &'static [&'static [&'static str]], keep_unknown: bool) -> Self {
+        Self {
+            names,
+            keep_unknown,
+        }
+    }
+
+    fn unknown(&self, key: impl FnOnce() -> String) -> FieldName {
+        FieldName::Unknown(if self.keep_unknown {
+            key()
+        } else {
+            String::new()
+        })
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for FieldIdentifier {
+    type Value = FieldName;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FieldIdentifier {
+    type Value = FieldName;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("field identifier")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match usize::try_from(value) {
+            Ok(index) if index < self.names.len() => Ok(FieldName::Known(index)),
+            _ => Ok(self.unknown(|| value.to_string())),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match self.names.iter().position(|names| names.contains(&value)) {
+            Some(index) => Ok(FieldName::Known(index)),
+            None => Ok(self.unknown(|| value.to_string())),
+        }
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match std::str::from_utf8(value) {
+            Ok(value) => self.visit_str(value),
+            Err(_) => Ok(self.unknown(|| String::from_utf8_lossy(value).into_owned())),
+        }
+    }
+}
+
+/// Stands in for a field missing from a struct, which reads as `None` if the field
+/// is an `Option` and fails as a missing field otherwise, as with serde's derive.
+#[doc(hidden)]
+pub struct MissingField<E> {
+    field: &'static str,
+    marker: PhantomData<E>,
+}
+
+impl<E> MissingField<E> {
+    pub fn new(field: &'static str) -> Self {
+        Self {
+            field,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, E> Deserializer<'de> for MissingField<E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        Err(E::missing_field(self.field))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
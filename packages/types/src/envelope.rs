@@ -0,0 +1,83 @@
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use std::fmt;
+
+use crate::matching::parse_version;
+
+/// The numeric id a version is written as with `repr = "envelope_id"`.
+///
+/// It is the 32-bit FNV-1a hash of the normalized version, so that `"0.2"` and
+/// `"0.2.0"` share an id, and it never changes when other versions are added.
+pub fn version_id(version: &str) -> u32 {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let version = match parse_version(version) {
+        Some(version) => version.to_string(),
+        None => version.to_string(),
+    };
+
+    version.bytes().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Reads the version of an envelope, then the data through the seed made for it.
+struct EnvelopeVisitor<F> {
+    ids: Option<&'static [(u32, &'static str)]>,
+    seed: F,
+}
+
+impl<'de, F, S> Visitor<'de> for EnvelopeVisitor<F>
+where
+    F: FnOnce(String) -> S,
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.ids {
+            Some(_) => formatter.write_str("a version id followed by its data"),
+            None => formatter.write_str("a version followed by its data"),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let version = match self.ids {
+            Some(ids) => {
+                let id = seq
+                    .next_element::<u32>()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                ids.iter()
+                    .find(|(key, _)| *key == id)
+                    .map(|(_, version)| version.to_string())
+                    .ok_or_else(|| de::Error::custom(format!("Unknown version id {}", id)))?
+            }
+            None => seq
+                .next_element::<String>()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?,
+        };
+
+        seq.next_element_seed((self.seed)(version))?
+            .ok_or_else(|| de::Error::invalid_length(1, &"a version followed by its data"))
+    }
+}
+
+/// Reads a two-element tuple of the version (or its id, if `ids` maps them) and the
+/// data, deserializing the data with the seed made for the version.
+///
+/// Unlike the other representations it never calls `deserialize_any`, so it works
+/// with formats that are not self-describing.
+pub fn deserialize_envelope<'de, D, F, S>(
+    deserializer: D,
+    ids: Option<&'static [(u32, &'static str)]>,
+    seed: F,
+) -> Result<S::Value, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(String) -> S,
+    S: DeserializeSeed<'de>,
+{
+    deserializer.deserialize_tuple(2, EnvelopeVisitor { ids, seed })
+}
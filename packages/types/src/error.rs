@@ -0,0 +1,77 @@
+use std::{convert::Infallible, fmt};
+
+/// A fallible converter failed while migrating a value to a newer version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationError {
+    /// The name of the versioned type.
+    pub ty: &'static str,
+    /// The version the hop starts from.
+    pub from: &'static str,
+    /// The version the hop leads to.
+    pub to: &'static str,
+    /// The field (or variant) the failing converter produces.
+    pub target: &'static str,
+    /// The error returned by the converter.
+    pub message: String,
+}
+
+impl MigrationError {
+    pub fn new(
+        ty: &'static str,
+        from: &'static str,
+        to: &'static str,
+        target: &'static str,
+        error: impl fmt::Display,
+    ) -> Self {
+        Self {
+            ty,
+            from,
+            to,
+            target,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to migrate {} from {} to {} at `{}`: {}",
+            self.ty, self.from, self.to, self.target, self.message
+        )
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+// Lets infallible and fallible hops be chained with `?`
+impl From<Infallible> for MigrationError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// A migrated value broke an invariant checked by a `post { ... }` block or by the
+/// `validate = ...` hook of its type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// The name of the versioned type.
+    pub ty: &'static str,
+    /// The version the value was read as.
+    pub version: &'static str,
+    /// The error returned by the check.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid {} read as version {}: {}",
+            self.ty, self.version, self.message
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
@@ -0,0 +1,226 @@
+//! JSON Schema documents describing every version of a type, generated with
+//! `#[version(..., json_schema)]`.
+//!
+//! The version tag is matched exactly: a schema only accepts the versions as they
+//! are declared, while deserialization also reads any tag that is compatible with
+//! one of them, e.g. `"0.2.1"` for a version declared as `"0.2"`.
+
+pub use serde_json::Value;
+
+use serde_json::{json, Map};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// The JSON Schema of a type, inlined wherever the type is used.
+///
+/// It is implemented for the common std types, for every historical version of a
+/// type declared with `json_schema`, and for the type itself, whose schema accepts
+/// any of its versions tagged as declared. Other types used as fields have to implement it by hand.
+pub trait JsonSchema {
+    fn json_schema() -> Value;
+}
+
+macro_rules! impl_json_schema {
+    ($schema:tt => $($ty:ty),*) => {
+        $(
+            impl JsonSchema for $ty {
+                fn json_schema() -> Value {
+                    json!($schema)
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema!({ "type": "boolean" } => bool);
+impl_json_schema!({ "type": "integer" } => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_json_schema!({ "type": "number" } => f32, f64);
+impl_json_schema!({ "type": "string" } => String, str, char);
+impl_json_schema!({ "type": "null" } => ());
+impl_json_schema!({ "type": "object" } => crate::UnknownFields);
+
+impl<T: JsonSchema + ?Sized> JsonSchema for &T {
+    fn json_schema() -> Value {
+        T::json_schema()
+    }
+}
+
+impl<T: JsonSchema + ?Sized> JsonSchema for Box<T> {
+    fn json_schema() -> Value {
+        T::json_schema()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Value {
+        json!({ "anyOf": [T::json_schema(), { "type": "null" }] })
+    }
+}
+
+macro_rules! impl_json_schema_array {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: JsonSchema> JsonSchema for $ty<T> {
+                fn json_schema() -> Value {
+                    json!({ "type": "array", "items": T::json_schema() })
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_array!(Vec, VecDeque, BTreeSet, HashSet);
+
+impl<T: JsonSchema> JsonSchema for [T] {
+    fn json_schema() -> Value {
+        Vec::<T>::json_schema()
+    }
+}
+
+impl<T: JsonSchema, const N: usize> JsonSchema for [T; N] {
+    fn json_schema() -> Value {
+        json!({ "type": "array", "items": T::json_schema(), "minItems": N, "maxItems": N })
+    }
+}
+
+impl<K, V: JsonSchema> JsonSchema for BTreeMap<K, V> {
+    fn json_schema() -> Value {
+        json!({ "type": "object", "additionalProperties": V::json_schema() })
+    }
+}
+
+impl<K, V: JsonSchema, S> JsonSchema for HashMap<K, V, S> {
+    fn json_schema() -> Value {
+        json!({ "type": "object", "additionalProperties": V::json_schema() })
+    }
+}
+
+macro_rules! impl_json_schema_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: JsonSchema),+> JsonSchema for ($($name,)+) {
+            fn json_schema() -> Value {
+                tuple(vec![$($name::json_schema()),+])
+            }
+        }
+    };
+}
+
+impl_json_schema_tuple!(A);
+impl_json_schema_tuple!(A, B);
+impl_json_schema_tuple!(A, B, C);
+impl_json_schema_tuple!(A, B, C, D);
+impl_json_schema_tuple!(A, B, C, D, E);
+impl_json_schema_tuple!(A, B, C, D, E, F);
+
+#[doc(hidden)]
+pub fn tuple(items: Vec<Value>) -> Value {
+    let len = items.len();
+    json!({ "type": "array", "prefixItems": items, "items": false, "minItems": len })
+}
+
+#[doc(hidden)]
+pub fn object(properties: Vec<(&str, Value, bool)>, additional: bool) -> Value {
+    let required = properties
+        .iter()
+        .filter(|(_, _, required)| *required)
+        .map(|(name, _, _)| json!(name))
+        .collect::<Vec<_>>();
+    let properties = properties
+        .into_iter()
+        .map(|(name, schema, _)| (name.to_string(), schema))
+        .collect::<Map<_, _>>();
+
+    let mut schema = json!({ "type": "object", "properties": properties, "required": required });
+    if !additional {
+        schema["additionalProperties"] = json!(false);
+    }
+    schema
+}
+
+/// A variant of an externally tagged enum, whose payload is `None` for a unit variant.
+#[doc(hidden)]
+pub fn variant(name: &str, payload: Option<Value>) -> Value {
+    match payload {
+        None => json!({ "const": name }),
+        Some(payload) => object(vec![(name, payload, true)], false),
+    }
+}
+
+#[doc(hidden)]
+pub fn one_of(schemas: Vec<Value>) -> Value {
+    json!({ "oneOf": schemas })
+}
+
+/// Adds the `tag` property to an object, or to every variant of an enum, which is
+/// what an internally tagged newtype does to its payload.
+#[doc(hidden)]
+pub fn internal(tag: &str, version: &str, mut schema: Value) -> Value {
+    fn insert(schema: &mut Value, tag: &str, version: &str) {
+        schema["properties"][tag] = json!({ "const": version });
+        match schema.get_mut("required").and_then(Value::as_array_mut) {
+            Some(required) => required.insert(0, json!(tag)),
+            None => schema["required"] = json!([tag]),
+        }
+    }
+
+    if let Some(variants) = schema.get_mut("oneOf").and_then(Value::as_array_mut) {
+        for variant in variants.iter_mut() {
+            // A unit variant becomes a key with no value
+            if let Some(name) = variant.get("const").and_then(Value::as_str) {
+                *variant = object(vec![(name, json!({ "type": "null" }), true)], false);
+            }
+            insert(variant, tag, version);
+        }
+        return schema;
+    }
+    if schema["type"] == "object" {
+        insert(&mut schema, tag, version);
+        return schema;
+    }
+
+    json!({
+        "allOf": [object(vec![(tag, json!({ "const": version }), true)], true), schema]
+    })
+}
+
+#[doc(hidden)]
+pub fn adjacent(tag: &str, content: &str, version: &str, schema: Value) -> Value {
+    object(
+        vec![
+            (tag, json!({ "const": version }), true),
+            (content, schema, true),
+        ],
+        false,
+    )
+}
+
+#[doc(hidden)]
+pub fn external(version: &str, schema: Value) -> Value {
+    object(vec![(version, schema, true)], false)
+}
+
+#[doc(hidden)]
+pub fn envelope(version: impl Into<Value>, schema: Value) -> Value {
+    tuple(vec![json!({ "const": version.into() }), schema])
+}
+
+/// Turns the schema of a version into a document of its own.
+#[doc(hidden)]
+pub fn document(title: &str, id: Option<&str>, schema: Value) -> Value {
+    let mut document = Map::new();
+    document.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    if let Some(id) = id {
+        document.insert("$id".to_string(), json!(id));
+    }
+    document.insert("title".to_string(), json!(title));
+    match schema {
+        Value::Object(schema) => document.extend(schema),
+        schema => {
+            document.insert("allOf".to_string(), json!([schema]));
+        }
+    }
+
+    Value::Object(document)
+}
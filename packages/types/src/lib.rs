@@ -1,11 +1,86 @@
 pub use _macros::version;
 
+mod content;
+mod context;
+mod envelope;
+mod error;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+mod matching;
+mod report;
+#[cfg(feature = "specta")]
+mod typescript;
+mod unknown;
+mod validation;
+mod xml;
+mod xml_schema;
+
+pub use context::WithContext;
+pub use envelope::version_id;
+pub use error::{MigrationError, ValidationError};
+#[cfg(feature = "json-schema")]
+pub use json_schema::JsonSchema;
+pub use matching::{parse_version, resolve_version};
+pub use report::{AppliedRule, MigrationHop, MigrationReport, WithReport};
+pub use unknown::UnknownFields;
+pub use validation::Validation;
+
 use anyhow::Result;
 
+#[doc(hidden)]
+pub mod __private {
+    pub use super::content::{
+        deserialize_adjacent, deserialize_external, deserialize_maybe_adjacent,
+        deserialize_maybe_tagged, deserialize_tagged, Content, ContentDeserializer,
+    };
+    pub use super::context::{
+        DeserializeInContext, FieldIdentifier, FieldName, MigrateWith, MissingField,
+    };
+    pub use super::envelope::deserialize_envelope;
+    pub use super::report::DeserializeVersioned;
+    pub use super::unknown::unknown_fields;
+    pub use super::validation::validate;
+    pub use super::xml::{deserialize_xml, serialize_xml, serialize_xml_enum};
+    pub use super::xml_schema::write_xml_schemas;
+    pub use anyhow::{anyhow, Error, Result};
+    #[cfg(feature = "specta")]
+    pub use specta;
+
+    #[cfg(feature = "specta")]
+    pub mod typescript {
+        pub use crate::typescript::{adjacent, envelope, external, internal, union};
+    }
+}
+
+/// Migrates a historical version of a type into a newer one.
+///
+/// The `#[version]` macro implements it from every historical version to each
+/// newer version, including the current type itself, when the version implements
+/// `Clone`.
 pub trait MigrateInto<T> {
+    /// Migrates a copy of the value, which is left as it is.
     fn migrate(&self) -> Result<T>;
 }
 
+/// Migrates a historical version of a type into a newer one by value.
+///
+/// The `#[version]` macro implements it alongside `MigrateInto`, for every version.
+/// The value is moved along the chain, so no field is cloned unless a migration
+/// rule uses it twice.
+pub trait MigrateIntoOwned<T>: Sized {
+    fn migrate_into(self) -> Result<T>;
+}
+
+/// A type whose serialized form carries its version.
+///
+/// The `#[version]` macro implements it for the current version of the type.
 pub trait Versioned {
-    fn version(&self) -> Option<&str>;
+    /// The version the type is serialized as.
+    const CURRENT_VERSION: &'static str;
+    /// Every version that can be deserialized into the type, the current one first.
+    const SUPPORTED_VERSIONS: &'static [&'static str];
+
+    fn version(&self) -> Option<&str> {
+        Some(Self::CURRENT_VERSION)
+    }
 }
@@ -0,0 +1,64 @@
+use semver::{Comparator, Version, VersionReq};
+
+/// Parses a version leniently, filling in a missing minor or patch number with zero,
+/// so that `"1"` and `"0.1"` are read as `1.0.0` and `0.1.0`.
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(split);
+
+    let padding = match core.split('.').count() {
+        1 => ".0.0",
+        2 => ".0",
+        _ => "",
+    };
+    Version::parse(&format!("{}{}{}", core, padding, suffix)).ok()
+}
+
+/// The lowest version a comparator accepts, used to rank overlapping ranges.
+fn lower_bound(comparator: &Comparator) -> Version {
+    Version {
+        major: comparator.major,
+        minor: comparator.minor.unwrap_or(0),
+        patch: comparator.patch.unwrap_or(0),
+        pre: comparator.pre.clone(),
+        build: Default::default(),
+    }
+}
+
+/// Resolves the version written in a payload to one of the declared versions,
+/// returning its index.
+///
+/// An identical string always wins. Otherwise every declared version acts as a
+/// range: plain versions such as `"0.1"` accept any compatible version like
+/// Cargo's caret requirements do, and explicit ranges like `"~0.1.2"`, `"0.1.x"`
+/// or `"=1.0.0"` are supported as well. When several ranges match, the one with the
+/// highest lower bound is the closest and is chosen.
+pub fn resolve_version(version: &str, declared: &[&str]) -> Option<usize> {
+    if let Some(index) = declared.iter().position(|item| *item == version) {
+        return Some(index);
+    }
+
+    let version = parse_version(version)?;
+    declared
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let req = VersionReq::parse(item).ok()?;
+            if !req.matches(&version) {
+                return None;
+            }
+
+            // `*` has no comparator at all and ranks below every other range
+            let bound = req
+                .comparators
+                .iter()
+                .map(lower_bound)
+                .max()
+                .unwrap_or(Version::new(0, 0, 0));
+            Some((bound, index))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, index)| index)
+}
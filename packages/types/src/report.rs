@@ -0,0 +1,139 @@
+//! A description of how a value was migrated while it was deserialized.
+//!
+//! Like the migration context, the report is handed down as an argument: the seed
+//! gives the generated deserialization a slot, which is filled once the value is
+//! migrated. Only the value read with the seed gets the slot, so the values nested
+//! inside it never overwrite its report.
+
+use serde::de::{DeserializeSeed, Deserializer};
+use std::marker::PhantomData;
+
+use crate::WithContext;
+
+/// What happened to a value while it was deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The version the value was read as.
+    pub source: &'static str,
+    /// The hops walked to reach the current version, oldest first.
+    pub hops: Vec<MigrationHop>,
+}
+
+impl MigrationReport {
+    /// Whether the value was read as an older version, and should be written back to
+    /// be kept up to date.
+    pub fn migrated(&self) -> bool {
+        !self.hops.is_empty()
+    }
+}
+
+/// A hop walked through the `From` chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationHop {
+    pub from: &'static str,
+    pub to: &'static str,
+    /// The rules of the hop, in the order they are declared. For an enum these are
+    /// all the rules of the hop, whichever variant the value holds.
+    pub rules: Vec<AppliedRule>,
+}
+
+/// A rule of a `#[migration]` block. The fields of struct-like variants are named
+/// `Variant.field`, and positional fields by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppliedRule {
+    /// `+ field: T`, filled by `Default::default()`.
+    Defaulted { field: &'static str },
+    /// A field filled by a converter from the older fields in `from`.
+    Converted {
+        field: &'static str,
+        from: &'static [&'static str],
+    },
+    /// `from => field: T`, moved as it is.
+    Renamed {
+        field: &'static str,
+        from: &'static str,
+    },
+    /// `+ from => field: T`, copied as it is.
+    Copied {
+        field: &'static str,
+        from: &'static str,
+    },
+    /// `- field: T`, dropped.
+    Removed { field: &'static str },
+    /// `+ Variant`.
+    VariantAdded { variant: &'static str },
+    /// `- Variant`, converted by hand or replaced by `Default::default()`.
+    VariantRemoved {
+        variant: &'static str,
+        converted: bool,
+    },
+    /// `Old => Variant` or `(A, B) => Variant`.
+    VariantRenamed {
+        variant: &'static str,
+        from: &'static [&'static str],
+    },
+}
+
+/// Deserializes a versioned type with its migration context, if it declares one
+/// with `context(...)`, and records its report into `report` if it is given.
+/// Types without a context implement it with `()`.
+#[doc(hidden)]
+pub trait DeserializeVersioned<'de, C: ?Sized>: Sized {
+    fn deserialize_versioned<D>(
+        deserializer: D,
+        context: Option<&C>,
+        report: Option<&mut Option<MigrationReport>>,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+/// A `DeserializeSeed` that deserializes `T` along with the report of its
+/// migration, made with `WithReport::new()` or `WithContext::with_report()`.
+pub struct WithReport<'c, T, C: ?Sized = ()> {
+    context: Option<&'c C>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, C: ?Sized> WithReport<'_, T, C> {
+    pub fn new() -> Self {
+        Self {
+            context: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, C: ?Sized> Default for WithReport<'_, T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'c, T, C: ?Sized> WithContext<'c, T, C> {
+    /// Deserializes the value along with the report of its migration.
+    pub fn with_report(self) -> WithReport<'c, T, C> {
+        WithReport {
+            context: self.context(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T, C: ?Sized> DeserializeSeed<'de> for WithReport<'_, T, C>
+where
+    T: DeserializeVersioned<'de, C>,
+{
+    type Value = (T, MigrationReport);
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut report = None;
+        let value = T::deserialize_versioned(deserializer, self.context, Some(&mut report))?;
+        let report = report.expect("a versioned type records its report once it is migrated");
+
+        Ok((value, report))
+    }
+}
@@ -0,0 +1,160 @@
+//! The shape a type declared with `#[version(..., specta)]` is serialized as, for
+//! `specta` to export: every version wrapped in its version tag, in one union.
+
+use specta::{
+    DataType, DefOpts, EnumRepr, EnumType, EnumVariant, ExportError, Flatten, LiteralType,
+    NamedDataType, NamedDataTypeItem, ObjectField, ObjectType, TupleType, Type,
+};
+
+use crate::UnknownFields;
+
+impl Type for UnknownFields {
+    fn inline(_: DefOpts, _: &[DataType]) -> Result<DataType, ExportError> {
+        Ok(DataType::Record(Box::new((
+            DataType::Primitive(specta::PrimitiveType::String),
+            DataType::Any,
+        ))))
+    }
+}
+
+impl Flatten for UnknownFields {}
+
+fn field(key: &'static str, ty: DataType) -> ObjectField {
+    ObjectField {
+        key,
+        optional: false,
+        flatten: false,
+        ty,
+    }
+}
+
+fn object(fields: Vec<ObjectField>) -> DataType {
+    DataType::Object(ObjectType {
+        generics: vec![],
+        fields,
+        tag: None,
+    })
+}
+
+fn literal(value: &str) -> DataType {
+    DataType::Literal(LiteralType::String(value.to_string()))
+}
+
+/// The definition of a historical version, which `specta` hands out as a named type.
+fn item(ty: DataType) -> DataType {
+    match ty {
+        DataType::Named(NamedDataType { item, .. }) => match item {
+            NamedDataTypeItem::Object(item) => DataType::Object(item),
+            NamedDataTypeItem::Enum(item) => DataType::Enum(item),
+            NamedDataTypeItem::Tuple(item) => DataType::Tuple(item),
+        },
+        ty => ty,
+    }
+}
+
+/// Adds the `tag` field to an object, or to every variant of an enum, which is
+/// what an internally tagged newtype does to its payload.
+pub fn internal(tag: &'static str, version: &str, ty: DataType) -> Vec<DataType> {
+    let tagged = |fields: Vec<ObjectField>| {
+        object(
+            [field(tag, literal(version))]
+                .into_iter()
+                .chain(fields)
+                .collect(),
+        )
+    };
+
+    match item(ty) {
+        DataType::Object(ObjectType { fields, .. }) => vec![tagged(fields)],
+        DataType::Enum(EnumType::Tagged { variants, repr, .. }) => variants
+            .into_iter()
+            .map(|(name, variant)| match (&repr, variant) {
+                // A unit variant becomes a key with no value
+                (EnumRepr::External, EnumVariant::Unit) => {
+                    tagged(vec![field(name, DataType::Literal(LiteralType::None))])
+                }
+                (EnumRepr::External, variant) => tagged(vec![field(name, variant.data_type())]),
+                (EnumRepr::Internal { tag: inner }, EnumVariant::Named(item)) => tagged(
+                    [field(inner, literal(name))]
+                        .into_iter()
+                        .chain(item.fields)
+                        .collect(),
+                ),
+                (EnumRepr::Internal { tag: inner }, _) => tagged(vec![field(inner, literal(name))]),
+                (EnumRepr::Adjacent { tag: inner, .. }, EnumVariant::Unit) => {
+                    tagged(vec![field(inner, literal(name))])
+                }
+                (
+                    EnumRepr::Adjacent {
+                        tag: inner,
+                        content,
+                    },
+                    variant,
+                ) => tagged(vec![
+                    field(inner, literal(name)),
+                    field(content, variant.data_type()),
+                ]),
+            })
+            .collect(),
+        ty => vec![tagged(vec![ObjectField {
+            key: "",
+            optional: false,
+            flatten: true,
+            ty,
+        }])],
+    }
+}
+
+pub fn adjacent(
+    tag: &'static str,
+    content: &'static str,
+    version: &str,
+    ty: DataType,
+) -> Vec<DataType> {
+    vec![object(vec![
+        field(tag, literal(version)),
+        field(content, item(ty)),
+    ])]
+}
+
+pub fn external(version: &'static str, ty: DataType) -> Vec<DataType> {
+    vec![object(vec![field(version, item(ty))])]
+}
+
+/// The version is a string, or the id of the version with `envelope_id`.
+pub fn envelope(version: LiteralType, ty: DataType) -> Vec<DataType> {
+    vec![DataType::Tuple(TupleType {
+        fields: vec![DataType::Literal(version), item(ty)],
+        generics: vec![],
+    })]
+}
+
+/// Every version of a type, any of which can be read.
+pub fn union(
+    name: &'static str,
+    sid: specta::TypeSid,
+    impl_location: specta::ImplLocation,
+    generics: Vec<&'static str>,
+    versions: Vec<Vec<DataType>>,
+) -> NamedDataType {
+    let variants = versions
+        .into_iter()
+        .flatten()
+        .map(|ty| {
+            EnumVariant::Unnamed(TupleType {
+                fields: vec![ty],
+                generics: vec![],
+            })
+        })
+        .collect();
+
+    NamedDataType {
+        name,
+        sid: Some(sid),
+        impl_location: Some(impl_location),
+        comments: &[],
+        export: None,
+        deprecated: None,
+        item: NamedDataTypeItem::Enum(EnumType::Untagged { variants, generics }),
+    }
+}
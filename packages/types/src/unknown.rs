@@ -0,0 +1,122 @@
+use serde::{
+    de::{DeserializeOwned, Deserializer, MapAccess, Visitor},
+    ser::{SerializeMap, Serializer},
+    Deserialize, Serialize,
+};
+use std::{collections::BTreeMap, fmt};
+
+use crate::content::{Content, ContentDeserializer};
+
+/// The keys of a payload that no field of its version describes.
+///
+/// With `#[version(..., preserve_unknown)]` every version flattens them into a
+/// field of this type, so that they are written back as they were read.
+#[derive(Debug, Clone, Default)]
+pub struct UnknownFields {
+    fields: BTreeMap<String, Content<'static>>,
+    human_readable: bool,
+}
+
+impl UnknownFields {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(String::as_str)
+    }
+
+    /// Deserializes the value of `key`, or returns `None` if there is no such key.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Option<Result<T, serde::de::value::Error>> {
+        let content = self.fields.get(key)?.clone();
+        Some(T::deserialize(ContentDeserializer::new(
+            content,
+            self.human_readable,
+        )))
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.fields.remove(key).is_some()
+    }
+}
+
+/// Gathers the unknown keys of a payload read by a generated visitor, which cannot
+/// flatten them as serde's derive does.
+#[doc(hidden)]
+pub fn unknown_fields(
+    fields: BTreeMap<String, Content<'static>>,
+    human_readable: bool,
+) -> UnknownFields {
+    UnknownFields {
+        fields,
+        human_readable,
+    }
+}
+
+// Whether the payload was human readable only matters for reading the values back
+impl PartialEq for UnknownFields {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
+impl Serialize for UnknownFields {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (key, value) in self.fields.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+struct UnknownFieldsVisitor {
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for UnknownFieldsVisitor {
+    type Value = UnknownFields;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of unknown fields")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry::<String, Content<'de>>()? {
+            fields.insert(key, value.into_owned());
+        }
+
+        Ok(UnknownFields {
+            fields,
+            human_readable: self.human_readable,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for UnknownFields {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_map(UnknownFieldsVisitor { human_readable })
+    }
+}
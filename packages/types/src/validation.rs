@@ -0,0 +1,45 @@
+use std::fmt;
+
+use crate::error::ValidationError;
+
+/// The outcome of a `post { ... }` block or a `validate = ...` hook.
+///
+/// A check may return `()` when it only repairs the value, a `bool` telling whether
+/// the value holds, or a `Result` whose error explains why it does not.
+pub trait Validation {
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl Validation for () {
+    fn into_result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl Validation for bool {
+    fn into_result(self) -> Result<(), String> {
+        match self {
+            true => Ok(()),
+            false => Err("validation failed".to_string()),
+        }
+    }
+}
+
+impl<E: fmt::Display> Validation for Result<(), E> {
+    fn into_result(self) -> Result<(), String> {
+        self.map_err(|error| error.to_string())
+    }
+}
+
+/// Turns the outcome of a check on a value of `ty` read as `version` into an error.
+pub fn validate(
+    ty: &'static str,
+    version: &'static str,
+    outcome: impl Validation,
+) -> Result<(), ValidationError> {
+    outcome.into_result().map_err(|message| ValidationError {
+        ty,
+        version,
+        message,
+    })
+}
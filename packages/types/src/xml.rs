@@ -0,0 +1,328 @@
+//! The `repr = "xml"` representation, where the version is an attribute of the root
+//! element, `xmlns` unless `tag` names another one.
+//!
+//! XML formats hand out every value as text, which they parse into the type asked
+//! for, so the data cannot be buffered while its version is looked for. Attributes
+//! come before the child elements instead, so only the attributes in front of the
+//! version are kept aside, and the rest of the element is read straight into the
+//! historical version.
+
+use serde::{
+    de::{
+        self, value::MapAccessDeserializer, DeserializeSeed, Deserializer, IntoDeserializer,
+        MapAccess, Unexpected, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{self, Impossible, SerializeStruct, Serializer},
+    Serialize,
+};
+use std::{fmt, marker::PhantomData, vec};
+
+/// Writes a struct as the root element `name`, with `version` in the attribute
+/// `key` in front of its fields.
+struct RootSerializer<S> {
+    inner: S,
+    name: &'static str,
+    key: &'static str,
+    version: &'static str,
+}
+
+impl<S: Serializer> RootSerializer<S> {
+    fn unsupported<T>(&self) -> Result<T, S::Error> {
+        Err(ser::Error::custom(format!(
+            "{} can only be written as an XML root element if it is a struct",
+            self.name
+        )))
+    }
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, S::Error> {
+                self.unsupported()
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for RootSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = S::SerializeStruct;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    unsupported! {
+        serialize_bool(bool) -> S::Ok;
+        serialize_i8(i8) -> S::Ok;
+        serialize_i16(i16) -> S::Ok;
+        serialize_i32(i32) -> S::Ok;
+        serialize_i64(i64) -> S::Ok;
+        serialize_u8(u8) -> S::Ok;
+        serialize_u16(u16) -> S::Ok;
+        serialize_u32(u32) -> S::Ok;
+        serialize_u64(u64) -> S::Ok;
+        serialize_f32(f32) -> S::Ok;
+        serialize_f64(f64) -> S::Ok;
+        serialize_char(char) -> S::Ok;
+        serialize_str(&str) -> S::Ok;
+        serialize_bytes(&[u8]) -> S::Ok;
+        serialize_none() -> S::Ok;
+        serialize_unit() -> S::Ok;
+        serialize_unit_struct(&'static str) -> S::Ok;
+        serialize_unit_variant(&'static str, u32, &'static str) -> S::Ok;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<S::Ok, S::Error> {
+        self.unsupported()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.unsupported()
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.unsupported()
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<S::SerializeStruct, S::Error> {
+        let mut state = self.inner.serialize_struct(self.name, len + 1)?;
+        state.serialize_field(self.key, self.version)?;
+        Ok(state)
+    }
+}
+
+/// Serializes a struct as the root element `name`, with `version` in the attribute
+/// `key`, written as `@name` like quick-xml expects.
+pub fn serialize_xml<S, T>(
+    serializer: S,
+    name: &'static str,
+    key: &'static str,
+    version: &'static str,
+    value: &T,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    value.serialize(RootSerializer {
+        inner: serializer,
+        name,
+        key,
+        version,
+    })
+}
+
+/// Serializes an enum as the root element `name`, with `version` in the attribute
+/// `key` and the element of its variant inside.
+pub fn serialize_xml_enum<S, T>(
+    serializer: S,
+    name: &'static str,
+    key: &'static str,
+    version: &'static str,
+    value: &T,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    let mut state = serializer.serialize_struct(name, 2)?;
+    state.serialize_field(key, version)?;
+    state.serialize_field("$value", value)?;
+    state.end()
+}
+
+/// The text of an attribute read before the version, parsed into whichever type
+/// its field asks for, as XML formats do.
+struct AttributeDeserializer<E> {
+    value: String,
+    marker: PhantomData<E>,
+}
+
+macro_rules! parse_attribute {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.value), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, E: de::Error> Deserializer<'de> for AttributeDeserializer<E> {
+    type Error = E;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_string(self.value)
+    }
+
+    parse_attribute! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// The root element without its version, with the attributes read before it put
+/// back in front.
+struct RootAccess<A> {
+    attributes: vec::IntoIter<(String, String)>,
+    value: Option<String>,
+    map: A,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for RootAccess<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        match self.attributes.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(IntoDeserializer::<A::Error>::into_deserializer(key))
+                    .map(Some)
+            }
+            None => self.map.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(AttributeDeserializer {
+                value,
+                marker: PhantomData,
+            }),
+            None => self.map.next_value_seed(seed),
+        }
+    }
+}
+
+/// Looks for the version among the attributes of the root element, then reads the
+/// element through the seed made for it.
+struct XmlVisitor<F> {
+    key: &'static str,
+    seed: F,
+}
+
+impl<'de, F, S> Visitor<'de> for XmlVisitor<F>
+where
+    F: FnOnce(String) -> S,
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "an element with its version in the attribute `{}`",
+            &self.key[1..]
+        )
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut attributes = vec![];
+        let version = loop {
+            match map.next_key::<String>()? {
+                Some(key) if key == self.key => break map.next_value::<String>()?,
+                Some(key) if key.starts_with('@') => {
+                    let value = map.next_value::<String>()?;
+                    attributes.push((key, value));
+                }
+                // Child elements only come after every attribute
+                _ => {
+                    return Err(de::Error::custom(format!(
+                        "The version attribute `{}` is missing",
+                        &self.key[1..]
+                    )))
+                }
+            }
+        };
+
+        (self.seed)(version).deserialize(MapAccessDeserializer::new(RootAccess {
+            attributes: attributes.into_iter(),
+            value: None,
+            map,
+        }))
+    }
+}
+
+/// Reads the version from the attribute `key` of the root element, written as
+/// `@name`, then the rest of the element with the seed made for the version.
+pub fn deserialize_xml<'de, D, F, S>(
+    deserializer: D,
+    key: &'static str,
+    seed: F,
+) -> Result<S::Value, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(String) -> S,
+    S: DeserializeSeed<'de>,
+{
+    deserializer.deserialize_map(XmlVisitor { key, seed })
+}
@@ -0,0 +1,30 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Writes the XML Schema of every version into `dir` as `{name}-{version}.xsd`,
+/// creating `dir` if it does not exist yet.
+pub fn write_xml_schemas(
+    dir: &Path,
+    name: &str,
+    schemas: &[(&str, &str)],
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    schemas
+        .iter()
+        .map(|(version, schema)| {
+            let version = version
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '+' => c,
+                    _ => '_',
+                })
+                .collect::<String>();
+            let path = dir.join(format!("{}-{}.xsd", name, version));
+            fs::write(&path, schema)?;
+            Ok(path)
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn keep_field_attributes() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        /// The name of the user
+        #[serde(rename = "userName")]
+        user_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
+        b: i32,
+    }
+
+    let value = Test {
+        user_name: "a".to_string(),
+        nickname: None,
+        b: 1,
+    };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "$version": "0.2", "userName": "a", "b": 1 })
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.2", "userName": "a", "b": 1 })
+        )?,
+        value
+    );
+
+    // Untouched fields keep their attributes in older versions as well
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "userName": "a" }))?,
+        Test { b: 0, ..value }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn per_version_field_attributes() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        #[serde(rename = "user")]
+        name => user_name: String,
+        #[serde(rename = "user_name")]
+        id: u32 => String { id.to_string() } <= { id.parse().unwrap_or_default() },
+        #[serde(default)]
+        - legacy: i32,
+    })]
+    struct Test {
+        #[serde(rename = "userName")]
+        user_name: String,
+        #[serde(rename = "userId")]
+        id: String,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.1", "user": "a", "user_name": 1 })
+        )?,
+        Test {
+            user_name: "a".to_string(),
+            id: "1".to_string()
+        }
+    );
+
+    let value = Test {
+        user_name: "a".to_string(),
+        id: "2".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "$version": "0.2", "userName": "a", "userId": "2" })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tuple_struct_field_attributes() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Test(i32, #[serde(default)] String);
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "0": 1 }))?,
+        Test(1, String::new())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn enum_variant_attributes() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        Resize {
+            + keep_ratio: bool,
+        },
+    })]
+    enum Test {
+        #[serde(rename = "resize")]
+        Resize {
+            #[serde(rename = "w")]
+            width: u32,
+            keep_ratio: bool,
+        },
+        #[serde(rename = "text")]
+        Text(#[serde(default)] String),
+    }
+
+    // The renames hold in the historical version as well
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.1", "resize": { "w": 3 } })
+        )?,
+        Test::Resize {
+            width: 3,
+            keep_ratio: false
+        }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "text": "a" }))?,
+        Test::Text("a".to_string())
+    );
+
+    assert_eq!(
+        serde_json::to_value(Test::Resize {
+            width: 3,
+            keep_ratio: true
+        })?,
+        serde_json::json!({ "$version": "0.2", "resize": { "keep_ratio": true, "w": 3 } })
+    );
+    assert_eq!(
+        serde_json::to_value(Test::Text("a".to_string()))?,
+        serde_json::json!({ "$version": "0.2", "text": "a" })
+    );
+
+    Ok(())
+}
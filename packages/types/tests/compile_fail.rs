@@ -0,0 +1,5 @@
+#[test]
+fn invalid_migration_graphs() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn per_version_rename_all() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    #[migration("0.1" => "0.2" {
+        #![serde(rename_all = "snake_case")]
+        + other_value: i32,
+    })]
+    struct Test {
+        some_value: i32,
+        other_value: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "some_value": 1 }))?,
+        Test {
+            some_value: 1,
+            other_value: 0
+        }
+    );
+    assert_eq!(
+        serde_json::to_value(Test {
+            some_value: 1,
+            other_value: 2
+        })?,
+        serde_json::json!({ "$version": "0.2", "someValue": 1, "otherValue": 2 })
+    );
+    assert_eq!(
+        Test {
+            some_value: 1,
+            other_value: 2
+        }
+        .serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.1", "some_value": 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn inherited_container_attrs() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    #[migration("0.1" => "0.2" {
+        #![serde()]
+        + other_value: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        + third_value: i32,
+    })]
+    struct Test {
+        some_value: i32,
+        other_value: i32,
+        third_value: i32,
+    }
+
+    // 0.2 inherits the attributes of 0.3, while 0.1 clears them
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.2", "someValue": 1, "otherValue": 2 })
+        )?,
+        Test {
+            some_value: 1,
+            other_value: 2,
+            third_value: 0
+        }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "some_value": 1 }))?,
+        Test {
+            some_value: 1,
+            other_value: 0,
+            third_value: 0
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn per_version_deny_unknown_fields() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        #![serde(deny_unknown_fields)]
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    assert!(serde_json::from_value::<Test>(
+        serde_json::json!({ "$version": "0.1", "a": 1, "c": 2 })
+    )
+    .is_err());
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.2", "a": 1, "b": 2, "c": 3 })
+        )?,
+        Test { a: 1, b: 2 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn enum_container_attrs() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    #[migration("0.1" => "0.2" {
+        #![serde(rename_all = "UPPERCASE")]
+        + OtherValue,
+    })]
+    enum Test {
+        SomeValue,
+        OtherValue,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.1", "SOMEVALUE": null })
+        )?,
+        Test::SomeValue
+    );
+
+    Ok(())
+}
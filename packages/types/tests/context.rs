@@ -0,0 +1,316 @@
+use anyhow::Result;
+use bincode::Options;
+use hifumi::{version, WithContext};
+use serde::de::DeserializeSeed;
+use std::collections::HashMap;
+
+struct Lookup {
+    categories: HashMap<i32, String>,
+    now: u64,
+}
+
+fn lookup() -> Lookup {
+    Lookup {
+        categories: HashMap::from([(1, "books".to_string()), (2, "music".to_string())]),
+        now: 42,
+    }
+}
+
+#[test]
+fn converters_use_context() -> Result<()> {
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        code: i32 => category: String { |ctx: &Lookup| ctx.categories[&code].clone() },
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Test {
+        category: String,
+        created_at: u64,
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"$version":"0.1","code":2}"#);
+    assert_eq!(
+        Test::deserialize_with_context(&mut deserializer, &lookup())?,
+        Test {
+            category: "music".to_string(),
+            created_at: 42
+        }
+    );
+
+    // The current version needs no context
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","category":"books","created_at":1}"#)?,
+        Test {
+            category: "books".to_string(),
+            created_at: 1
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn converters_without_context() -> Result<()> {
+    #[version("0.3", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32 { 2 },
+    })]
+    #[migration("0.2" => "0.3" {
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Test {
+        b: i32,
+        created_at: u64,
+    }
+
+    // Only the converters that name the context need it
+    assert_eq!(TestV0_2::from(TestV0_1 {}), TestV0_2 { b: 2 });
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"$version":"0.1"}"#);
+    assert_eq!(
+        Test::deserialize_with_context(&mut deserializer, &lookup())?,
+        Test {
+            b: 2,
+            created_at: 42
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn missing_context() -> Result<()> {
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Test {
+        created_at: u64,
+    }
+
+    assert!(serde_json::from_str::<Test>(r#"{"$version":"0.1"}"#)
+        .unwrap_err()
+        .to_string()
+        .contains("No migration context of type `Lookup` was given"));
+
+    Ok(())
+}
+
+#[test]
+fn fallible_converter_with_context() -> Result<()> {
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        code: i32 => category: String { |ctx: &Lookup|
+            ctx.categories.get(&code).cloned().ok_or("unknown category")
+        }?,
+    })]
+    struct Test {
+        category: String,
+    }
+
+    let value = WithContext::<Test, _>::new(&lookup()).deserialize(
+        &mut serde_json::Deserializer::from_str(r#"{"$version":"0.1","code":1}"#),
+    )?;
+    assert_eq!(
+        value,
+        Test {
+            category: "books".to_string()
+        }
+    );
+    assert!(WithContext::<Test, _>::new(&lookup())
+        .deserialize(&mut serde_json::Deserializer::from_str(
+            r#"{"$version":"0.1","code":3}"#
+        ))
+        .unwrap_err()
+        .to_string()
+        .contains("unknown category"));
+
+    Ok(())
+}
+
+#[test]
+fn borrowed_context() -> Result<()> {
+    #[version("0.2", context(str))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        name: String => name: String { |prefix: &str| format!("{}{}", prefix, name) },
+    })]
+    struct Test {
+        name: String,
+    }
+
+    // The context does not have to outlive anything but the call
+    let prefix = String::from("user-");
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"$version":"0.1","name":"a"}"#);
+    assert_eq!(
+        Test::deserialize_with_context(&mut deserializer, prefix.as_str())?,
+        Test {
+            name: "user-a".to_string()
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn envelope_with_context() -> Result<()> {
+    #[version("0.2", repr = "envelope", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Test {
+        created_at: u64,
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#"["0.1",{}]"#);
+    assert_eq!(
+        Test::deserialize_with_context(&mut deserializer, &lookup())?,
+        Test { created_at: 42 }
+    );
+    assert!(serde_json::from_str::<Test>(r#"["0.1",{}]"#)
+        .unwrap_err()
+        .to_string()
+        .contains("No migration context of type `Lookup` was given"));
+
+    Ok(())
+}
+
+#[test]
+fn nested_context() -> Result<()> {
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Inner {
+        created_at: u64,
+    }
+
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + name: String,
+    })]
+    struct Outer {
+        #[version(context)]
+        items: Vec<Inner>,
+        #[version(context)]
+        #[serde(rename = "main")]
+        first: Option<Inner>,
+        name: String,
+    }
+
+    // The context reaches the values inside every version of the parent
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.1","items":[{"$version":"0.1"},{"$version":"0.2","created_at":1}]}"#,
+    );
+    assert_eq!(
+        Outer::deserialize_with_context(&mut deserializer, &lookup())?,
+        Outer {
+            items: vec![Inner { created_at: 42 }, Inner { created_at: 1 }],
+            first: None,
+            name: String::new(),
+        }
+    );
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.2","items":[],"main":{"$version":"0.1"},"name":"a"}"#,
+    );
+    assert_eq!(
+        Outer::deserialize_with_context(&mut deserializer, &lookup())?,
+        Outer {
+            items: vec![],
+            first: Some(Inner { created_at: 42 }),
+            name: "a".to_string(),
+        }
+    );
+
+    assert!(serde_json::from_str::<Outer>(
+        r#"{"$version":"0.2","items":[{"$version":"0.1"}],"main":null,"name":"a"}"#
+    )
+    .unwrap_err()
+    .to_string()
+    .contains("No migration context of type `Lookup` was given"));
+
+    Ok(())
+}
+
+#[test]
+fn nested_context_in_envelope() -> Result<()> {
+    #[version("0.2", repr = "envelope", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        code: i32 => category: String { |ctx: &Lookup| ctx.categories[&code].clone() },
+    })]
+    struct Inner {
+        category: String,
+    }
+
+    #[version("0.1", repr = "envelope", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Outer {
+        #[version(context)]
+        items: Vec<Inner>,
+    }
+
+    // Formats that are not self-describing read the nested values in place
+    let options = bincode::DefaultOptions::new();
+    let bytes = options.serialize(&("0.1", (vec![("0.1", (2,))],)))?;
+    assert_eq!(
+        Outer::deserialize_with_context(
+            &mut bincode::Deserializer::from_slice(&bytes, options),
+            &lookup()
+        )?,
+        Outer {
+            items: vec![Inner {
+                category: "music".to_string()
+            }],
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn nested_context_with_serde_attributes() -> Result<()> {
+    #[version("0.2", context(Lookup))]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + created_at: u64 { |ctx: &Lookup| ctx.now },
+    })]
+    struct Inner {
+        created_at: u64,
+    }
+
+    #[version("0.1", context(Lookup), preserve_unknown)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Outer {
+        #[version(context)]
+        #[serde(alias = "entries")]
+        item_list: Vec<Inner>,
+        #[serde(default)]
+        count: u32,
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.1","entries":[{"$version":"0.1"}],"extra":true}"#,
+    );
+    let value = Outer::deserialize_with_context(&mut deserializer, &lookup())?;
+    assert_eq!(value.item_list, vec![Inner { created_at: 42 }]);
+    assert_eq!(value.count, 0);
+    assert!(value.unknown_fields.contains_key("extra"));
+    assert!(!value.unknown_fields.contains_key("entries"));
+
+    assert!(
+        serde_json::from_str::<Outer>(r#"{"$version":"0.1","itemList":[],"itemList":[]}"#)
+            .unwrap_err()
+            .to_string()
+            .contains("duplicate field `itemList`")
+    );
+
+    Ok(())
+}
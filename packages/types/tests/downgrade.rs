@@ -0,0 +1,170 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn downgrade_to_current_version() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Test {
+        a: i32,
+    }
+
+    assert_eq!(
+        Test { a: 1 }.serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.1", "a": 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_added_and_renamed_fields() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        a => c: i32,
+    })]
+    struct Test {
+        b: i32,
+        c: i32,
+    }
+
+    let value = Test { b: 2, c: 1 };
+    assert_eq!(
+        value.serialize_as_version("0.2", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.2", "a": 1, "b": 2 })
+    );
+    assert_eq!(
+        value.serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.1", "a": 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_with_reverse_rules() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - legacy: bool <= { *enabled },
+        count: String => i32 { count.parse().unwrap_or_default() } <= { count.to_string() },
+        (first: String, last: String) => name: String {
+            format!("{} {}", first, last)
+        } <= {
+            let (first, last) = name.split_once(' ').unwrap_or((name, ""));
+            (first.to_string(), last.to_string())
+        },
+    })]
+    struct Test {
+        count: i32,
+        enabled: bool,
+        name: String,
+    }
+
+    let value = Test {
+        count: 3,
+        enabled: true,
+        name: "Hifumi Takimoto".to_string(),
+    };
+    let old = value.serialize_as_version("0.1", serde_json::value::Serializer)?;
+    assert_eq!(
+        old,
+        serde_json::json!({
+            "$version": "0.1",
+            "count": "3",
+            "enabled": true,
+            "first": "Hifumi",
+            "last": "Takimoto",
+            "legacy": true,
+        })
+    );
+    assert_eq!(serde_json::from_value::<Test>(old)?, value);
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_without_reverse_rule() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - legacy: bool,
+    })]
+    struct Test {
+        a: i32,
+    }
+
+    let error = Test { a: 1 }
+        .serialize_as_version("0.1", serde_json::value::Serializer)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Cannot downgrade Test from 0.2 to 0.1: the removed field `legacy` has no reverse rule"
+    );
+    assert!(Test { a: 1 }
+        .serialize_as_version("0.3", serde_json::value::Serializer)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_enum_variants() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + Unknown,
+        Bright => Day,
+        (Click, Tap) => Press <= Tap,
+        Move {
+            + speed: f32,
+        },
+    })]
+    enum Event {
+        Day,
+        Press(u32),
+        Move { x: i32, speed: f32 },
+        Unknown,
+    }
+
+    let downgrade = |value: Event| value.serialize_as_version("0.1", serde_json::value::Serializer);
+    assert_eq!(
+        downgrade(Event::Day)?,
+        serde_json::json!({ "$version": "0.1", "Bright": null })
+    );
+    assert_eq!(
+        downgrade(Event::Press(1))?,
+        serde_json::json!({ "$version": "0.1", "Tap": 1 })
+    );
+    assert_eq!(
+        downgrade(Event::Move { x: 1, speed: 2. })?,
+        serde_json::json!({ "$version": "0.1", "Move": { "x": 1 } })
+    );
+    assert_eq!(
+        downgrade(Event::Unknown).unwrap_err().to_string(),
+        "Cannot downgrade Event from 0.2 to 0.1: the variant `Unknown` does not exist yet"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_tuple_struct() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + 2: u8 { 255 },
+    })]
+    struct Rgba(u8, u8, u8);
+
+    assert_eq!(
+        Rgba(1, 2, 3).serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.1", "0": 1, "1": 2 })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn decl_enum() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Test {
+        A,
+        B(i32, String),
+        C { a: i32, b: bool },
+    }
+
+    assert_eq!(
+        serde_json::to_string(&Test::A)?,
+        r#"{"$version":"0.1","A":null}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Test::B(1, "2".to_string()))?,
+        r#"{"$version":"0.1","B":[1,"2"]}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Test::C { a: 1, b: true })?,
+        r#"{"$version":"0.1","C":{"a":1,"b":true}}"#
+    );
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","A":null}"#)?,
+        Test::A
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","B":[1,"2"]}"#)?,
+        Test::B(1, "2".to_string())
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","C":{"a":1,"b":true}}"#)?,
+        Test::C { a: 1, b: true }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_variant() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + C,
+    })]
+    enum Test {
+        A,
+        B(i32),
+        C,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","B":1}"#)?,
+        Test::B(1)
+    );
+    assert!(serde_json::from_str::<Test>(r#"{"$version":"0.1","C":null}"#).is_err());
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","C":null}"#)?,
+        Test::C
+    );
+
+    Ok(())
+}
+
+#[test]
+fn remove_variant() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq, Default)]
+    #[migration("0.1" => "0.2" {
+        - Legacy { code: i32 } => { Self::Code(code.to_string()) },
+        - Unknown,
+    })]
+    enum Test {
+        #[default]
+        Empty,
+        Code(String),
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Legacy":{"code":42}}"#)?,
+        Test::Code("42".to_string())
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Unknown":null}"#)?,
+        Test::Empty
+    );
+    assert!(serde_json::from_str::<Test>(r#"{"$version":"0.2","Unknown":null}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rename_variant() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        Dark => Night,
+        Bright => Day,
+    })]
+    enum Test {
+        Day { level: u8 },
+        Night,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Dark":null}"#)?,
+        Test::Night
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Bright":{"level":3}}"#)?,
+        Test::Day { level: 3 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","Night":null}"#)?,
+        Test::Night
+    );
+
+    Ok(())
+}
+
+#[test]
+fn merge_variants() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        (Click, Tap) => Press,
+    })]
+    enum Test {
+        Press(u32),
+        Scroll(i32),
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Click":1}"#)?,
+        Test::Press(1)
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Tap":2}"#)?,
+        Test::Press(2)
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Scroll":-3}"#)?,
+        Test::Scroll(-3)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn migrate_variant_fields() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.2" => "0.3" {
+        Resize {
+            + keep_ratio: bool,
+            w: u32 => width: u32,
+        },
+    })]
+    #[migration("0.1" => "0.2" {
+        Resize {
+            (x: u32, y: u32) => w: u32 { x * y },
+        },
+        + Reset,
+    })]
+    enum Test {
+        Resize {
+            width: u32,
+            h: u32,
+            keep_ratio: bool,
+        },
+        Reset,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","Resize":{"x":2,"y":3,"h":4}}"#)?,
+        Test::Resize {
+            width: 6,
+            h: 4,
+            keep_ratio: false
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","Resize":{"w":5,"h":4}}"#)?,
+        Test::Resize {
+            width: 5,
+            h: 4,
+            keep_ratio: false
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","Reset":null}"#)?,
+        Test::Reset
+    );
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use bincode::Options;
+use hifumi::{version, version_id};
+
+#[version("0.2", repr = "envelope")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + c: i32,
+    b => d: String,
+})]
+struct Save {
+    a: i32,
+    c: i32,
+    d: String,
+}
+
+#[version("0.2", repr = "envelope_id")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + Dark,
+    Plain => Text,
+})]
+enum Message {
+    Light,
+    Dark,
+    Text(String),
+    Point { x: i32, y: i32 },
+}
+
+#[test]
+fn envelope_bincode() -> Result<()> {
+    let value = Save {
+        a: 1,
+        c: 2,
+        d: "x".to_string(),
+    };
+    let bytes = bincode::serialize(&value)?;
+    assert_eq!(bincode::deserialize::<Save>(&bytes)?, value);
+
+    let old = bincode::serialize(&(
+        "0.1",
+        SaveV0_1 {
+            a: 3,
+            b: "y".to_string(),
+        },
+    ))?;
+    assert_eq!(
+        bincode::deserialize::<Save>(&old)?,
+        Save {
+            a: 3,
+            c: 0,
+            d: "y".to_string(),
+        }
+    );
+
+    let mut downgraded = Vec::new();
+    value.serialize_as_version(
+        "0.1",
+        &mut bincode::Serializer::new(&mut downgraded, bincode::DefaultOptions::new()),
+    )?;
+    assert_eq!(
+        bincode::DefaultOptions::new().deserialize::<(String, SaveV0_1)>(&downgraded)?,
+        (
+            "0.1".to_string(),
+            SaveV0_1 {
+                a: 1,
+                b: "x".to_string()
+            }
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn envelope_postcard() -> Result<()> {
+    let value = Save {
+        a: 1,
+        c: 2,
+        d: "x".to_string(),
+    };
+    let bytes = postcard::to_allocvec(&value)?;
+    assert_eq!(&bytes[..4], b"\x030.2");
+    assert_eq!(postcard::from_bytes::<Save>(&bytes)?, value);
+
+    let old = postcard::to_allocvec(&(
+        "0.1.0",
+        SaveV0_1 {
+            a: 3,
+            b: "y".to_string(),
+        },
+    ))?;
+    assert_eq!(
+        postcard::from_bytes::<Save>(&old)?,
+        Save {
+            a: 3,
+            c: 0,
+            d: "y".to_string(),
+        }
+    );
+
+    let unknown = postcard::to_allocvec(&(
+        "0.3",
+        SaveV0_1 {
+            a: 3,
+            b: "y".to_string(),
+        },
+    ))?;
+    assert!(postcard::from_bytes::<Save>(&unknown).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn envelope_json() -> Result<()> {
+    assert_eq!(
+        serde_json::to_string(&Save {
+            a: 1,
+            c: 2,
+            d: "x".to_string(),
+        })?,
+        r#"["0.2",{"a":1,"c":2,"d":"x"}]"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn envelope_id() -> Result<()> {
+    assert_eq!(version_id("0.2"), version_id("0.2.0"));
+    assert_ne!(version_id("0.1"), version_id("0.2"));
+
+    for value in [
+        Message::Light,
+        Message::Dark,
+        Message::Text("a".to_string()),
+        Message::Point { x: 1, y: 2 },
+    ] {
+        let bytes = bincode::serialize(&value)?;
+        assert_eq!(&bytes[..4], version_id("0.2").to_le_bytes());
+        assert_eq!(bincode::deserialize::<Message>(&bytes)?, value);
+        assert_eq!(
+            postcard::from_bytes::<Message>(&postcard::to_allocvec(&value)?)?,
+            value
+        );
+    }
+
+    let old = postcard::to_allocvec(&(version_id("0.1"), MessageV0_1::Plain("b".to_string())))?;
+    assert_eq!(
+        postcard::from_bytes::<Message>(&old)?,
+        Message::Text("b".to_string())
+    );
+
+    let unknown = postcard::to_allocvec(&(version_id("0.3"), MessageV0_1::Light))?;
+    assert_eq!(
+        postcard::from_bytes::<Message>(&unknown)
+            .unwrap_err()
+            .to_string(),
+        postcard::Error::SerdeDeCustom.to_string()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn fallible_converter() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        count: String => i32 { count.parse::<i32>() }?,
+    })]
+    struct Test {
+        count: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","count":"42"}"#)?,
+        Test { count: 42 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","count":"many"}"#)
+            .unwrap_err()
+            .to_string(),
+        "Failed to migrate Test from 0.1 to 0.2 at `count`: invalid digit found in string"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fallible_converter_with_custom_error() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + percent: u8 { 100 },
+        (done: u32, total: u32) => ratio: f32 {
+            if total == 0 {
+                Err("total must not be zero")
+            } else {
+                Ok(done as f32 / total as f32)
+            }
+        }?,
+    })]
+    struct Test {
+        percent: u8,
+        ratio: f32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","done":1,"total":4}"#)?,
+        Test {
+            percent: 100,
+            ratio: 0.25
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","done":1,"total":0}"#)
+            .unwrap_err()
+            .to_string(),
+        "Failed to migrate Test from 0.1 to 0.2 at `ratio`: total must not be zero"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fallible_hop_in_chain() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        level: String => u8 {
+            match level.as_str() {
+                "low" => Ok(1),
+                "high" => Ok(9),
+                _ => Err(format!("unknown level {}", level)),
+            }
+        }?,
+    })]
+    #[migration("0.2" => "0.3" {
+        + name: String,
+    })]
+    struct Test {
+        level: u8,
+        name: String,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","level":"high"}"#)?,
+        Test {
+            level: 9,
+            name: "".to_string()
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","level":"mid"}"#)
+            .unwrap_err()
+            .to_string(),
+        "Failed to migrate Test from 0.1 to 0.2 at `level`: unknown level mid"
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","level":5}"#)?,
+        Test {
+            level: 5,
+            name: "".to_string()
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fallible_enum_converters() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - Code(i64) => { u16::try_from(_0).map(Self::Port) }?,
+        Resize {
+            size: String => u32 { size.parse::<u32>() }?,
+        },
+    })]
+    enum Command {
+        Port(u16),
+        Resize { size: u32 },
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Command>(r#"{"$version":"0.1","Code":8080}"#)?,
+        Command::Port(8080)
+    );
+    assert_eq!(
+        serde_json::from_str::<Command>(r#"{"$version":"0.1","Code":-1}"#)
+            .unwrap_err()
+            .to_string(),
+        "Failed to migrate Command from 0.1 to 0.2 at `Code`: out of range integral type conversion attempted"
+    );
+    assert_eq!(
+        serde_json::from_str::<Command>(r#"{"$version":"0.1","Resize":{"size":"x"}}"#)
+            .unwrap_err()
+            .to_string(),
+        "Failed to migrate Command from 0.1 to 0.2 at `Resize.size`: invalid digit found in string"
+    );
+
+    Ok(())
+}
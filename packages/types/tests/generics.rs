@@ -0,0 +1,140 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn decl_generic_struct() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + tag: String,
+    })]
+    struct Wrapper<T> {
+        value: T,
+        tag: String,
+    }
+
+    assert_eq!(
+        serde_json::to_string(&Wrapper {
+            value: 1,
+            tag: "a".to_string()
+        })?,
+        r#"{"$version":"0.2","tag":"a","value":1}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Wrapper<String>>(r#"{"$version":"0.1","value":"b"}"#)?,
+        Wrapper {
+            value: "b".to_string(),
+            tag: "".to_string()
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn generic_bounds_and_where_clause() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - legacy: Option<T>,
+    })]
+    struct Bounded<T: Clone + Default, const N: usize>
+    where
+        T: PartialEq,
+    {
+        items: Vec<T>,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Bounded<u8, 4>>(r#"{"$version":"0.1","items":[1,2],"legacy":3}"#)?,
+        Bounded { items: vec![1, 2] }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn generic_param_unused_by_old_version() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + value: Option<T>,
+    })]
+    struct Test<T> {
+        name: String,
+        value: Option<T>,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test<f32>>(r#"{"$version":"0.1","name":"a"}"#)?,
+        Test {
+            name: "a".to_string(),
+            value: None
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn borrowed_fields() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + id: u32 { 7 },
+    })]
+    struct Borrowed<'a> {
+        name: &'a str,
+        id: u32,
+    }
+
+    let input = r#"{"$version":"0.1","name":"hifumi"}"#.to_string();
+    let value = serde_json::from_str::<Borrowed>(&input)?;
+    assert_eq!(
+        value,
+        Borrowed {
+            name: "hifumi",
+            id: 7
+        }
+    );
+    // Zero-copy: the field points into the input buffer
+    assert!(input
+        .as_bytes()
+        .as_ptr_range()
+        .contains(&value.name.as_ptr()));
+
+    assert_eq!(
+        serde_json::to_string(&value)?,
+        r#"{"$version":"0.2","id":7,"name":"hifumi"}"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn decl_generic_enum() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + Data,
+    })]
+    enum Event<T> {
+        Data(T),
+        Empty,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Event<i32>>(r#"{"$version":"0.1","Empty":null}"#)?,
+        Event::Empty
+    );
+    assert_eq!(
+        serde_json::from_str::<Event<i32>>(r#"{"$version":"0.2","Data":1}"#)?,
+        Event::Data(1)
+    );
+    assert_eq!(
+        serde_json::to_string(&Event::Data("a"))?,
+        r#"{"$version":"0.2","Data":"a"}"#
+    );
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use hifumi::{version, Versioned};
+
+#[test]
+fn shortcut_migration() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        a => b: i32 { a / 10 },
+    })]
+    #[migration("0.2" => "0.3" {
+        b => c: i32 { b * 10 },
+    })]
+    #[migration("0.1" => "0.3" {
+        a => c: i32,
+    })]
+    struct Test {
+        c: i32,
+    }
+
+    // The direct migration skips the lossy intermediate step
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "a": 15 }))?,
+        Test { c: 15 }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.2", "b": 1 }))?,
+        Test { c: 10 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn preferred_migration() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration(prefer "0.1" => "0.2" {
+        a => b: i32 { a + 1 },
+    })]
+    #[migration("0.2" => "0.3" {
+        b => c: i32 { b + 1 },
+    })]
+    #[migration("0.1" => "0.3" {
+        a => c: i32,
+    })]
+    struct Test {
+        c: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "a": 1 }))?,
+        Test { c: 3 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn converging_branches() -> Result<()> {
+    #[version("0.6")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.3" => "0.4" {
+        + b: i32 { 4 },
+    })]
+    #[migration("0.3" => "0.5" {
+        + b: i32 { 5 },
+    })]
+    #[migration("0.4" => "0.6" {
+        + c: i32 { 4 },
+    })]
+    #[migration("0.5" => "0.6" {
+        + c: i32 { 5 },
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+        c: i32,
+    }
+
+    assert_eq!(Test::SUPPORTED_VERSIONS, &["0.6", "0.4", "0.5", "0.3"]);
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.3", "a": 1 }))?,
+        Test { a: 1, b: 4, c: 4 }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.4", "a": 1, "b": 2 }))?,
+        Test { a: 1, b: 2, c: 4 }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.5", "a": 1, "b": 2 }))?,
+        Test { a: 1, b: 2, c: 5 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_along_route() -> Result<()> {
+    #[version("0.6")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.3" => "0.4" {
+        + b: i32,
+    })]
+    #[migration("0.3" => "0.5" {
+        + b: i32,
+    })]
+    #[migration("0.4" => "0.6" {
+        + c: i32,
+    })]
+    #[migration("0.5" => "0.6" {
+        + c: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+        c: i32,
+    }
+
+    let value = Test { a: 1, b: 2, c: 3 };
+    assert_eq!(
+        value.serialize_as_version("0.5", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.5", "a": 1, "b": 2 })
+    );
+    assert_eq!(
+        value.serialize_as_version("0.3", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.3", "a": 1 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn enum_shortcut_migration() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - B => { Self::A },
+    })]
+    #[migration("0.2" => "0.3" {
+        + B,
+    })]
+    #[migration("0.1" => "0.3")]
+    enum Test {
+        A,
+        B,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.1", "B": null }))?,
+        Test::B
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "$version": "0.2", "A": null }))?,
+        Test::A
+    );
+
+    Ok(())
+}
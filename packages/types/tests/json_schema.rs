@@ -0,0 +1,201 @@
+use anyhow::Result;
+use hifumi::{version, JsonSchema};
+use serde_json::json;
+
+#[test]
+fn json_schema_per_version() -> Result<()> {
+    #[version("0.2", json_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    #[migration("0.1" => "0.2" {
+        #![serde(deny_unknown_fields)]
+        + user_id: u64,
+        - legacy: Option<bool>,
+    })]
+    struct User {
+        user_id: u64,
+        #[serde(rename = "displayName")]
+        name: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    assert_eq!(
+        <UserV0_1 as JsonSchema>::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "displayName": { "type": "string" },
+                "legacy": { "anyOf": [{ "type": "boolean" }, { "type": "null" }] },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["displayName"],
+            "additionalProperties": false,
+        })
+    );
+
+    let schemas = User::json_schemas();
+    assert_eq!(
+        schemas
+            .iter()
+            .map(|(version, _)| *version)
+            .collect::<Vec<_>>(),
+        vec!["0.2", "0.1"]
+    );
+    assert_eq!(
+        schemas[0].1,
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": "0.2",
+            "title": "User",
+            "type": "object",
+            "properties": {
+                "$version": { "const": "0.2" },
+                "displayName": { "type": "string" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "userId": { "type": "integer" },
+            },
+            "required": ["$version", "displayName", "userId"],
+        })
+    );
+
+    let any = User::json_schema_document();
+    assert_eq!(any["title"], "User");
+    assert_eq!(any["oneOf"].as_array().map(Vec::len), Some(2));
+    assert_eq!(any["oneOf"][1]["properties"]["$version"]["const"], "0.1");
+
+    Ok(())
+}
+
+#[test]
+fn json_schema_enum() -> Result<()> {
+    #[version("0.2", json_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + C,
+    })]
+    enum Test {
+        A,
+        B(i32, String),
+        C { x: f64 },
+    }
+
+    assert_eq!(
+        <TestV0_2 as JsonSchema>::json_schema(),
+        json!({
+            "oneOf": [
+                { "const": "A" },
+                {
+                    "type": "object",
+                    "properties": {
+                        "B": {
+                            "type": "array",
+                            "prefixItems": [{ "type": "integer" }, { "type": "string" }],
+                            "items": false,
+                            "minItems": 2,
+                        },
+                    },
+                    "required": ["B"],
+                    "additionalProperties": false,
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "C": {
+                            "type": "object",
+                            "properties": { "x": { "type": "number" } },
+                            "required": ["x"],
+                        },
+                    },
+                    "required": ["C"],
+                    "additionalProperties": false,
+                },
+            ],
+        })
+    );
+    // The unit variant is written as a key once it is tagged
+    assert_eq!(
+        Test::json_schemas()[1].1["oneOf"][0],
+        json!({
+            "type": "object",
+            "properties": {
+                "$version": { "const": "0.1" },
+                "A": { "type": "null" },
+            },
+            "required": ["$version", "A"],
+            "additionalProperties": false,
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn json_schema_repr() -> Result<()> {
+    #[version("0.1", json_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Inner {
+        a: i32,
+    }
+
+    #[version("0.1", repr = "external", json_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct External {
+        inner: Inner,
+    }
+
+    #[version("0.1", repr = "envelope", json_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Envelope<T> {
+        value: T,
+    }
+
+    assert_eq!(
+        <External as JsonSchema>::json_schema(),
+        json!({
+            "oneOf": [{
+                "type": "object",
+                "properties": {
+                    "0.1": {
+                        "type": "object",
+                        "properties": {
+                            "inner": {
+                                "oneOf": [{
+                                    "type": "object",
+                                    "properties": {
+                                        "$version": { "const": "0.1" },
+                                        "a": { "type": "integer" },
+                                    },
+                                    "required": ["$version", "a"],
+                                }],
+                            },
+                        },
+                        "required": ["inner"],
+                    },
+                },
+                "required": ["0.1"],
+                "additionalProperties": false,
+            }],
+        })
+    );
+    assert_eq!(
+        <Envelope<bool> as JsonSchema>::json_schema(),
+        json!({
+            "oneOf": [{
+                "type": "array",
+                "prefixItems": [
+                    { "const": "0.1" },
+                    {
+                        "type": "object",
+                        "properties": { "value": { "type": "boolean" } },
+                        "required": ["value"],
+                    },
+                ],
+                "items": false,
+                "minItems": 2,
+            }],
+        })
+    );
+
+    Ok(())
+}
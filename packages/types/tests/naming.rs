@@ -0,0 +1,148 @@
+use anyhow::Result;
+use hifumi::{version, MigrateInto};
+
+mod models {
+    use hifumi::version;
+
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32 { 2 },
+    })]
+    #[migration("0.2" => "0.3" {
+        a => c: i32,
+    })]
+    pub struct Test {
+        pub b: i32,
+        pub c: i32,
+    }
+}
+
+mod accounts {
+    use hifumi::version;
+
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + token: String,
+        login => name: String,
+    })]
+    pub struct Account {
+        pub name: String,
+        pub(crate) id: u32,
+        token: String,
+    }
+}
+
+use models::{Test, TestV0_1, TestV0_2};
+
+fn upgrade(value: TestV0_1) -> Result<Test> {
+    value.migrate()
+}
+
+#[test]
+fn nameable_historical_types() -> Result<()> {
+    let value = TestV0_1 { a: 1 };
+    assert_eq!(upgrade(value.clone())?, Test { b: 2, c: 1 });
+    assert_eq!(
+        MigrateInto::<TestV0_2>::migrate(&value)?,
+        TestV0_2 { a: 1, b: 2 }
+    );
+    assert_eq!(Test::from(TestV0_2 { a: 3, b: 4 }), Test { b: 4, c: 3 });
+
+    // A historical type is serialized without its version
+    assert_eq!(serde_json::to_value(&value)?, serde_json::json!({ "a": 1 }));
+
+    Ok(())
+}
+
+#[test]
+fn nameable_historical_enums() -> Result<()> {
+    #[version("1.0.0-rc.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("1.0.0-rc.1" => "1.0.0-rc.2" {
+        A => B,
+    })]
+    enum Test {
+        B,
+    }
+
+    assert_eq!(Test::from(TestV1_0_0_rc_1::A), Test::B);
+    assert_eq!(
+        MigrateInto::<TestV1_0_0_rc_2>::migrate(&TestV1_0_0_rc_1::A)?,
+        TestV1_0_0_rc_2::B
+    );
+
+    Ok(())
+}
+
+#[test]
+fn nameable_historical_tuple_structs() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + 1: String,
+    })]
+    struct Test(i32, String);
+
+    assert_eq!(Test::from(TestV0_1 { _0: 1 }), Test(1, String::new()));
+
+    Ok(())
+}
+
+/// Every historical version is documented, so a public versioned type can be used
+/// in a crate that denies `missing_docs`.
+#[deny(missing_docs)]
+pub mod documented {
+    use hifumi::version;
+
+    /// A documented type.
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        /// Renamed to `c` in `0.3`.
+        a => c: i32,
+    })]
+    pub struct Test {
+        /// Renamed from `a`.
+        pub c: i32,
+        /// Added in `0.2`.
+        pub b: i32,
+    }
+
+    /// A documented enum.
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        A => B,
+        + C,
+    })]
+    pub enum Shape {
+        /// Renamed from `A`.
+        B,
+        /// Added in `0.2`.
+        C,
+    }
+}
+
+#[test]
+fn historical_field_visibility() -> Result<()> {
+    use accounts::{AccountV0_1, AccountV0_2};
+
+    // `login` no longer exists and falls back to the visibility of the struct, while
+    // `id` keeps the visibility it has on the current struct
+    let value = AccountV0_1 {
+        login: "a".to_string(),
+        id: 1,
+    };
+    assert_eq!(value.login, "a");
+    assert_eq!(value.id, 1);
+
+    let value: AccountV0_2 = serde_json::from_str(r#"{"name":"b","id":2,"token":"t"}"#)?;
+    assert_eq!((value.name.as_str(), value.id), ("b", 2));
+
+    Ok(())
+}
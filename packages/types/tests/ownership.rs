@@ -0,0 +1,182 @@
+use anyhow::Result;
+use hifumi::{version, MigrateIntoOwned};
+
+/// A field type that can only be moved, so that any clone of it fails to compile.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Buffer(Vec<u8>);
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Buffer(vec![0])
+    }
+}
+
+#[test]
+fn serialize_without_clone() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + name: String,
+    })]
+    struct Document {
+        name: String,
+        #[serde(rename = "bytes")]
+        data: Buffer,
+    }
+
+    let document = Document {
+        name: "a".to_string(),
+        data: Buffer(vec![1, 2]),
+    };
+    assert_eq!(
+        serde_json::to_string(&document)?,
+        r#"{"$version":"0.2","bytes":[1,2],"name":"a"}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Document>(r#"{"$version":"0.1","bytes":[3]}"#)?,
+        Document {
+            name: "".to_string(),
+            data: Buffer(vec![3]),
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn migration_moves_fields() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        data => payload: Buffer,
+    })]
+    #[migration("0.2" => "0.3" {
+        - legacy: Buffer,
+        + extra: Buffer,
+    })]
+    struct Document {
+        payload: Buffer,
+        extra: Buffer,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Document>(r#"{"$version":"0.1","data":[7],"legacy":[8]}"#)?,
+        Document {
+            payload: Buffer(vec![7]),
+            extra: Buffer(vec![0]),
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn migrate_into_takes_ownership() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        old => new: Buffer,
+    })]
+    struct Document {
+        new: Buffer,
+    }
+
+    let value: Document = MigrateIntoOwned::<Document>::migrate_into(DocumentV0_1 {
+        old: Buffer(vec![5]),
+    })?;
+    assert_eq!(
+        value,
+        Document {
+            new: Buffer(vec![5])
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn serialize_tuple_and_enum_by_reference() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, PartialEq)]
+    struct Pair(Buffer, String);
+
+    #[version("0.1")]
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Blob(Buffer),
+        Named { data: Buffer },
+    }
+
+    assert_eq!(
+        serde_json::to_string(&Pair(Buffer(vec![1]), "b".to_string()))?,
+        r#"{"$version":"0.1","0":[1],"1":"b"}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Shape::Named {
+            data: Buffer(vec![2])
+        })?,
+        r#"{"$version":"0.1","Named":{"data":[2]}}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Shape>(r#"{"$version":"0.1","Blob":[3]}"#)?,
+        Shape::Blob(Buffer(vec![3]))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn downgrade_without_clone() -> Result<()> {
+    // Only the fields carried over into the older version are cloned, so the
+    // fields that it does not have yet can only be moved
+    #[version("0.2")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + data: Buffer,
+        title => name: String,
+    })]
+    struct Document {
+        data: Buffer,
+        name: String,
+    }
+
+    #[version("0.2")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + Blob,
+        Named { + data: Buffer },
+    })]
+    enum Shape {
+        Blob(Buffer),
+        Named { name: String, data: Buffer },
+    }
+
+    let mut serializer = serde_json::Serializer::new(Vec::new());
+    Document {
+        data: Buffer(vec![1]),
+        name: "a".to_string(),
+    }
+    .serialize_as_version("0.1", &mut serializer)?;
+    assert_eq!(
+        String::from_utf8(serializer.into_inner())?,
+        r#"{"$version":"0.1","title":"a"}"#
+    );
+
+    let mut serializer = serde_json::Serializer::new(Vec::new());
+    Shape::Named {
+        name: "b".to_string(),
+        data: Buffer(vec![2]),
+    }
+    .serialize_as_version("0.1", &mut serializer)?;
+    assert_eq!(
+        String::from_utf8(serializer.into_inner())?,
+        r#"{"$version":"0.1","Named":{"name":"b"}}"#
+    );
+
+    let mut serializer = serde_json::Serializer::new(Vec::new());
+    assert!(Shape::Blob(Buffer(vec![3]))
+        .serialize_as_version("0.1", &mut serializer)
+        .is_err());
+
+    Ok(())
+}
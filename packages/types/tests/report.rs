@@ -0,0 +1,230 @@
+use anyhow::Result;
+use bincode::Options;
+use hifumi::{version, AppliedRule, MigrationHop, MigrationReport, WithContext};
+use serde::de::DeserializeSeed;
+
+#[version("0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + b: i32,
+    - legacy: bool,
+    a => c: i32,
+})]
+#[migration("0.2" => "0.3" {
+    + c => d: i32,
+    + e: String { "e".to_string() },
+    (b: i32, c: i32) => f: i32 { b + c },
+})]
+struct Test {
+    d: i32,
+    e: String,
+    f: i32,
+    inner: Inner,
+}
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[migration("0.1" => "0.2" {
+    + Night,
+    - Legacy,
+    Dark => Auto,
+    Resize { + keep_ratio: bool },
+})]
+enum Inner {
+    #[default]
+    Auto,
+    Night,
+    Resize {
+        w: u32,
+        keep_ratio: bool,
+    },
+}
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    name => label: String,
+})]
+struct Tree {
+    label: String,
+    child: Option<Box<Tree>>,
+}
+
+#[version("0.2", context(str), repr = "envelope")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    id: u32 => name: String { |prefix: &str| format!("{prefix}-{id}") },
+})]
+struct Named {
+    name: String,
+}
+
+#[test]
+fn report_upgrade() -> Result<()> {
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.1","a":1,"legacy":true,"inner":{"$version":"0.1","Dark":null}}"#,
+    );
+    let (value, report) = Test::deserialize_with_report(&mut deserializer)?;
+
+    assert_eq!(
+        value,
+        Test {
+            d: 1,
+            e: "e".to_string(),
+            f: 1,
+            inner: Inner::Auto,
+        }
+    );
+    assert!(report.migrated());
+    assert_eq!(
+        report,
+        MigrationReport {
+            source: "0.1",
+            hops: vec![
+                MigrationHop {
+                    from: "0.1",
+                    to: "0.2",
+                    rules: vec![
+                        AppliedRule::Defaulted { field: "b" },
+                        AppliedRule::Removed { field: "legacy" },
+                        AppliedRule::Renamed {
+                            field: "c",
+                            from: "a"
+                        },
+                    ],
+                },
+                MigrationHop {
+                    from: "0.2",
+                    to: "0.3",
+                    rules: vec![
+                        AppliedRule::Copied {
+                            field: "d",
+                            from: "c"
+                        },
+                        AppliedRule::Converted {
+                            field: "e",
+                            from: &[]
+                        },
+                        AppliedRule::Converted {
+                            field: "f",
+                            from: &["b", "c"]
+                        },
+                    ],
+                },
+            ],
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn report_current() -> Result<()> {
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.3.0","d":1,"e":"","f":2,"inner":{"$version":"0.1","Legacy":null}}"#,
+    );
+    let (_, report) = Test::deserialize_with_report(&mut deserializer)?;
+
+    assert!(!report.migrated());
+    assert_eq!(
+        report,
+        MigrationReport {
+            source: "0.3",
+            hops: vec![],
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn report_enum() -> Result<()> {
+    let mut deserializer =
+        serde_json::Deserializer::from_str(r#"{"$version":"0.1","Resize":{"w":1}}"#);
+    let (value, report) = Inner::deserialize_with_report(&mut deserializer)?;
+
+    assert_eq!(
+        value,
+        Inner::Resize {
+            w: 1,
+            keep_ratio: false
+        }
+    );
+    assert_eq!(report.source, "0.1");
+    assert_eq!(
+        report.hops[0].rules,
+        vec![
+            AppliedRule::VariantAdded { variant: "Night" },
+            AppliedRule::VariantRemoved {
+                variant: "Legacy",
+                converted: false
+            },
+            AppliedRule::VariantRenamed {
+                variant: "Auto",
+                from: &["Dark"]
+            },
+            AppliedRule::Defaulted {
+                field: "Resize.keep_ratio"
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn report_nested_value() -> Result<()> {
+    // The child is read after the root picked its version, and must not replace
+    // the report of the root
+    let mut deserializer = serde_json::Deserializer::from_str(
+        r#"{"$version":"0.1","name":"root","child":{"$version":"0.2","label":"leaf","child":null}}"#,
+    );
+    let (value, report) = Tree::deserialize_with_report(&mut deserializer)?;
+
+    assert_eq!(
+        value,
+        Tree {
+            label: "root".to_string(),
+            child: Some(Box::new(Tree {
+                label: "leaf".to_string(),
+                child: None,
+            })),
+        }
+    );
+    assert_eq!(report.source, "0.1");
+    assert!(report.migrated());
+
+    Ok(())
+}
+
+#[test]
+fn report_with_context() -> Result<()> {
+    let options = bincode::DefaultOptions::new();
+    let bytes = options.serialize(&("0.1", NamedV0_1 { id: 7 }))?;
+    let (value, report) = WithContext::<Named, str>::new("item")
+        .with_report()
+        .deserialize(&mut bincode::Deserializer::from_slice(&bytes, options))?;
+
+    assert_eq!(
+        value,
+        Named {
+            name: "item-7".to_string(),
+        }
+    );
+    assert_eq!(
+        report,
+        MigrationReport {
+            source: "0.1",
+            hops: vec![MigrationHop {
+                from: "0.1",
+                to: "0.2",
+                rules: vec![AppliedRule::Converted {
+                    field: "name",
+                    from: &["id"]
+                }],
+            }],
+        }
+    );
+
+    Ok(())
+}
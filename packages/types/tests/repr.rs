@@ -0,0 +1,135 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn custom_tag() -> Result<()> {
+    #[version("0.2", tag = "schemaVersion")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    let value = Test { a: 1, b: 2 };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "schemaVersion": "0.2", "a": 1, "b": 2 })
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "schemaVersion": "0.1", "a": 1 }))?,
+        Test { a: 1, b: 0 }
+    );
+    assert!(serde_json::from_value::<Test>(
+        serde_json::json!({ "$version": "0.2", "a": 1, "b": 2 })
+    )
+    .unwrap_err()
+    .to_string()
+    .contains("missing field `schemaVersion`"));
+
+    Ok(())
+}
+
+#[test]
+fn adjacent_repr() -> Result<()> {
+    #[version("0.2", repr = "adjacent")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    let value = Test { a: 1, b: 2 };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "$version": "0.2", "data": { "a": 1, "b": 2 } })
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(
+            serde_json::json!({ "$version": "0.1", "data": { "a": 1 } })
+        )?,
+        Test { a: 1, b: 0 }
+    );
+    assert_eq!(
+        value.serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({ "$version": "0.1", "data": { "a": 1 } })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn adjacent_repr_with_custom_keys() -> Result<()> {
+    #[version("0.1", tag = "version", repr = "adjacent", content = "payload")]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Test {
+        A(i32),
+        B,
+    }
+
+    assert_eq!(
+        serde_json::to_value(Test::A(1))?,
+        serde_json::json!({ "version": "0.1", "payload": { "A": 1 } })
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"payload":"B","version":"0.1"}"#)?,
+        Test::B
+    );
+    assert!(serde_json::from_str::<Test>(r#"{"version":"0.1"}"#)
+        .unwrap_err()
+        .to_string()
+        .contains("missing field `payload`"));
+
+    Ok(())
+}
+
+#[test]
+fn external_repr() -> Result<()> {
+    #[version("0.2", repr = "external")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    let value = Test { a: 1, b: 2 };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "0.2": { "a": 1, "b": 2 } })
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "0.1": { "a": 1 } }))?,
+        Test { a: 1, b: 0 }
+    );
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({ "0.2.3": { "a": 1, "b": 2 } }))?,
+        value
+    );
+    assert!(serde_json::from_value::<Test>(
+        serde_json::json!({ "0.1": { "a": 1 }, "0.2": { "a": 1, "b": 2 } })
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn external_repr_tuple_struct() -> Result<()> {
+    #[version("0.1", repr = "external")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Test(i32, String);
+
+    let value = Test(1, "a".to_string());
+    let json = serde_json::to_string(&value)?;
+    assert_eq!(serde_json::from_str::<Test>(&json)?, value);
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use hifumi::{resolve_version, version};
+
+#[test]
+fn resolve_exact_and_compatible_versions() {
+    let declared = ["0.2", "0.1"];
+    assert_eq!(resolve_version("0.2", &declared), Some(0));
+    assert_eq!(resolve_version("0.1", &declared), Some(1));
+    assert_eq!(resolve_version("0.1.3", &declared), Some(1));
+    assert_eq!(resolve_version("0.2.0", &declared), Some(0));
+    assert_eq!(resolve_version("0.3", &declared), None);
+    assert_eq!(resolve_version("unknown", &declared), None);
+}
+
+#[test]
+fn resolve_version_ranges() {
+    let declared = ["1.x", "~0.2.1", "=0.1.0", "*"];
+    assert_eq!(resolve_version("1.4.2", &declared), Some(0));
+    assert_eq!(resolve_version("0.2.5", &declared), Some(1));
+    assert_eq!(resolve_version("0.2.0", &declared), Some(3));
+    assert_eq!(resolve_version("0.1", &declared), Some(2));
+    assert_eq!(resolve_version("0.1.1", &declared), Some(3));
+}
+
+#[test]
+fn resolve_closest_version() {
+    let declared = ["1.1", "1.0"];
+    assert_eq!(resolve_version("1.4.2", &declared), Some(0));
+    assert_eq!(resolve_version("1.0.9", &declared), Some(1));
+
+    // Pre-releases only match ranges that mention a pre-release of the same version
+    let declared = ["1.0.0-rc12", "0.9"];
+    assert_eq!(resolve_version("1.0.0-rc12", &declared), Some(0));
+    assert_eq!(resolve_version("1.0.0-rc13", &declared), Some(0));
+    assert_eq!(resolve_version("1.0.0-alpha", &declared), None);
+    assert_eq!(resolve_version("0.9.1-alpha", &declared), None);
+}
+
+#[test]
+fn deserialize_compatible_version() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1.3","a":1}"#)?,
+        Test { a: 1, b: 0 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2.7","a":1,"b":2}"#)?,
+        Test { a: 1, b: 2 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"1.0","a":1,"b":2}"#)
+            .unwrap_err()
+            .to_string(),
+        "No version of Test is compatible with 1.0, expected one of: 0.2, 0.1"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_declared_range() -> Result<()> {
+    #[version("1.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1.x" => "1.0" {
+        a => b: i32,
+    })]
+    #[migration("1.0" => "1.1" {
+        + c: i32,
+    })]
+    struct Test {
+        b: i32,
+        c: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1.9","a":1}"#)?,
+        Test { b: 1, c: 0 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"1.0.4","b":1}"#)?,
+        Test { b: 1, c: 0 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"1.3","b":1,"c":2}"#)?,
+        Test { b: 1, c: 2 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn equivalent_version_spellings() -> Result<()> {
+    #[version("0.2.0")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    assert_eq!(
+        serde_json::to_string(&Test { a: 1, b: 2 })?,
+        r#"{"$version":"0.2.0","a":1,"b":2}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.1","a":1}"#)?,
+        Test { a: 1, b: 0 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn version_tag_position_and_presence() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Test {
+        a: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1,"$version":"0.1"}"#)?,
+        Test { a: 1 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)
+            .unwrap_err()
+            .to_string(),
+        "missing field `$version`"
+    );
+
+    Ok(())
+}
@@ -82,3 +82,193 @@ fn specta_typescript_export() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn specta_versions() -> Result<()> {
+    // 测试 `specta` 选项导出带版本号的所有版本
+    #[version("0.2", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + c: bool,
+    })]
+    struct Test {
+        a: i32,
+        b: String,
+        c: bool,
+    }
+
+    let ts = specta::ts::export::<Test>(&Default::default())?;
+    assert_eq!(
+        ts,
+        r#"export type Test = { $version: "0.2"; a: number; b: string; c: boolean } | { $version: "0.1"; a: number; b: string }"#
+    );
+
+    // 导出的类型与 serde_json 实际写出的数据一致
+    let json = serde_json::to_value(Test {
+        a: 1,
+        b: "hello".to_string(),
+        c: true,
+    })?;
+    assert_eq!(
+        json,
+        serde_json::json!({ "$version": "0.2", "a": 1, "b": "hello", "c": true })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn specta_enum() -> Result<()> {
+    // 枚举的每个变体都带有版本号
+    #[version("0.2", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + Text,
+    })]
+    enum Message {
+        Light,
+        Text(String),
+        Point { x: i32, y: i32 },
+    }
+
+    let ts = specta::ts::export::<Message>(&Default::default())?;
+    assert_eq!(
+        ts,
+        concat!(
+            r#"export type Message = { $version: "0.2"; Light: null }"#,
+            r#" | { $version: "0.2"; Point: { x: number; y: number } }"#,
+            r#" | { $version: "0.2"; Text: string }"#,
+            r#" | { $version: "0.1"; Light: null }"#,
+            r#" | { $version: "0.1"; Point: { x: number; y: number } }"#,
+        )
+    );
+
+    assert_eq!(
+        serde_json::to_value(Message::Light)?,
+        serde_json::json!({ "$version": "0.2", "Light": null })
+    );
+    assert_eq!(
+        serde_json::to_value(Message::Point { x: 1, y: 2 })?,
+        serde_json::json!({ "$version": "0.2", "Point": { "x": 1, "y": 2 } })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn specta_repr() -> Result<()> {
+    // 其它表示方式下的版本号
+    #[version("0.2", repr = "adjacent", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + c: bool,
+    })]
+    struct Adjacent<T> {
+        a: T,
+        c: bool,
+    }
+
+    assert_eq!(
+        specta::ts::export::<Adjacent<i32>>(&Default::default())?,
+        r#"export type Adjacent<T> = { $version: "0.2"; data: { a: T; c: boolean } } | { $version: "0.1"; data: { a: T } }"#
+    );
+
+    #[version("0.2", repr = "external", specta, preserve_unknown)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + c: bool,
+    })]
+    struct External {
+        a: i32,
+        c: bool,
+    }
+
+    assert_eq!(
+        specta::ts::export::<External>(&Default::default())?,
+        r#"export type External = { "0.2": ({ [key: string]: any }) & { a: number; c: boolean } } | { "0.1": ({ [key: string]: any }) & { a: number } }"#
+    );
+
+    #[version("0.2", repr = "envelope", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + c: bool,
+    })]
+    struct Envelope {
+        a: i32,
+        c: bool,
+    }
+
+    assert_eq!(
+        specta::ts::export::<Envelope>(&Default::default())?,
+        r#"export type Envelope = ["0.2", { a: number; c: boolean }] | ["0.1", { a: number }]"#
+    );
+
+    #[version("0.2", repr = "envelope_id", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + c: bool,
+    })]
+    struct EnvelopeId {
+        a: i32,
+        c: bool,
+    }
+
+    assert_eq!(
+        specta::ts::export::<EnvelopeId>(&Default::default())?,
+        format!(
+            "export type EnvelopeId = [{}, {{ a: number; c: boolean }}] | [{}, {{ a: number }}]",
+            hifumi::version_id("0.2"),
+            hifumi::version_id("0.1")
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn specta_nested() -> Result<()> {
+    // 嵌套的版本化类型以名称引用
+    #[version("0.1", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Inner {
+        x: i32,
+    }
+
+    #[version("0.1", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Outer {
+        inner: Inner,
+        items: Vec<Inner>,
+    }
+
+    assert_eq!(
+        specta::ts::export::<Outer>(&Default::default())?,
+        r#"export type Outer = { $version: "0.1"; inner: Inner; items: Inner[] }"#
+    );
+    assert_eq!(
+        specta::ts::export::<Inner>(&Default::default())?,
+        r#"export type Inner = { $version: "0.1"; x: number }"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn specta_documented_versions() -> Result<()> {
+    // 使用 specta 时历史版本同样带有列出其字段的文档
+    #[version("0.2", specta)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: Vec<String>,
+    })]
+    struct Test {
+        a: i32,
+        b: Vec<String>,
+    }
+
+    let ts = specta::ts::export::<TestV0_1>(&Default::default())?;
+    assert!(ts.contains("Version `0.1` of [`Test`], which migrates into version `0.2`."));
+    assert!(ts.contains("- `a`: `i32`"));
+    assert!(ts.contains("- `b` is added with its default value"));
+    Ok(())
+}
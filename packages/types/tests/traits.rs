@@ -0,0 +1,136 @@
+use anyhow::Result;
+use hifumi::{version, MigrateInto, MigrateIntoOwned, Versioned};
+
+fn check_version<T: Versioned>(version: &str) -> bool {
+    T::SUPPORTED_VERSIONS.contains(&version)
+}
+
+#[test]
+fn versioned_constants() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        + c: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+        c: i32,
+    }
+
+    assert_eq!(Test::CURRENT_VERSION, "0.3");
+    assert_eq!(Test::SUPPORTED_VERSIONS, &["0.3", "0.2", "0.1"]);
+    assert_eq!(Test { a: 1, b: 2, c: 3 }.version(), Some("0.3"));
+
+    assert!(check_version::<Test>("0.1"));
+    assert!(!check_version::<Test>("0.4"));
+
+    Ok(())
+}
+
+#[test]
+fn versioned_single_version() -> Result<()> {
+    #[version("1.0.0-rc12")]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Test {
+        A,
+    }
+
+    assert_eq!(Test::CURRENT_VERSION, "1.0.0-rc12");
+    assert_eq!(Test::SUPPORTED_VERSIONS, &["1.0.0-rc12"]);
+    assert_eq!(Test::A.version(), Some("1.0.0-rc12"));
+
+    Ok(())
+}
+
+#[test]
+fn versioned_generic_type() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + value: Option<T>,
+    })]
+    struct Test<T> {
+        value: Option<T>,
+    }
+
+    assert_eq!(<Test<String>>::CURRENT_VERSION, "0.2");
+    assert!(check_version::<Test<u8>>("0.1"));
+
+    Ok(())
+}
+
+#[test]
+fn migrate_into_without_clone() -> Result<()> {
+    #[derive(Debug, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+    struct Handle(u32);
+
+    #[version("0.3")]
+    #[derive(Debug, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: Handle,
+    })]
+    #[migration("0.2" => "0.3" {
+        a => c: Handle,
+    })]
+    struct Test {
+        b: Handle,
+        c: Handle,
+    }
+
+    assert_eq!(
+        MigrateIntoOwned::<Test>::migrate_into(TestV0_1 { a: Handle(1) })?,
+        Test {
+            b: Handle(0),
+            c: Handle(1)
+        }
+    );
+    assert_eq!(
+        MigrateIntoOwned::<TestV0_3>::migrate_into(TestV0_1 { a: Handle(2) })?,
+        TestV0_3 {
+            b: Handle(0),
+            c: Handle(2)
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn migrate_a_copy() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    let value = TestV0_1 { a: 1 };
+    assert_eq!(MigrateInto::<Test>::migrate(&value)?, Test { a: 1, b: 0 });
+    assert_eq!(value.a, 1);
+
+    Ok(())
+}
+
+#[test]
+fn migrate_into_by_hand() -> Result<()> {
+    // Types outside the macro keep implementing the trait with `migrate` alone
+    struct Celsius(f64);
+    struct Fahrenheit(f64);
+
+    impl MigrateInto<Fahrenheit> for Celsius {
+        fn migrate(&self) -> Result<Fahrenheit> {
+            Ok(Fahrenheit(self.0 * 1.8 + 32.))
+        }
+    }
+
+    assert_eq!(Celsius(100.).migrate()?.0, 212.);
+
+    Ok(())
+}
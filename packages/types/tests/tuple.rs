@@ -0,0 +1,121 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn decl_tuple_struct() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Rgb(u8, u8, u8);
+
+    assert_eq!(
+        serde_json::to_string(&Rgb(255, 128, 0))?,
+        r#"{"$version":"0.1","0":255,"1":128,"2":0}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Rgb>(r#"{"$version":"0.1","0":255,"1":128,"2":0}"#)?,
+        Rgb(255, 128, 0)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn decl_newtype_struct() -> Result<()> {
+    #[version("0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Meters(f64);
+
+    assert_eq!(
+        serde_json::to_string(&Meters(1.5))?,
+        r#"{"$version":"0.1","0":1.5}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Meters>(r#"{"$version":"0.1","0":1.5}"#)?,
+        Meters(1.5)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_positional_field() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + 2: u8 { 255 },
+    })]
+    struct Rgba(u8, u8, u8);
+
+    assert_eq!(
+        serde_json::from_str::<Rgba>(r#"{"$version":"0.1","0":1,"1":2}"#)?,
+        Rgba(1, 2, 255)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shift_positional_fields() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + 0: u32,
+        0 => 1: String,
+    })]
+    struct Entry(u32, String);
+
+    assert_eq!(
+        serde_json::from_str::<Entry>(r#"{"$version":"0.1","0":"name"}"#)?,
+        Entry(0, "name".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn change_positional_field_type() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        1: i32 => String { _1.to_string() },
+    })]
+    struct Pair(String, String);
+
+    assert_eq!(
+        serde_json::from_str::<Pair>(r#"{"$version":"0.1","0":"a","1":1}"#)?,
+        Pair("a".to_string(), "1".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn newtype_into_named_struct() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        0 => meters: f64,
+        + unit: String { "m".to_string() },
+    })]
+    struct Distance {
+        meters: f64,
+        unit: String,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Distance>(r#"{"$version":"0.1","0":1.5}"#)?,
+        Distance {
+            meters: 1.5,
+            unit: "m".to_string()
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Distance>(r#"{"$version":"0.2","meters":2.0,"unit":"km"}"#)?,
+        Distance {
+            meters: 2.,
+            unit: "km".to_string()
+        }
+    );
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn array_and_tuple_types() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        - key: [u8; 4],
+        + pos: (f32, f32),
+        (x: f32, y: f32) => point: [f32; 2] { [x, y] },
+    })]
+    struct Test {
+        pos: (f32, f32),
+        point: [f32; 2],
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({
+            "$version": "0.1",
+            "key": [1, 2, 3, 4],
+            "x": 1.0,
+            "y": 2.0
+        }))?,
+        Test {
+            pos: (0., 0.),
+            point: [1., 2.]
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn qualified_path_types() -> Result<()> {
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        a: i32 => std::string::String { a.to_string() },
+        b: ::std::vec::Vec<(u8, u8)> => c: Option<Vec<(u8, u8)>> { Some(b) },
+    })]
+    struct Test {
+        a: String,
+        c: Option<Vec<(u8, u8)>>,
+    }
+
+    assert_eq!(
+        serde_json::from_value::<Test>(serde_json::json!({
+            "$version": "0.1",
+            "a": 1,
+            "b": [[1, 2]]
+        }))?,
+        Test {
+            a: "1".to_string(),
+            c: Some(vec![(1, 2)])
+        }
+    );
+
+    Ok(())
+}
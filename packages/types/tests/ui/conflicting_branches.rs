@@ -0,0 +1,20 @@
+use hifumi::version;
+
+#[version("0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + b: i32,
+})]
+#[migration("0.2" => "0.3" {
+    + c: i32,
+})]
+#[migration("0.1" => "0.3" {
+    + c: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+    c: i32,
+}
+
+fn main() {}
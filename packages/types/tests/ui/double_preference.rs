@@ -0,0 +1,17 @@
+use hifumi::version;
+
+#[version("0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration(prefer "0.1" => "0.2" {
+    + b: i32,
+})]
+#[migration("0.2" => "0.3")]
+#[migration(prefer "0.1" => "0.3" {
+    + b: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+}
+
+fn main() {}
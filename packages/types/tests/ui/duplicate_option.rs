@@ -0,0 +1,23 @@
+use hifumi::version;
+
+#[version("0.1", tag = "version", tag = "schemaVersion")]
+#[derive(Debug, Clone, PartialEq)]
+struct Test {
+    a: i32,
+}
+
+#[version("0.1", repr = "adjacent", content = "data", content = "payload")]
+#[derive(Debug, Clone, PartialEq)]
+struct Content {
+    a: i32,
+}
+
+struct Lookup;
+
+#[version("0.1", context(Lookup), context(Lookup))]
+#[derive(Debug, Clone, PartialEq)]
+struct Context {
+    a: i32,
+}
+
+fn main() {}
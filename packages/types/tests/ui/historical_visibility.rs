@@ -0,0 +1,19 @@
+mod accounts {
+    use hifumi::version;
+
+    #[version("0.2")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + name: String,
+    })]
+    pub struct Account {
+        pub name: String,
+        token: String,
+    }
+}
+
+fn token(value: &accounts::AccountV0_1) -> &str {
+    &value.token
+}
+
+fn main() {}
@@ -0,0 +1,50 @@
+use hifumi::version;
+
+struct Lookup {
+    now: u64,
+}
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + created_at: u64 { |ctx: &Lookup| ctx.now },
+})]
+struct Undeclared {
+    created_at: u64,
+}
+
+#[version("0.2", context(Lookup))]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + created_at: u64 { |ctx: Lookup| ctx.now },
+})]
+struct Owned {
+    created_at: u64,
+}
+
+#[version("0.1")]
+#[derive(Debug, Clone, PartialEq)]
+struct UndeclaredField {
+    #[version(context)]
+    items: Vec<Owned>,
+}
+
+#[version("0.1", context(Lookup))]
+#[derive(Debug, Clone, PartialEq)]
+enum Variant {
+    A {
+        #[version(context)]
+        items: Vec<Owned>,
+    },
+}
+
+#[version("0.1", context(Lookup))]
+#[derive(Debug, Clone, PartialEq)]
+struct Custom {
+    #[version(context)]
+    items: Vec<Owned>,
+    #[serde(with = "serde_bytes")]
+    bytes: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use hifumi::version;
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + B,
+    post { true },
+})]
+enum Test {
+    A,
+    B,
+}
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + b: i32,
+    post { true },
+    post { b > 0 },
+})]
+struct Other {
+    b: i32,
+}
+
+fn main() {}
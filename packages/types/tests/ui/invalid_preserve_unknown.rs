@@ -0,0 +1,7 @@
+use hifumi::version;
+
+#[version("0.1", preserve_unknown)]
+#[derive(Debug, Clone, PartialEq)]
+struct Test(i32);
+
+fn main() {}
@@ -0,0 +1,15 @@
+use hifumi::version;
+
+#[version("0.1", repr = "flattened")]
+#[derive(Debug, Clone, PartialEq)]
+struct Test {
+    a: i32,
+}
+
+#[version("0.1", tag = "schemaVersion", repr = "external")]
+#[derive(Debug, Clone, PartialEq)]
+struct Other {
+    a: i32,
+}
+
+fn main() {}
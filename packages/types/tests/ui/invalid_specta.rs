@@ -0,0 +1,15 @@
+use hifumi::version;
+
+#[version("0.1", specta)]
+#[derive(Debug, Clone, PartialEq, specta::Type)]
+struct Test {
+    a: i32,
+}
+
+#[version("0.1", specta, repr = "xml")]
+#[derive(Debug, Clone, PartialEq)]
+struct Xml {
+    a: i32,
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use hifumi::version;
+
+#[version("0.2", untagged_default = "0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + b: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+}
+
+#[version("0.1", repr = "external", untagged = "structural")]
+#[derive(Debug, Clone, PartialEq)]
+struct Other {
+    a: i32,
+}
+
+#[version("0.1", json_schema, untagged = "structural")]
+#[derive(Debug, Clone, PartialEq)]
+struct Schema {
+    a: i32,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use hifumi::version;
+
+#[version("0.1", repr = "xml", untagged_default = "0.1")]
+#[derive(Debug, Clone, PartialEq)]
+struct Test {
+    a: i32,
+}
+
+#[version("0.1", json_schema, repr = "xml")]
+#[derive(Debug, Clone, PartialEq)]
+struct Schema {
+    a: i32,
+}
+
+#[version("0.1", repr = "xml")]
+#[derive(Debug, Clone, PartialEq)]
+struct Tuple(i32, String);
+
+fn main() {}
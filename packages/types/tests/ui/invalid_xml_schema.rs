@@ -0,0 +1,10 @@
+use hifumi::version;
+
+#[version("0.1", xml_schema)]
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Empty,
+    Line(i32, String),
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use hifumi::version;
+
+#[version("0.3")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.2" => "0.3" {
+    + b: i32,
+})]
+#[migration("0.4" => "0.5" {
+    + c: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use hifumi::version;
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.2.0" => "0.2" {
+    + b: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+}
+
+fn main() {}
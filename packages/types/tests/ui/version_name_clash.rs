@@ -0,0 +1,16 @@
+use hifumi::version;
+
+#[version("0.2")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1-a" => "0.2" {
+    + b: i32,
+})]
+#[migration("0.1.a" => "0.2" {
+    + b: i32,
+})]
+struct Test {
+    a: i32,
+    b: i32,
+}
+
+fn main() {}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use hifumi::{version, UnknownFields};
+
+#[test]
+fn preserve_unknown_fields() -> Result<()> {
+    #[version("0.3", preserve_unknown)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        a => c: i32,
+    })]
+    struct Test {
+        b: i32,
+        c: i32,
+    }
+
+    let value = serde_json::from_value::<Test>(serde_json::json!({
+        "$version": "0.1",
+        "a": 1,
+        "extra": { "nested": [1, 2] },
+        "flag": true
+    }))?;
+    assert_eq!(value.c, 1);
+    assert_eq!(value.unknown_fields.len(), 2);
+    assert_eq!(
+        value.unknown_fields.get::<bool>("flag").transpose()?,
+        Some(true)
+    );
+
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({
+            "$version": "0.3",
+            "b": 0,
+            "c": 1,
+            "extra": { "nested": [1, 2] },
+            "flag": true
+        })
+    );
+    assert_eq!(
+        value.serialize_as_version("0.1", serde_json::value::Serializer)?,
+        serde_json::json!({
+            "$version": "0.1",
+            "a": 1,
+            "extra": { "nested": [1, 2] },
+            "flag": true
+        })
+    );
+
+    let value = Test {
+        b: 1,
+        c: 2,
+        unknown_fields: UnknownFields::default(),
+    };
+    assert_eq!(
+        serde_json::to_value(&value)?,
+        serde_json::json!({ "$version": "0.3", "b": 1, "c": 2 })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn declared_unknown_fields() -> Result<()> {
+    #[version("0.2", preserve_unknown)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    pub struct Test {
+        pub a: i32,
+        pub b: i32,
+        unknown_fields: UnknownFields,
+    }
+
+    let value: Test = serde_json::from_str(r#"{"$version":"0.1","a":1,"x":"y"}"#)?;
+    assert!(value.unknown_fields.contains_key("x"));
+    assert_eq!(
+        serde_json::to_string(&value)?,
+        r#"{"$version":"0.2","a":1,"b":0,"x":"y"}"#
+    );
+
+    Ok(())
+}
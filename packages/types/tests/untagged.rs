@@ -0,0 +1,167 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn untagged_default_version() -> Result<()> {
+    #[version("0.3", untagged_default = "0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    #[migration("0.2" => "0.3" {
+        a => c: i32,
+    })]
+    struct Test {
+        b: i32,
+        c: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)?,
+        Test { b: 0, c: 1 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.3","b":2,"c":1}"#)?,
+        Test { b: 2, c: 1 }
+    );
+    assert!(serde_json::from_str::<Test>(r#"{"b":2,"c":1}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn untagged_default_equivalent_version() -> Result<()> {
+    #[version("0.2", untagged_default = "0.1.0")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)?,
+        Test { a: 1, b: 0 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn untagged_adjacent() -> Result<()> {
+    #[version("0.2", repr = "adjacent", untagged_default = "0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)?,
+        Test { a: 1, b: 0 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"$version":"0.2","data":{"a":1,"b":2}}"#)?,
+        Test { a: 1, b: 2 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn untagged_enum() -> Result<()> {
+    #[version("0.2", untagged_default = "0.1")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        A => B,
+    })]
+    enum Test {
+        B,
+    }
+
+    assert_eq!(serde_json::from_str::<Test>(r#""A""#)?, Test::B);
+
+    Ok(())
+}
+
+#[test]
+fn untagged_structural() -> Result<()> {
+    #[version("0.3", untagged = "structural")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        a: i32 => b: String { a.to_string() },
+    })]
+    #[migration("0.2" => "0.3" {
+        + c: i32 { 3 },
+    })]
+    struct Test {
+        b: String,
+        c: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)?,
+        Test {
+            b: "1".to_string(),
+            c: 3
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"b":"2"}"#)?,
+        Test {
+            b: "2".to_string(),
+            c: 3
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"b":"2","c":4}"#)?,
+        Test {
+            b: "2".to_string(),
+            c: 4
+        }
+    );
+    assert!(serde_json::from_str::<Test>(r#"{"d":1}"#)
+        .unwrap_err()
+        .to_string()
+        .contains("The untagged data matches no version of Test"));
+
+    Ok(())
+}
+
+#[test]
+fn untagged_structural_shortcut() -> Result<()> {
+    #[version("0.3", untagged = "structural")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.3" {
+        + b: i32 { -1 },
+        + c: i32 { -1 },
+    })]
+    #[migration("0.2" => "0.2.5" {
+        + d: i32 { -2 },
+    })]
+    #[migration("0.2.5" => "0.3" {
+        d => c: i32,
+    })]
+    struct Test {
+        a: i32,
+        b: i32,
+        c: i32,
+    }
+
+    // The shortcut gives "0.1" a shorter route than "0.2", which is still tried first
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1,"b":2}"#)?,
+        Test { a: 1, b: 2, c: -2 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Test>(r#"{"a":1}"#)?,
+        Test { a: 1, b: -1, c: -1 }
+    );
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[test]
+fn validate_hook() -> Result<()> {
+    fn check(value: &Range) -> Result<(), String> {
+        if value.end < value.start {
+            return Err(format!("{} ends before {}", value.end, value.start));
+        }
+        Ok(())
+    }
+
+    #[version("0.2", validate = check)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + start: i32,
+    })]
+    struct Range {
+        start: i32,
+        end: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Range>(r#"{"$version":"0.1","end":3}"#)?,
+        Range { start: 0, end: 3 }
+    );
+    assert_eq!(
+        serde_json::from_str::<Range>(r#"{"$version":"0.1","end":-1}"#)
+            .unwrap_err()
+            .to_string(),
+        "Invalid Range read as version 0.1: -1 ends before 0"
+    );
+    assert_eq!(
+        serde_json::from_str::<Range>(r#"{"$version":"0.2","start":2,"end":1}"#)
+            .unwrap_err()
+            .to_string(),
+        "Invalid Range read as version 0.2: 1 ends before 2"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn post_blocks() -> Result<()> {
+    #[version("0.3")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + name: String,
+        post { !name.is_empty() },
+    })]
+    #[migration("0.2" => "0.3" {
+        + start: i32,
+        post {
+            if end < start {
+                *end = *start;
+            }
+        },
+    })]
+    struct Task {
+        name: String,
+        start: i32,
+        end: i32,
+    }
+
+    assert_eq!(
+        serde_json::from_str::<Task>(r#"{"$version":"0.2","name":"a","end":-1}"#)?,
+        Task {
+            name: "a".to_string(),
+            start: 0,
+            end: 0,
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Task>(r#"{"$version":"0.1","end":1}"#)
+            .unwrap_err()
+            .to_string(),
+        "Invalid Task read as version 0.1: validation failed"
+    );
+    // Data already written as 0.2 does not pass the first hop
+    assert_eq!(
+        serde_json::from_str::<Task>(r#"{"$version":"0.2","name":"","end":1}"#)?,
+        Task {
+            name: "".to_string(),
+            start: 0,
+            end: 1,
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Task>(r#"{"$version":"0.3","name":"","start":2,"end":1}"#)?,
+        Task {
+            name: "".to_string(),
+            start: 2,
+            end: 1,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn post_block_with_result() -> Result<()> {
+    #[version("0.2", untagged = "structural")]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + 1: u8 { 255 },
+        post {
+            match _0 {
+                0 => Err("the red channel is empty"),
+                _ => Ok(()),
+            }
+        },
+    })]
+    struct Color(u8, u8);
+
+    assert_eq!(serde_json::from_str::<Color>(r#"{"0":1}"#)?, Color(1, 255));
+    assert_eq!(
+        serde_json::from_str::<Color>(r#"{"0":0}"#)
+            .unwrap_err()
+            .to_string(),
+        "Invalid Color read as version 0.1: the red channel is empty"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn validate_enum_envelope() -> Result<()> {
+    fn positive(value: &Shape) -> bool {
+        match value {
+            Shape::Circle(radius) => *radius > 0,
+            Shape::Point => true,
+        }
+    }
+
+    #[version("0.1", repr = "envelope", validate = positive)]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Shape {
+        Point,
+        Circle(i32),
+    }
+
+    assert_eq!(
+        postcard::from_bytes::<Shape>(&postcard::to_allocvec(&Shape::Circle(1))?)?,
+        Shape::Circle(1)
+    );
+    assert!(postcard::from_bytes::<Shape>(&postcard::to_allocvec(&Shape::Circle(0))?).is_err());
+    assert!(serde_json::from_str::<Shape>(r#"["0.1",{"Circle":0}]"#)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid Shape read as version 0.1: validation failed"));
+
+    Ok(())
+}
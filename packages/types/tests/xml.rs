@@ -0,0 +1,141 @@
+use anyhow::Result;
+use hifumi::version;
+
+#[version("0.2", repr = "xml")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + id: u32,
+    title => name: String,
+})]
+struct Book {
+    #[serde(rename = "@id")]
+    id: u32,
+    name: String,
+    pages: i32,
+    tags: Vec<String>,
+}
+
+#[version("0.2", repr = "xml", tag = "version")]
+#[derive(Debug, Clone, PartialEq)]
+#[migration("0.1" => "0.2" {
+    + Circle,
+    Square => Rect,
+})]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect { w: u32, h: u32 },
+}
+
+#[test]
+fn xml_struct() -> Result<()> {
+    let value = Book {
+        id: 7,
+        name: "Hifumi".to_string(),
+        pages: 120,
+        tags: vec!["a".to_string(), "b".to_string()],
+    };
+    let xml = quick_xml::se::to_string(&value)?;
+    assert_eq!(
+        xml,
+        r#"<Book xmlns="0.2" id="7"><name>Hifumi</name><pages>120</pages><tags>a</tags><tags>b</tags></Book>"#
+    );
+    assert_eq!(quick_xml::de::from_str::<Book>(&xml)?, value);
+
+    // Attributes in front of the version are read like the others
+    assert_eq!(
+        quick_xml::de::from_str::<Book>(
+            r#"<Book id="7" xmlns="0.2"><name>Hifumi</name><pages>120</pages><tags>a</tags><tags>b</tags></Book>"#
+        )?,
+        value
+    );
+
+    assert_eq!(
+        quick_xml::de::from_str::<Book>(
+            r#"<Book xmlns="0.1"><pages>64</pages><tags>c</tags><title>Old</title></Book>"#
+        )?,
+        Book {
+            id: 0,
+            name: "Old".to_string(),
+            pages: 64,
+            tags: vec!["c".to_string()],
+        }
+    );
+
+    let mut old = String::new();
+    value.serialize_as_version("0.1", quick_xml::se::Serializer::new(&mut old))?;
+    assert_eq!(
+        old,
+        r#"<Book xmlns="0.1"><pages>120</pages><tags>a</tags><tags>b</tags><title>Hifumi</title></Book>"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn xml_enum() -> Result<()> {
+    for value in [Shape::Empty, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }] {
+        let xml = quick_xml::se::to_string(&value)?;
+        assert!(xml.starts_with(r#"<Shape version="0.2">"#));
+        assert_eq!(quick_xml::de::from_str::<Shape>(&xml)?, value);
+    }
+    assert_eq!(
+        quick_xml::se::to_string(&Shape::Rect { w: 2, h: 3 })?,
+        r#"<Shape version="0.2"><Rect><h>3</h><w>2</w></Rect></Shape>"#
+    );
+
+    assert_eq!(
+        quick_xml::de::from_str::<Shape>(
+            r#"<Shape version="0.1"><Square><h>1</h><w>1</w></Square></Shape>"#
+        )?,
+        Shape::Rect { w: 1, h: 1 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn xml_errors() -> Result<()> {
+    assert_eq!(
+        quick_xml::de::from_str::<Book>(r#"<Book id="1"><name>x</name></Book>"#)
+            .unwrap_err()
+            .to_string(),
+        "The version attribute `xmlns` is missing"
+    );
+    assert!(quick_xml::de::from_str::<Book>(
+        r#"<Book xmlns="0.3"><name>x</name><pages>1</pages></Book>"#
+    )
+    .unwrap_err()
+    .to_string()
+    .contains("No version of Book is compatible with 0.3"));
+
+    Ok(())
+}
+
+#[test]
+fn xml_nested() -> Result<()> {
+    #[version("0.1", repr = "xml")]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Library {
+        book: Book,
+        shelf: i32,
+    }
+
+    let value = Library {
+        book: Book {
+            id: 1,
+            name: "x".to_string(),
+            pages: 2,
+            tags: vec!["y".to_string()],
+        },
+        shelf: 3,
+    };
+    let xml = quick_xml::se::to_string(&value)?;
+    assert_eq!(
+        xml,
+        r#"<Library xmlns="0.1"><book xmlns="0.2" id="1"><name>x</name><pages>2</pages><tags>y</tags></book><shelf>3</shelf></Library>"#
+    );
+    assert_eq!(quick_xml::de::from_str::<Library>(&xml)?, value);
+
+    Ok(())
+}
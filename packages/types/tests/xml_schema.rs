@@ -0,0 +1,261 @@
+use anyhow::Result;
+use hifumi::version;
+use std::{path::Path, process::Command};
+
+/// Validates `xml` against `xsd` with `xmllint`, or returns `false` if it is not
+/// installed.
+fn validate(dir: &Path, xsd: &str, xml: &str) -> Result<bool> {
+    std::fs::create_dir_all(dir)?;
+    let xsd_path = dir.join("schema.xsd");
+    let xml_path = dir.join("value.xml");
+    std::fs::write(&xsd_path, xsd)?;
+    std::fs::write(&xml_path, xml)?;
+
+    let output = match Command::new("xmllint")
+        .arg("--noout")
+        .arg("--schema")
+        .arg(&xsd_path)
+        .arg(&xml_path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error.into()),
+    };
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        xml,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(true)
+}
+
+#[test]
+fn xml_schema_per_version() -> Result<()> {
+    #[version("0.2", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + id: u64,
+        - legacy: bool,
+    })]
+    struct User {
+        #[serde(rename = "@id")]
+        id: u64,
+        name: String,
+        #[serde(default)]
+        age: i32,
+        nickname: Option<String>,
+        tags: Vec<String>,
+        extra: Box<[u8; 4]>,
+    }
+
+    let schemas = User::xml_schemas();
+    assert_eq!(
+        schemas
+            .iter()
+            .map(|(version, _)| *version)
+            .collect::<Vec<_>>(),
+        vec!["0.2", "0.1"]
+    );
+    assert_eq!(
+        schemas[0].1,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" targetNamespace="0.2" xmlns="0.2" elementFormDefault="qualified">
+  <xs:element name="User">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="age" type="xs:int" minOccurs="0"/>
+        <xs:element name="extra" type="xs:anyType"/>
+        <xs:element name="name" type="xs:string"/>
+        <xs:element name="nickname" type="xs:string" minOccurs="0"/>
+        <xs:element name="tags" type="xs:string" minOccurs="0" maxOccurs="unbounded"/>
+      </xs:sequence>
+      <xs:attribute name="id" type="xs:unsignedLong" use="required"/>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>
+"#
+    );
+    assert!(schemas[1].1.contains(r#"targetNamespace="0.1""#));
+    assert!(schemas[1]
+        .1
+        .contains(r#"<xs:element name="legacy" type="xs:boolean"/>"#));
+    assert!(!schemas[1].1.contains(r#"name="id""#));
+
+    Ok(())
+}
+
+#[test]
+fn xml_schema_enum() -> Result<()> {
+    #[version("0.1", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    assert_eq!(
+        Shape::xml_schemas()[0].1,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" targetNamespace="0.1" xmlns="0.1" elementFormDefault="qualified">
+  <xs:element name="Shape">
+    <xs:complexType>
+      <xs:choice>
+        <xs:element name="Circle" type="xs:double"/>
+        <xs:element name="Empty">
+          <xs:complexType/>
+        </xs:element>
+        <xs:element name="Rect">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="h" type="xs:unsignedInt"/>
+              <xs:element name="w" type="xs:unsignedInt"/>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+      </xs:choice>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>
+"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_xml_schemas() -> Result<()> {
+    #[version("0.2", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + b: String,
+    })]
+    struct Test {
+        a: i32,
+        b: String,
+    }
+
+    let dir = std::env::temp_dir().join(format!("hifumi-xsd-{}", std::process::id()));
+    let paths = Test::write_xml_schemas(&dir)?;
+    assert_eq!(
+        paths,
+        vec![dir.join("Test-0.2.xsd"), dir.join("Test-0.1.xsd")]
+    );
+    assert_eq!(
+        std::fs::read_to_string(&paths[1])?,
+        Test::xml_schemas()[1].1
+    );
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[test]
+fn xml_schema_tag() -> Result<()> {
+    #[version("0.2", repr = "xml", tag = "schemaVersion", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + id: String,
+    })]
+    struct Item {
+        #[serde(rename = "@id")]
+        id: String,
+        name: String,
+    }
+
+    // Without a namespace, the version is an attribute the root element declares
+    assert_eq!(
+        Item::xml_schemas()[0].1,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="Item">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="name" type="xs:string"/>
+      </xs:sequence>
+      <xs:attribute name="id" type="xs:string" use="required"/>
+      <xs:attribute name="schemaVersion" type="xs:string" fixed="0.2" use="required"/>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>
+"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn xml_schema_validates_output() -> Result<()> {
+    #[version("0.2", repr = "xml", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + id: String,
+    })]
+    struct Item {
+        #[serde(rename = "@id")]
+        id: String,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[version("0.2", repr = "xml", tag = "schemaVersion", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[migration("0.1" => "0.2" {
+        + id: String,
+    })]
+    struct Tagged {
+        #[serde(rename = "@id")]
+        id: String,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[version("0.1", repr = "xml", tag = "schemaVersion", xml_schema)]
+    #[derive(Debug, Clone, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    let dir = std::env::temp_dir().join(format!("hifumi-xsd-validate-{}", std::process::id()));
+    let documents = [
+        (
+            Item::xml_schemas()[0].1,
+            quick_xml::se::to_string(&Item {
+                id: "q".to_string(),
+                name: "a".to_string(),
+                tags: vec!["b".to_string()],
+            })?,
+        ),
+        (
+            Tagged::xml_schemas()[0].1,
+            quick_xml::se::to_string(&Tagged {
+                id: "q".to_string(),
+                name: "a".to_string(),
+                tags: vec!["b".to_string()],
+            })?,
+        ),
+        (
+            Shape::xml_schemas()[0].1,
+            quick_xml::se::to_string(&Shape::Rect { w: 1, h: 2 })?,
+        ),
+        (
+            Shape::xml_schemas()[0].1,
+            quick_xml::se::to_string(&Shape::Circle(1.5))?,
+        ),
+    ];
+
+    let mut validated = true;
+    for (xsd, xml) in documents.iter() {
+        validated &= validate(&dir, xsd, xml)?;
+    }
+    std::fs::remove_dir_all(&dir)?;
+    if !validated {
+        eprintln!("xmllint is not installed, the output was not validated");
+    }
+
+    Ok(())
+}